@@ -1,12 +1,46 @@
-use utoipa::OpenApi;
+use utoipa::{
+    openapi::{ObjectBuilder, SchemaType},
+    Modify, OpenApi,
+};
+
+use crate::models::{depth_history_model, earnings_model, rpmuh_model, swap_history_model};
 
 #[derive(OpenApi)]
 #[openapi(
     paths(
         crate::routes::depth_route::get_pool_depth_price_history,
         crate::routes::earnings_route::get_pool_earnings_api,
+        crate::routes::earnings_route::get_earnings_summary,
         crate::routes::rpmuh_route::get_rune_pool_history,
-        crate::routes::swaps_route::get_pool_swap_history
+        crate::routes::swaps_route::get_pool_swap_history,
+        crate::routes::midgard_route::get_midgard_swaps_history,
+        crate::routes::midgard_route::get_midgard_depths_history,
+        crate::routes::midgard_route::get_midgard_earnings_history,
+        crate::routes::midgard_route::get_midgard_rune_pool_history,
+        crate::routes::export_route::export_dataset,
+        crate::routes::export_route::export_parquet,
+        crate::routes::analytics_route::get_pool_apy,
+        crate::routes::analytics_route::get_pool_fees,
+        crate::routes::analytics_route::get_lp_performance,
+        crate::routes::analytics_route::get_rolling,
+        crate::routes::candles_route::get_candles,
+        crate::routes::quote_route::get_quote,
+        crate::routes::compare_route::get_comparison,
+        crate::routes::leaderboard_route::get_leaderboard,
+        crate::routes::snapshot_route::get_snapshot,
+        crate::routes::rune_price_route::get_rune_price,
+        crate::routes::anomalies_route::get_anomalies,
+        crate::routes::alerts_route::create_alert_rule,
+        crate::routes::alerts_route::list_alert_rules,
+        crate::routes::alerts_route::get_alert_rule,
+        crate::routes::alerts_route::update_alert_rule,
+        crate::routes::alerts_route::delete_alert_rule,
+        crate::routes::alerts_route::list_alert_deliveries,
+        crate::routes::stream_route::stream_events,
+        crate::routes::stream_route::stream_websocket,
+        crate::routes::graphql_route::graphql_query,
+        crate::routes::graphql_route::graphql_explorer,
+        crate::routes::graphql_route::graphql_schema
     ),
     components(
         schemas(
@@ -14,17 +48,139 @@ use utoipa::OpenApi;
             crate::routes::depth_route::PoolDepthPriceHistoryResponse, 
             crate::routes::earnings_route::EarningsQueryParams,
             crate::routes::earnings_route::PoolEarningsResponse,
+            crate::routes::earnings_route::EarningsSummaryQueryParams,
+            crate::routes::earnings_route::EarningsSummaryResponse,
             crate::routes::rpmuh_route::RunePoolHistoryQueryParams,
             crate::routes::rpmuh_route::RunePoolHistoryResponse,
             crate::routes::swaps_route::SwapHistoryQueryParams,
-            crate::routes::swaps_route::PoolSwapHistoryResponse
+            crate::routes::swaps_route::PoolSwapHistoryResponse,
+            crate::routes::midgard_route::MidgardHistoryQueryParams,
+            crate::routes::export_route::ExportQueryParams,
+            crate::routes::export_route::ParquetQueryParams,
+            crate::routes::analytics_route::ApyQueryParams,
+            crate::services::analytics::PoolYield,
+            crate::routes::analytics_route::FeesQueryParams,
+            crate::services::analytics::FeeAmount,
+            crate::services::analytics::PoolFees,
+            crate::routes::analytics_route::LpQueryParams,
+            crate::services::lp_calculator::LpPoint,
+            crate::services::lp_calculator::LpPerformance,
+            crate::routes::analytics_route::RollingQueryParams,
+            crate::routes::candles_route::CandlesQueryParams,
+            crate::services::analytics::Candle,
+            crate::routes::quote_route::QuoteQueryParams,
+            crate::services::quote::QuoteLeg,
+            crate::services::quote::Quote,
+            crate::routes::compare_route::CompareQueryParams,
+            crate::services::compare::MetricChange,
+            crate::services::compare::PoolComparison,
+            crate::services::compare::Period,
+            crate::services::compare::Comparison,
+            crate::routes::leaderboard_route::LeaderboardQueryParams,
+            crate::routes::leaderboard_route::LeaderboardEntryResponse,
+            crate::routes::snapshot_route::SnapshotQueryParams,
+            crate::routes::snapshot_route::SnapshotResponse,
+            crate::routes::rune_price_route::RunePriceQueryParams,
+            crate::services::analytics::RunePriceCandle,
+            crate::routes::anomalies_route::AnomaliesQueryParams,
+            crate::routes::anomalies_route::AnomalyResponse,
+            crate::routes::alerts_route::AlertRuleParams,
+            crate::routes::alerts_route::AlertSecretQueryParams,
+            crate::routes::alerts_route::AlertDeliveriesQueryParams,
+            crate::services::alerts::AlertRuleView,
+            crate::services::alerts::AlertDeliveryView,
+            crate::routes::stream_route::StreamQueryParams,
+            crate::services::live_feed::LiveEvent,
+            crate::routes::graphql_route::GraphQLRequest,
+            crate::routes::graphql_route::GraphQLResponse
             )
     ),
     tags(
         (name = "Depth and Price History", description = "Endpoint to get depth and price history"),
         (name = "Earnings History", description = "Endpoint to get earnings history"),
         (name = "Rune Pool History", description = "Endpoint to get RunePool total members and units history"),
-        (name = "Swaps History", description = "Endpoint to get swaps history")
-    )
+        (name = "Swaps History", description = "Endpoint to get swaps history"),
+        (name = "Midgard Compatible", description = "Midgard v2 history endpoints answered from the stored collections"),
+        (name = "Export", description = "Endpoints to stream whole datasets as CSV or NDJSON and download them as Parquet"),
+        (name = "Analytics", description = "Endpoints deriving metrics from the stored history"),
+        (name = "Candles", description = "Endpoint to get OHLC price candles for charting"),
+        (name = "Quote", description = "Endpoint to estimate swaps from the stored pool depths"),
+        (name = "Compare", description = "Endpoint to compare a period with a previous one"),
+        (name = "Leaderboard", description = "Endpoint to rank pools by a metric over a period"),
+        (name = "Snapshot", description = "Endpoint to get the state of the pools at a point in time"),
+        (name = "RUNE Price", description = "Endpoint to get the RUNE price history in USD"),
+        (name = "Anomalies", description = "Endpoint to get intervals that moved far outside their recent distribution"),
+        (name = "Alerts", description = "Endpoints to manage alert rules delivered to webhooks and read their delivery log"),
+        (name = "Stream", description = "Endpoints to subscribe to closed intervals as the hourly ingestion inserts them"),
+        (name = "GraphQL", description = "Endpoint to query every dataset in one request with GraphQL")
+    ),
+    modifiers(&SortFields)
 )]
-pub struct ApiDoc;
\ No newline at end of file
+pub struct ApiDoc;
+
+/// Lists the fields each history endpoint accepts in `sort_by`, built from the model's `FIELDS` so
+/// the documentation cannot drift from the validation
+struct SortFields;
+
+impl Modify for SortFields {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let endpoints: [(&str, Vec<&str>); 4] = [
+            (
+                "/depths",
+                depth_history_model::FIELDS.iter().chain(depth_history_model::DERIVED_FIELDS).copied().collect(),
+            ),
+            ("/swaps", swap_history_model::FIELDS.to_vec()),
+            ("/earnings", earnings_model::FIELDS.to_vec()),
+            ("/runepool", rpmuh_model::FIELDS.to_vec()),
+        ];
+
+        for (path, fields) in endpoints {
+            let Some(item) = openapi.paths.paths.get_mut(path) else {
+                continue;
+            };
+
+            let parameters = item
+                .operations
+                .values_mut()
+                .filter_map(|operation| operation.parameters.as_mut())
+                .flatten()
+                .filter(|parameter| parameter.name == "sort_by");
+
+            for parameter in parameters {
+                let description = parameter.description.get_or_insert_with(String::new);
+                description.push_str(&format!(" Allowed fields: {}", fields.join(", ")));
+
+                let field = format!("[-+]?({})", fields.join("|"));
+                parameter.schema = Some(
+                    ObjectBuilder::new()
+                        .schema_type(SchemaType::String)
+                        .pattern(Some(format!("^{}(,{})*$", field, field)))
+                        .into(),
+                );
+            }
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sort_by_lists_the_model_fields() {
+        let openapi = ApiDoc::openapi();
+        let parameter = openapi.paths.paths["/depths"]
+            .operations
+            .values()
+            .flat_map(|operation| operation.parameters.iter().flatten())
+            .find(|parameter| parameter.name == "sort_by")
+            .unwrap();
+
+        let description = parameter.description.as_deref().unwrap();
+        for field in depth_history_model::FIELDS.iter().chain(depth_history_model::DERIVED_FIELDS) {
+            assert!(description.contains(field), "{} is not listed", field);
+        }
+
+        let schema = serde_json::to_value(&parameter.schema).unwrap();
+        assert!(schema["pattern"].as_str().unwrap().contains("|tvl_usd|"));
+    }
+}
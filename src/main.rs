@@ -8,16 +8,37 @@ mod api_doc;
 use actix_web::web::Data;
 use actix_web::{get, App, HttpResponse, HttpServer, Responder};
 use api_doc::ApiDoc;
+use routes::analytics_route::{get_lp_performance, get_pool_apy, get_pool_fees, get_rolling};
+use routes::candles_route::get_candles;
+use routes::compare_route::get_comparison;
 use routes::depth_route::get_pool_depth_price_history;
 use routes::depths_scraper::fetch_and_store_depth;
 use routes::earning_scraper:: fetch_and_store_earnings;
-use routes::earnings_route::get_pool_earnings_api;
+use routes::earnings_route::{get_earnings_summary, get_pool_earnings_api};
+use routes::export_route::{export_dataset, export_parquet};
+use routes::leaderboard_route::get_leaderboard;
+use routes::midgard_route::{
+    get_midgard_depths_history, get_midgard_earnings_history, get_midgard_rune_pool_history,
+    get_midgard_swaps_history,
+};
+use routes::quote_route::get_quote;
 use routes::rpmuh_route::get_rune_pool_history;
 use routes::rune_pool_scraper::fetch_and_store_rune_pool;
 use routes::scraper_cron::run_all_jobs;
+use routes::snapshot_route::get_snapshot;
+use routes::rune_price_route::get_rune_price;
+use routes::anomalies_route::get_anomalies;
+use routes::stream_route::{stream_events, stream_websocket};
+use routes::graphql_route::{graphql_explorer, graphql_query, graphql_schema};
+use routes::alerts_route::{
+    create_alert_rule, delete_alert_rule, get_alert_rule, list_alert_deliveries, list_alert_rules, update_alert_rule,
+};
 use routes::swaps_route::get_pool_swap_history;
 use routes::swaps_scraper::fetch_and_store_swaps;
-use services::{db::Database, fetch_all_cron::run_cron_job};
+use services::{
+    db::Database, fetch_all_cron::run_cron_job, graphql::build_schema, midgard_import::run_import,
+    parquet_export::run_batch_export,
+};
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
@@ -29,21 +50,67 @@ async fn hello() -> impl Responder {
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     let db = Database::init().await;
+
+    // `export-parquet [out_dir] [dataset...]` writes the partitioned Parquet files and exits
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("export-parquet") {
+        let out_dir = args.get(2).cloned().unwrap_or_else(|| "parquet".to_string());
+        run_batch_export(&db, &out_dir, args.get(3..).unwrap_or_default()).await;
+        return Ok(());
+    }
+
+    // `import-midgard <kind> [--pool <pool>] <file or dir>...` stores saved Midgard responses and exits
+    if args.get(1).map(String::as_str) == Some("import-midgard") {
+        run_import(&db, args.get(2..).unwrap_or_default()).await;
+        return Ok(());
+    }
+
     let db_data = Data::new(db);
+    let schema = Data::new(build_schema(db_data.clone()));
     actix_web::rt::spawn(run_cron_job(db_data.clone(), "BTC.BTC".to_string()));
     HttpServer::new(move || {
         App::new()
             .app_data(db_data.clone())
+            .app_data(schema.clone())
             .service(hello)
             .service(get_pool_depth_price_history)
             .service(get_pool_swap_history)
             .service(get_rune_pool_history)
             .service(get_pool_earnings_api)
+            .service(get_earnings_summary)
             .service(fetch_and_store_earnings)
             .service(fetch_and_store_swaps)
             .service(fetch_and_store_depth)
             .service(fetch_and_store_rune_pool)
             .service(run_all_jobs)
+            .service(get_midgard_swaps_history)
+            .service(get_midgard_depths_history)
+            .service(get_midgard_earnings_history)
+            .service(get_midgard_rune_pool_history)
+            .service(export_dataset)
+            .service(export_parquet)
+            .service(get_pool_apy)
+            .service(get_pool_fees)
+            .service(get_lp_performance)
+            .service(get_rolling)
+            .service(get_candles)
+            .service(get_quote)
+            .service(get_comparison)
+            .service(get_leaderboard)
+            .service(get_snapshot)
+            .service(get_rune_price)
+            .service(get_anomalies)
+            .service(create_alert_rule)
+            .service(list_alert_rules)
+            .service(get_alert_rule)
+            .service(update_alert_rule)
+            .service(delete_alert_rule)
+            .service(list_alert_deliveries)
+            .service(stream_events)
+            .service(stream_websocket)
+            .service(graphql_query)
+            .service(graphql_explorer)
+            .service(graphql_schema)
             .service(
                 SwaggerUi::new("/docs/{_:.*}")
                     .url("/api-docs/openapi.json", ApiDoc::openapi()),
@@ -0,0 +1,104 @@
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertRule {
+    pub _id: ObjectId,
+    pub name: String,
+    pub kind: String,
+    pub dataset: String,
+    pub pool: Option<String>,
+    pub metric: Option<String>,
+    pub operator: String,
+    pub threshold: f64,
+    pub window: Option<i64>,
+    pub webhook_url: String,
+    pub enabled: bool,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AlertRuleRequest {
+    pub name: String,
+    pub kind: String,
+    pub dataset: String,
+    pub pool: Option<String>,
+    pub metric: Option<String>,
+    pub operator: String,
+    pub threshold: f64,
+    pub window: Option<i64>,
+    pub webhook_url: String,
+    pub enabled: bool,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+impl TryFrom<AlertRuleRequest> for AlertRule {
+    type Error = Box<dyn std::error::Error>;
+
+    fn try_from(item: AlertRuleRequest) -> Result<Self, Self::Error> {
+        Ok(Self {
+            _id: ObjectId::new(),
+            name: item.name,
+            kind: item.kind,
+            dataset: item.dataset,
+            pool: item.pool,
+            metric: item.metric,
+            operator: item.operator,
+            threshold: item.threshold,
+            window: item.window,
+            webhook_url: item.webhook_url,
+            enabled: item.enabled,
+            created_at: item.created_at,
+            updated_at: item.updated_at,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AlertDelivery {
+    pub _id: ObjectId,
+    pub rule_id: ObjectId,
+    pub event_key: String,
+    pub value: f64,
+    pub webhook_url: String,
+    pub status: String,
+    pub attempts: i64,
+    pub response_status: Option<i64>,
+    pub error: Option<String>,
+    pub fired_at: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AlertDeliveryRequest {
+    pub rule_id: ObjectId,
+    pub event_key: String,
+    pub value: f64,
+    pub webhook_url: String,
+    pub status: String,
+    pub attempts: i64,
+    pub response_status: Option<i64>,
+    pub error: Option<String>,
+    pub fired_at: i64,
+}
+
+impl TryFrom<AlertDeliveryRequest> for AlertDelivery {
+    type Error = Box<dyn std::error::Error>;
+
+    fn try_from(item: AlertDeliveryRequest) -> Result<Self, Self::Error> {
+        Ok(Self {
+            _id: ObjectId::new(),
+            rule_id: item.rule_id,
+            event_key: item.event_key,
+            value: item.value,
+            webhook_url: item.webhook_url,
+            status: item.status,
+            attempts: item.attempts,
+            response_status: item.response_status,
+            error: item.error,
+            fired_at: item.fired_at,
+        })
+    }
+}
@@ -0,0 +1,55 @@
+use mongodb::bson::oid::ObjectId;
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Anomaly {
+    pub _id: ObjectId,
+    pub dataset: String,
+    pub pool: String,
+    pub metric: String,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub value: f64,
+    pub mean: f64,
+    pub std_dev: f64,
+    pub z_score: f64,
+    pub samples: i64,
+    pub detected_at: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AnomalyRequest {
+    pub dataset: String,
+    pub pool: String,
+    pub metric: String,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub value: f64,
+    pub mean: f64,
+    pub std_dev: f64,
+    pub z_score: f64,
+    pub samples: i64,
+    pub detected_at: i64,
+}
+
+impl TryFrom<AnomalyRequest> for Anomaly {
+    type Error = Box<dyn std::error::Error>;
+
+    fn try_from(item: AnomalyRequest) -> Result<Self, Self::Error> {
+        Ok(Self {
+            _id: ObjectId::new(),
+            dataset: item.dataset,
+            pool: item.pool,
+            metric: item.metric,
+            start_time: item.start_time,
+            end_time: item.end_time,
+            value: item.value,
+            mean: item.mean,
+            std_dev: item.std_dev,
+            z_score: item.z_score,
+            samples: item.samples,
+            detected_at: item.detected_at,
+        })
+    }
+}
@@ -2,6 +2,18 @@ use mongodb::bson::oid::ObjectId;
 use serde::{Deserialize, Serialize};
 use std::convert::TryFrom;
 
+/// Fields exposed by the query endpoints, used to validate `sort_by` and `fields`
+pub const FIELDS: &[&str] = &[
+    "pool", "asset_depth", "asset_price", "asset_price_usd", "start_time", "end_time",
+    "liquidity_units", "luvi", "members_count", "rune_depth", "synth_supply", "synth_units",
+    "units",
+];
+
+/// Fields computed from a depth row on request, accepted by `sort_by`, `fields` and `filter` as well
+pub const DERIVED_FIELDS: &[&str] = &[
+    "tvl_usd", "synth_utilization", "implied_rune_price_usd", "units_per_member",
+];
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PoolDepthPriceHistory {
     pub _id: ObjectId,
@@ -2,6 +2,12 @@ use mongodb::bson::oid::ObjectId;
 use serde::{Deserialize, Serialize};
 use std::convert::TryFrom;
 
+/// Fields merged into each earnings row with `summary=true`, accepted by `fields` in that case
+pub const MERGED_FIELDS: &[&str] = &[
+    "block_rewards", "avg_node_count", "bonding_earnings", "liquidity_earnings", "liquidity_fees",
+    "rune_price_usd",
+];
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct EarningsSummary {
     pub _id: ObjectId,
@@ -2,6 +2,12 @@ use mongodb::bson::oid::ObjectId;
 use serde::{Deserialize, Serialize};
 use std::convert::TryFrom;
 
+/// Fields exposed by the query endpoints, used to validate `sort_by` and `fields`
+pub const FIELDS: &[&str] = &[
+    "pool", "asset_liquidity_fees", "rune_liquidity_fees", "total_liquidity_fees_rune",
+    "saver_earning", "rewards", "start_time", "end_time",
+];
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PoolEarnings {
     pub _id: ObjectId,
@@ -2,4 +2,6 @@ pub mod depth_history_model;
 pub mod earnings_model;
 pub mod rpmuh_model;
 pub mod swap_history_model;
-pub mod earning_summary_model;
\ No newline at end of file
+pub mod earning_summary_model;
+pub mod anomaly_model;
+pub mod alert_model;
\ No newline at end of file
@@ -2,6 +2,11 @@ use mongodb::bson::oid::ObjectId;
 use serde::{Deserialize, Serialize};
 use std::convert::TryFrom;
 
+/// Fields exposed by the query endpoints, used to validate `sort_by` and `fields`
+pub const FIELDS: &[&str] = &[
+    "start_time", "end_time", "count", "units",
+];
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RunePoolHistory {
     pub _id: ObjectId,
@@ -2,6 +2,20 @@ use mongodb::bson::oid::ObjectId;
 use serde::{Deserialize, Serialize};
 use std::convert::TryFrom;
 
+/// Fields exposed by the query endpoints, used to validate `sort_by` and `fields`
+pub const FIELDS: &[&str] = &[
+    "pool", "start_time", "end_time", "to_asset_count", "to_rune_count", "to_trade_count",
+    "from_trade_count", "synth_mint_count", "synth_redeem_count", "total_count",
+    "to_asset_volume", "to_rune_volume", "to_trade_volume", "from_trade_volume",
+    "synth_mint_volume", "synth_redeem_volume", "total_volume", "to_asset_volume_usd",
+    "to_rune_volume_usd", "to_trade_volume_usd", "from_trade_volume_usd",
+    "synth_mint_volume_usd", "synth_redeem_volume_usd", "total_volume_usd", "to_asset_fees",
+    "to_rune_fees", "to_trade_fees", "from_trade_fees", "synth_mint_fees", "synth_redeem_fees",
+    "total_fees", "to_asset_average_slip", "to_rune_average_slip", "to_trade_average_slip",
+    "from_trade_average_slip", "synth_mint_average_slip", "synth_redeem_average_slip",
+    "average_slip", "rune_price_usd",
+];
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PoolSwapHistory {
     pub _id: ObjectId,
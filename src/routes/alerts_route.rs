@@ -0,0 +1,320 @@
+use crate::models::alert_model::{AlertRule, AlertRuleRequest};
+use crate::services::{
+    alerts::{self, AlertDeliveryView, AlertRuleView},
+    db::Database,
+};
+use actix_web::{
+    delete, get, post, put,
+    web::{Data, Json, Path, Query},
+    HttpResponse,
+};
+use chrono::Utc;
+use dotenv::dotenv;
+use mongodb::bson::oid::ObjectId;
+use serde::Deserialize;
+use std::env;
+use utoipa::ToSchema;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AlertRuleParams {
+    pub secret: String,
+    #[schema(example = "BTC depth drop")]
+    pub name: String,
+    #[schema(example = "change")]
+    pub kind: String,
+    #[schema(example = "depths")]
+    pub dataset: String,
+    #[schema(example = "BTC.BTC")]
+    pub pool: Option<String>,
+    #[schema(example = "asset_depth")]
+    pub metric: Option<String>,
+    #[schema(example = "<=")]
+    pub operator: String,
+    #[schema(example = -20.0)]
+    pub threshold: f64,
+    #[schema(example = 3600)]
+    pub window: Option<i64>,
+    #[schema(example = "https://hooks.example.com/alerts")]
+    pub webhook_url: String,
+    #[schema(example = true)]
+    pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AlertSecretQueryParams {
+    pub secret: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AlertDeliveriesQueryParams {
+    pub secret: String,
+    #[schema(example = "6717c0f4a1b2c3d4e5f60718")]
+    pub rule_id: Option<String>,
+    #[schema(example = 1, minimum = 1)]
+    pub page: Option<u32>,
+    #[schema(example = 10, minimum = 1, maximum = 100)]
+    pub limit: Option<u32>,
+}
+
+// Rules hold webhook URLs, so every alert endpoint takes the same secret as the scrapers
+fn authorized(secret: &str) -> bool {
+    dotenv().ok();
+
+    let expected_secret = env::var("SECRET_KEY").unwrap_or_else(|_| "default_secret".to_string());
+    secret == expected_secret
+}
+
+fn parse_id(id: &str) -> Result<ObjectId, String> {
+    ObjectId::parse_str(id).map_err(|_| "id must be a valid rule id.".to_string())
+}
+
+fn build_rule(params: AlertRuleParams, created_at: i64) -> AlertRule {
+    AlertRule::try_from(AlertRuleRequest {
+        name: params.name,
+        kind: params.kind,
+        dataset: params.dataset,
+        pool: params.pool,
+        metric: params.metric,
+        operator: params.operator,
+        threshold: params.threshold,
+        window: params.window,
+        webhook_url: params.webhook_url,
+        enabled: params.enabled.unwrap_or(true),
+        created_at,
+        updated_at: Utc::now().timestamp(),
+    })
+    .unwrap()
+}
+
+/// Create an alert rule
+#[utoipa::path(
+    post,
+    path = "/alerts/rules",
+    request_body = AlertRuleParams,
+    responses(
+        (status = 201, description = "Created rule", body = AlertRuleView),
+        (status = 400, description = "Bad request - Invalid rule"),
+        (status = 401, description = "Wrong secret"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Alerts"
+)]
+#[post("/alerts/rules")]
+pub async fn create_alert_rule(
+    db: Data<Database>,
+    params: Json<AlertRuleParams>,
+) -> HttpResponse {
+    if !authorized(&params.secret) {
+        return HttpResponse::Unauthorized().body("No");
+    }
+
+    let rule = build_rule(params.into_inner(), Utc::now().timestamp());
+    if let Err(message) = alerts::validate_rule(&rule) {
+        return HttpResponse::BadRequest().body(message);
+    }
+
+    match db.create_alert_rule(rule.clone()).await {
+        Ok(_) => HttpResponse::Created().json(AlertRuleView::from(rule)),
+        Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
+    }
+}
+
+/// List alert rules
+#[utoipa::path(
+    get,
+    path = "/alerts/rules",
+    params(
+        ("secret" = String, Query, description = "Secret key")
+    ),
+    responses(
+        (status = 200, description = "Every rule, oldest first", body = [AlertRuleView]),
+        (status = 401, description = "Wrong secret"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Alerts"
+)]
+#[get("/alerts/rules")]
+pub async fn list_alert_rules(
+    db: Data<Database>,
+    query: Query<AlertSecretQueryParams>,
+) -> HttpResponse {
+    if !authorized(&query.secret) {
+        return HttpResponse::Unauthorized().body("No");
+    }
+
+    match db.get_alert_rules(false).await {
+        Ok(rules) => HttpResponse::Ok().json(rules.into_iter().map(AlertRuleView::from).collect::<Vec<_>>()),
+        Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
+    }
+}
+
+/// Get an alert rule
+#[utoipa::path(
+    get,
+    path = "/alerts/rules/{id}",
+    params(
+        ("id" = String, Path, description = "Rule id"),
+        ("secret" = String, Query, description = "Secret key")
+    ),
+    responses(
+        (status = 200, description = "The rule", body = AlertRuleView),
+        (status = 400, description = "Bad request - Invalid id"),
+        (status = 401, description = "Wrong secret"),
+        (status = 404, description = "No rule with this id"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Alerts"
+)]
+#[get("/alerts/rules/{id}")]
+pub async fn get_alert_rule(
+    db: Data<Database>,
+    id: Path<String>,
+    query: Query<AlertSecretQueryParams>,
+) -> HttpResponse {
+    if !authorized(&query.secret) {
+        return HttpResponse::Unauthorized().body("No");
+    }
+
+    let id = match parse_id(&id) {
+        Ok(id) => id,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+
+    match db.get_alert_rule(id).await {
+        Ok(Some(rule)) => HttpResponse::Ok().json(AlertRuleView::from(rule)),
+        Ok(None) => HttpResponse::NotFound().body("No alert rule with this id."),
+        Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
+    }
+}
+
+/// Replace an alert rule
+#[utoipa::path(
+    put,
+    path = "/alerts/rules/{id}",
+    params(
+        ("id" = String, Path, description = "Rule id")
+    ),
+    request_body = AlertRuleParams,
+    responses(
+        (status = 200, description = "Updated rule", body = AlertRuleView),
+        (status = 400, description = "Bad request - Invalid id or rule"),
+        (status = 401, description = "Wrong secret"),
+        (status = 404, description = "No rule with this id"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Alerts"
+)]
+#[put("/alerts/rules/{id}")]
+pub async fn update_alert_rule(
+    db: Data<Database>,
+    id: Path<String>,
+    params: Json<AlertRuleParams>,
+) -> HttpResponse {
+    if !authorized(&params.secret) {
+        return HttpResponse::Unauthorized().body("No");
+    }
+
+    let id = match parse_id(&id) {
+        Ok(id) => id,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+
+    let existing = match db.get_alert_rule(id).await {
+        Ok(Some(existing)) => existing,
+        Ok(None) => return HttpResponse::NotFound().body("No alert rule with this id."),
+        Err(err) => return HttpResponse::InternalServerError().body(err.to_string()),
+    };
+
+    let mut rule = build_rule(params.into_inner(), existing.created_at);
+    rule._id = id;
+
+    if let Err(message) = alerts::validate_rule(&rule) {
+        return HttpResponse::BadRequest().body(message);
+    }
+
+    match db.update_alert_rule(&rule).await {
+        Ok(true) => HttpResponse::Ok().json(AlertRuleView::from(rule)),
+        Ok(false) => HttpResponse::NotFound().body("No alert rule with this id."),
+        Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
+    }
+}
+
+/// Delete an alert rule and its delivery log
+#[utoipa::path(
+    delete,
+    path = "/alerts/rules/{id}",
+    params(
+        ("id" = String, Path, description = "Rule id"),
+        ("secret" = String, Query, description = "Secret key")
+    ),
+    responses(
+        (status = 204, description = "Rule deleted"),
+        (status = 400, description = "Bad request - Invalid id"),
+        (status = 401, description = "Wrong secret"),
+        (status = 404, description = "No rule with this id"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Alerts"
+)]
+#[delete("/alerts/rules/{id}")]
+pub async fn delete_alert_rule(
+    db: Data<Database>,
+    id: Path<String>,
+    query: Query<AlertSecretQueryParams>,
+) -> HttpResponse {
+    if !authorized(&query.secret) {
+        return HttpResponse::Unauthorized().body("No");
+    }
+
+    let id = match parse_id(&id) {
+        Ok(id) => id,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+
+    match db.delete_alert_rule(id).await {
+        Ok(true) => HttpResponse::NoContent().finish(),
+        Ok(false) => HttpResponse::NotFound().body("No alert rule with this id."),
+        Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
+    }
+}
+
+/// Get the webhook delivery log
+#[utoipa::path(
+    get,
+    path = "/alerts/deliveries",
+    params(
+        ("secret" = String, Query, description = "Secret key"),
+        ("rule_id" = Option<String>, Query, description = "Only deliveries of this rule"),
+        ("page" = Option<u32>, Query, description = "Page number (minimum: 1)"),
+        ("limit" = Option<u32>, Query, description = "Items per page (1-100)")
+    ),
+    responses(
+        (status = 200, description = "Deliveries, latest first", body = [AlertDeliveryView]),
+        (status = 400, description = "Bad request - Invalid parameters"),
+        (status = 401, description = "Wrong secret"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Alerts"
+)]
+#[get("/alerts/deliveries")]
+pub async fn list_alert_deliveries(
+    db: Data<Database>,
+    query: Query<AlertDeliveriesQueryParams>,
+) -> HttpResponse {
+    if !authorized(&query.secret) {
+        return HttpResponse::Unauthorized().body("No");
+    }
+
+    let rule_id = match query.rule_id.as_deref().map(parse_id).transpose() {
+        Ok(rule_id) => rule_id,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+
+    let limit = query.limit.unwrap_or(10).clamp(1, 100);
+    let page = query.page.unwrap_or(1).max(1);
+
+    match db.get_alert_deliveries(rule_id, page, limit).await {
+        Ok(deliveries) => HttpResponse::Ok().json(deliveries.into_iter().map(AlertDeliveryView::from).collect::<Vec<_>>()),
+        Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
+    }
+}
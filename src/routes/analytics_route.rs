@@ -0,0 +1,298 @@
+use crate::services::{
+    analytics,
+    db::{interval_duration, Database},
+    lp_calculator,
+    query_builder::parse_pools,
+};
+use actix_web::{
+    get,
+    web::{Data, Query},
+    HttpResponse,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ApyQueryParams {
+    #[schema(example = "30d")]
+    pub window: Option<String>,
+    #[schema(example = 1653373410)]
+    pub start_time: Option<i64>,
+    #[schema(example = 1666592610)]
+    pub end_time: Option<i64>,
+    #[schema(example = "BTC.BTC,ETH.ETH")]
+    pub pool: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct FeesQueryParams {
+    #[schema(example = "30d")]
+    pub window: Option<String>,
+    #[schema(example = 1653373410)]
+    pub start_time: Option<i64>,
+    #[schema(example = 1666592610)]
+    pub end_time: Option<i64>,
+    #[schema(example = "BTC.BTC,ETH.ETH")]
+    pub pool: Option<String>,
+    #[schema(example = "day")]
+    pub interval: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct LpQueryParams {
+    #[schema(example = "BTC.BTC")]
+    pub pool: String,
+    #[schema(example = 1653373410)]
+    pub deposit_time: i64,
+    #[schema(example = 10000.0)]
+    pub amount: f64,
+    #[schema(example = 1666592610)]
+    pub end_time: Option<i64>,
+    #[schema(example = "day")]
+    pub interval: Option<String>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RollingQueryParams {
+    #[schema(example = "swaps")]
+    pub dataset: String,
+    #[schema(example = "7d")]
+    pub window: Option<String>,
+    #[schema(example = "BTC.BTC,ETH.ETH")]
+    pub pool: Option<String>,
+    #[schema(example = "day")]
+    pub interval: Option<String>,
+    #[schema(example = 1653373410)]
+    pub start_time: Option<i64>,
+    #[schema(example = 1666592610)]
+    pub end_time: Option<i64>,
+    #[schema(example = 100, minimum = 1, maximum = 1000)]
+    pub limit: Option<i64>,
+}
+
+// Resolves the window into a time range, rolling windows end at end_time or now
+fn resolve_window(window: &str, start_time: Option<i64>, end_time: Option<i64>) -> Result<(i64, i64), String> {
+    let valid_window = vec!["7d", "30d", "custom"];
+    let days = match window {
+        "7d" => 7,
+        "30d" => 30,
+        "custom" => {
+            return match (start_time, end_time) {
+                (Some(start), Some(end)) if start < end => Ok((start, end)),
+                (Some(_), Some(_)) => Err("start_time must be less than end_time.".to_string()),
+                _ => Err("window=custom requires start_time and end_time.".to_string()),
+            };
+        }
+        _ => return Err(format!("window must be one of: {:?}", valid_window)),
+    };
+
+    if start_time.is_some() {
+        return Err("start_time is only supported with window=custom.".to_string());
+    }
+
+    let end = end_time.unwrap_or_else(|| Utc::now().timestamp());
+    Ok((end - days * 86400, end))
+}
+
+/// Get annualized LP and saver yield per pool
+#[utoipa::path(
+    get,
+    path = "/analytics/apy",
+    params(
+        ("window" = Option<String>, Query, description = "Window to measure over (7d, 30d or custom), defaults to 30d"),
+        ("start_time" = Option<i64>, Query, description = "Start time Unix timestamp, only with window=custom"),
+        ("end_time" = Option<i64>, Query, description = "End time Unix timestamp, defaults to now"),
+        ("pool" = Option<String>, Query, description = "Comma separated pool identifiers, all pools when omitted")
+    ),
+    responses(
+        (status = 200, description = "Yield per pool, highest LP APR first", body = [analytics::PoolYield]),
+        (status = 400, description = "Bad request - Invalid parameters"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Analytics"
+)]
+#[get("/analytics/apy")]
+pub async fn get_pool_apy(
+    db: Data<Database>,
+    query: Query<ApyQueryParams>,
+) -> HttpResponse {
+    let window = query.window.as_deref().unwrap_or("30d");
+    let (from, to) = match resolve_window(window, query.start_time, query.end_time) {
+        Ok(range) => range,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+
+    let pools = parse_pools(query.pool.as_deref());
+
+    match analytics::get_pool_yields(&db, &pools, from, to).await {
+        Ok(yields) => HttpResponse::Ok().json(yields),
+        Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
+    }
+}
+
+/// Get fee revenue per pool, swap type and period
+#[utoipa::path(
+    get,
+    path = "/analytics/fees",
+    params(
+        ("window" = Option<String>, Query, description = "Window to measure over (7d, 30d or custom), defaults to 30d"),
+        ("start_time" = Option<i64>, Query, description = "Start time Unix timestamp, only with window=custom"),
+        ("end_time" = Option<i64>, Query, description = "End time Unix timestamp, defaults to now"),
+        ("pool" = Option<String>, Query, description = "Comma separated pool identifiers, all pools when omitted"),
+        ("interval" = Option<String>, Query, description = "Split the window into periods (hour, day, week, month, quarter, year), a single period when omitted")
+    ),
+    responses(
+        (status = 200, description = "Swap fees per swap type and liquidity fees per side in RUNE and USD, with each pool's share of the protocol liquidity fees", body = [analytics::PoolFees]),
+        (status = 400, description = "Bad request - Invalid parameters"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Analytics"
+)]
+#[get("/analytics/fees")]
+pub async fn get_pool_fees(
+    db: Data<Database>,
+    query: Query<FeesQueryParams>,
+) -> HttpResponse {
+    let window = query.window.as_deref().unwrap_or("30d");
+    let (from, to) = match resolve_window(window, query.start_time, query.end_time) {
+        Ok(range) => range,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+
+    let valid_interval = vec!["hour", "day", "week", "month", "quarter", "year"];
+    if let Some(ref interval) = query.interval {
+        if !valid_interval.contains(&interval.as_str()) {
+            return HttpResponse::BadRequest().body(format!("interval must be one of: {:?}", valid_interval));
+        }
+    }
+
+    let pools = parse_pools(query.pool.as_deref());
+    let interval_duration = query.interval.as_deref().map(interval_duration);
+
+    match analytics::get_pool_fees(&db, &pools, from, to, interval_duration).await {
+        Ok(fees) => HttpResponse::Ok().json(fees),
+        Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
+    }
+}
+
+/// Compare an LP position against holding its assets
+#[utoipa::path(
+    get,
+    path = "/analytics/lp",
+    params(
+        ("pool" = String, Query, description = "Pool identifier"),
+        ("deposit_time" = i64, Query, description = "Deposit time Unix timestamp"),
+        ("amount" = f64, Query, description = "Deposited value in USD, added symmetrically"),
+        ("end_time" = Option<i64>, Query, description = "Value the position up to this Unix timestamp, defaults to the latest snapshot"),
+        ("interval" = Option<String>, Query, description = "Spacing of the series (hour, day, week, month, quarter, year), defaults to day")
+    ),
+    responses(
+        (status = 200, description = "Position value, impermanent loss and fee growth at the deposit, now and over time", body = lp_calculator::LpPerformance),
+        (status = 400, description = "Bad request - Invalid parameters"),
+        (status = 404, description = "No depth history for the pool at the deposit time"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Analytics"
+)]
+#[get("/analytics/lp")]
+pub async fn get_lp_performance(
+    db: Data<Database>,
+    query: Query<LpQueryParams>,
+) -> HttpResponse {
+    if let Some(end) = query.end_time {
+        if query.deposit_time >= end {
+            return HttpResponse::BadRequest().body("deposit_time must be less than end_time.");
+        }
+    }
+
+    if !(query.amount.is_finite() && query.amount > 0.0) {
+        return HttpResponse::BadRequest().body("amount must be a positive number.");
+    }
+
+    let valid_interval = vec!["hour", "day", "week", "month", "quarter", "year"];
+    let interval = query.interval.as_deref().unwrap_or("day");
+    if !valid_interval.contains(&interval) {
+        return HttpResponse::BadRequest().body(format!("interval must be one of: {:?}", valid_interval));
+    }
+
+    match lp_calculator::get_lp_performance(
+        &db,
+        query.pool.clone(),
+        query.deposit_time,
+        query.amount,
+        query.end_time,
+        interval_duration(interval),
+    )
+    .await
+    {
+        Ok(Some(performance)) => HttpResponse::Ok().json(performance),
+        Ok(None) => HttpResponse::NotFound().body("No depth history for this pool at deposit_time."),
+        Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
+    }
+}
+
+/// Get rolling sums and time-weighted averages
+#[utoipa::path(
+    get,
+    path = "/analytics/rolling",
+    params(
+        ("dataset" = String, Query, description = "depths for the TWAP of asset_price_usd and time-weighted depths (null for a window without duration), swaps for rolling sums of total_volume_usd, total_fees and total_count"),
+        ("window" = Option<String>, Query, description = "Trailing window (7d or 30d), defaults to 7d"),
+        ("pool" = Option<String>, Query, description = "Comma separated pool identifiers, all pools when omitted"),
+        ("interval" = Option<String>, Query, description = "Keep the last value of each interval (hour, day, week, month, quarter, year), defaults to day"),
+        ("start_time" = Option<i64>, Query, description = "Start time Unix timestamp. Without it the latest values are returned"),
+        ("end_time" = Option<i64>, Query, description = "End time Unix timestamp"),
+        ("limit" = Option<i64>, Query, description = "Maximum number of rows (1-1000), defaults to 100")
+    ),
+    responses(
+        (status = 200, description = "Window values per pool, oldest first"),
+        (status = 400, description = "Bad request - Invalid parameters"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Analytics"
+)]
+#[get("/analytics/rolling")]
+pub async fn get_rolling(
+    db: Data<Database>,
+    query: Query<RollingQueryParams>,
+) -> HttpResponse {
+    if let (Some(start), Some(end)) = (query.start_time, query.end_time) {
+        if start >= end {
+            return HttpResponse::BadRequest().body("start_time must be less than end_time.");
+        }
+    }
+
+    let valid_window = vec!["7d", "30d"];
+    let window = match query.window.as_deref().unwrap_or("7d") {
+        "7d" => 7 * 86400,
+        "30d" => 30 * 86400,
+        _ => return HttpResponse::BadRequest().body(format!("window must be one of: {:?}", valid_window)),
+    };
+
+    let valid_interval = vec!["hour", "day", "week", "month", "quarter", "year"];
+    let interval = query.interval.as_deref().unwrap_or("day");
+    if !valid_interval.contains(&interval) {
+        return HttpResponse::BadRequest().body(format!("interval must be one of: {:?}", valid_interval));
+    }
+
+    let limit = query.limit.unwrap_or(100);
+    if !(1..=1000).contains(&limit) {
+        return HttpResponse::BadRequest().body("limit must be between 1 and 1000.");
+    }
+
+    let pools = parse_pools(query.pool.as_deref());
+    // Rows are hourly already, so an hourly interval needs no bucketing
+    let interval_duration = Some(interval_duration(interval)).filter(|duration| *duration > 3600);
+
+    let result = match query.dataset.as_str() {
+        "depths" => db.get_rolling_depths(&pools, window, query.start_time, query.end_time, interval_duration, limit).await,
+        "swaps" => db.get_rolling_swaps(&pools, window, query.start_time, query.end_time, interval_duration, limit).await,
+        _ => return HttpResponse::BadRequest().body("dataset must be one of: [\"depths\", \"swaps\"]"),
+    };
+
+    match result {
+        Ok(rows) => HttpResponse::Ok().json(rows),
+        Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
+    }
+}
@@ -0,0 +1,133 @@
+use crate::services::{
+    anomaly_detector::{DEPTH_METRICS, SWAP_METRICS},
+    db::Database,
+    query_builder::parse_pools,
+};
+use actix_web::{
+    get,
+    web::{Data, Query},
+    HttpResponse,
+};
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AnomaliesQueryParams {
+    #[schema(example = "BTC.BTC,ETH.ETH")]
+    pub pool: Option<String>,
+    #[schema(example = "total_volume_usd")]
+    pub metric: Option<String>,
+    #[schema(example = 1653373410)]
+    pub start_time: Option<i64>,
+    #[schema(example = 1666592610)]
+    pub end_time: Option<i64>,
+    #[schema(example = 5.0)]
+    pub min_score: Option<f64>,
+    #[schema(example = 1, minimum = 1)]
+    pub page: Option<u32>,
+    #[schema(example = 10, minimum = 1, maximum = 100)]
+    pub limit: Option<u32>,
+}
+
+#[derive(utoipa::ToSchema)]
+#[allow(dead_code)]
+pub struct AnomalyResponse {
+    /// Dataset the interval belongs to (swaps or depths)
+    #[schema(example = "swaps")]
+    pub dataset: String,
+
+    /// Pool identifier
+    #[schema(example = "BTC.BTC")]
+    pub pool: String,
+
+    /// Metric that moved outside its recent distribution
+    #[schema(example = "total_volume_usd")]
+    pub metric: String,
+
+    /// Start time of the flagged interval (UNIX timestamp)
+    #[schema(example = 1666591200)]
+    pub start_time: i64,
+
+    /// End time of the flagged interval (UNIX timestamp)
+    #[schema(example = 1666594800)]
+    pub end_time: i64,
+
+    /// Value of the metric in the interval
+    #[schema(example = 98765432.1)]
+    pub value: f64,
+
+    /// Mean of the metric over the pool's previous 7 days
+    #[schema(example = 1234567.8)]
+    pub mean: f64,
+
+    /// Population standard deviation of the metric over the pool's previous 7 days
+    #[schema(example = 2345678.9)]
+    pub std_dev: f64,
+
+    /// (value - mean) / std_dev, with std_dev floored at 1% of the mean and at 1 for metrics that barely moved
+    #[schema(example = 41.6)]
+    pub z_score: f64,
+
+    /// Number of intervals in the previous 7 days
+    #[schema(example = 168)]
+    pub samples: i64,
+
+    /// Time the interval was scored (UNIX timestamp)
+    #[schema(example = 1666595000)]
+    pub detected_at: i64,
+}
+
+/// Get intervals flagged as anomalous
+#[utoipa::path(
+    get,
+    path = "/anomalies",
+    params(
+        ("pool" = Option<String>, Query, description = "Comma separated pool identifiers, all pools when omitted"),
+        ("metric" = Option<String>, Query, description = "Only this metric (total_volume_usd, average_slip, asset_depth)"),
+        ("start_time" = Option<i64>, Query, description = "Start time Unix timestamp"),
+        ("end_time" = Option<i64>, Query, description = "End time Unix timestamp"),
+        ("min_score" = Option<f64>, Query, description = "Only anomalies with an absolute z-score of at least this value. Intervals are flagged from 4"),
+        ("page" = Option<u32>, Query, description = "Page number (minimum: 1)"),
+        ("limit" = Option<u32>, Query, description = "Items per page (1-100)")
+    ),
+    responses(
+        (status = 200, description = "Flagged intervals, latest first", body = Vec<AnomalyResponse>),
+        (status = 400, description = "Bad request - Invalid parameters"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Anomalies"
+)]
+#[get("/anomalies")]
+pub async fn get_anomalies(
+    db: Data<Database>,
+    query: Query<AnomaliesQueryParams>,
+) -> HttpResponse {
+    if let (Some(start), Some(end)) = (query.start_time, query.end_time) {
+        if start >= end {
+            return HttpResponse::BadRequest().body("start_time must be less than end_time.");
+        }
+    }
+
+    if let Some(ref metric) = query.metric {
+        let metrics: Vec<&str> = SWAP_METRICS.iter().chain(DEPTH_METRICS).copied().collect();
+        if !metrics.contains(&metric.as_str()) {
+            return HttpResponse::BadRequest().body(format!("metric must be one of: {:?}", metrics));
+        }
+    }
+
+    if query.min_score.is_some_and(|min_score| !min_score.is_finite() || min_score < 0.0) {
+        return HttpResponse::BadRequest().body("min_score must be a finite number that is not negative.");
+    }
+
+    let limit = query.limit.unwrap_or(10).clamp(1, 100);
+    let page = query.page.unwrap_or(1).max(1);
+    let pools = parse_pools(query.pool.as_deref());
+
+    match db
+        .get_anomalies(&pools, query.metric.as_deref(), query.start_time, query.end_time, query.min_score, page, limit)
+        .await
+    {
+        Ok(anomalies) => HttpResponse::Ok().json(anomalies),
+        Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
+    }
+}
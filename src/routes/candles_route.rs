@@ -0,0 +1,87 @@
+use crate::services::{analytics, db::{interval_duration, Database}};
+use actix_web::{
+    get,
+    web::{Data, Query},
+    HttpResponse,
+};
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CandlesQueryParams {
+    #[schema(example = "BTC.BTC")]
+    pub pool: String,
+    #[schema(example = "day")]
+    pub interval: Option<String>,
+    #[schema(example = 1653373410)]
+    pub start_time: Option<i64>,
+    #[schema(example = 1666592610)]
+    pub end_time: Option<i64>,
+    #[schema(example = "usd")]
+    pub currency: Option<String>,
+    #[schema(example = 500, minimum = 1, maximum = 1000)]
+    pub limit: Option<i64>,
+}
+
+/// Get OHLC price candles with swap volume for a pool
+#[utoipa::path(
+    get,
+    path = "/candles",
+    params(
+        ("pool" = String, Query, description = "Pool identifier"),
+        ("interval" = Option<String>, Query, description = "Candle size (hour, day, week, month, quarter, year), defaults to day"),
+        ("start_time" = Option<i64>, Query, description = "Start time Unix timestamp. Without it the latest candles are returned"),
+        ("end_time" = Option<i64>, Query, description = "End time Unix timestamp"),
+        ("currency" = Option<String>, Query, description = "Quote prices and volume in usd or rune, defaults to usd"),
+        ("limit" = Option<i64>, Query, description = "Maximum number of candles (1-1000), defaults to 500")
+    ),
+    responses(
+        (status = 200, description = "Candles oldest first, as time/open/high/low/close/volume objects", body = [analytics::Candle]),
+        (status = 400, description = "Bad request - Invalid parameters"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Candles"
+)]
+#[get("/candles")]
+pub async fn get_candles(
+    db: Data<Database>,
+    query: Query<CandlesQueryParams>,
+) -> HttpResponse {
+    if let (Some(start), Some(end)) = (query.start_time, query.end_time) {
+        if start >= end {
+            return HttpResponse::BadRequest().body("start_time must be less than end_time.");
+        }
+    }
+
+    let valid_interval = vec!["hour", "day", "week", "month", "quarter", "year"];
+    let interval = query.interval.as_deref().unwrap_or("day");
+    if !valid_interval.contains(&interval) {
+        return HttpResponse::BadRequest().body(format!("interval must be one of: {:?}", valid_interval));
+    }
+
+    let valid_currency = vec!["usd", "rune"];
+    let currency = query.currency.as_deref().unwrap_or("usd");
+    if !valid_currency.contains(&currency) {
+        return HttpResponse::BadRequest().body(format!("currency must be one of: {:?}", valid_currency));
+    }
+
+    let limit = query.limit.unwrap_or(500);
+    if !(1..=1000).contains(&limit) {
+        return HttpResponse::BadRequest().body("limit must be between 1 and 1000.");
+    }
+
+    match analytics::get_candles(
+        &db,
+        query.pool.clone(),
+        currency,
+        query.start_time,
+        query.end_time,
+        interval_duration(interval),
+        limit,
+    )
+    .await
+    {
+        Ok(candles) => HttpResponse::Ok().json(candles),
+        Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
+    }
+}
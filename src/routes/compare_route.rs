@@ -0,0 +1,83 @@
+use crate::services::{
+    compare::{self, Period, DATASETS},
+    db::Database,
+    query_builder::parse_pools,
+};
+use actix_web::{
+    get,
+    web::{Data, Query},
+    HttpResponse,
+};
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CompareQueryParams {
+    #[schema(example = "swaps")]
+    pub dataset: String,
+    #[schema(example = 1666000000)]
+    pub start_time: i64,
+    #[schema(example = 1666604800)]
+    pub end_time: i64,
+    #[schema(example = 1665395200)]
+    pub previous_start_time: Option<i64>,
+    #[schema(example = 1666000000)]
+    pub previous_end_time: Option<i64>,
+    #[schema(example = "BTC.BTC,ETH.ETH")]
+    pub pool: Option<String>,
+}
+
+/// Compare a period with a previous one
+#[utoipa::path(
+    get,
+    path = "/compare",
+    params(
+        ("dataset" = String, Query, description = "Dataset to compare (swaps, depths, earnings, runepool)"),
+        ("start_time" = i64, Query, description = "Start of the current period, Unix timestamp"),
+        ("end_time" = i64, Query, description = "End of the current period, Unix timestamp"),
+        ("previous_start_time" = Option<i64>, Query, description = "Start of the previous period, defaults to the period of the same length right before the current one"),
+        ("previous_end_time" = Option<i64>, Query, description = "End of the previous period, defaults to start_time"),
+        ("pool" = Option<String>, Query, description = "Comma separated pool identifiers, all pools when omitted. Not available for runepool")
+    ),
+    responses(
+        (status = 200, description = "Values of both periods with absolute and percentage changes per pool and metric", body = compare::Comparison),
+        (status = 400, description = "Bad request - Invalid parameters"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Compare"
+)]
+#[get("/compare")]
+pub async fn get_comparison(
+    db: Data<Database>,
+    query: Query<CompareQueryParams>,
+) -> HttpResponse {
+    if !DATASETS.contains(&query.dataset.as_str()) {
+        return HttpResponse::BadRequest().body(format!("dataset must be one of: {:?}", DATASETS));
+    }
+
+    if query.start_time >= query.end_time {
+        return HttpResponse::BadRequest().body("start_time must be less than end_time.");
+    }
+
+    let previous_end_time = query.previous_end_time.unwrap_or(query.start_time);
+    let previous_start_time = query
+        .previous_start_time
+        .unwrap_or(previous_end_time - (query.end_time - query.start_time));
+
+    if previous_start_time >= previous_end_time {
+        return HttpResponse::BadRequest().body("previous_start_time must be less than previous_end_time.");
+    }
+
+    if query.dataset == "runepool" && query.pool.is_some() {
+        return HttpResponse::BadRequest().body("runepool has no pool, so pool is not supported.");
+    }
+
+    let current = Period { start_time: query.start_time, end_time: query.end_time };
+    let previous = Period { start_time: previous_start_time, end_time: previous_end_time };
+    let pools = parse_pools(query.pool.as_deref());
+
+    match compare::compare_periods(&db, &query.dataset, &pools, current, previous).await {
+        Ok(comparison) => HttpResponse::Ok().json(comparison),
+        Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
+    }
+}
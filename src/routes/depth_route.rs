@@ -1,4 +1,8 @@
-use crate::services::db::Database;
+use crate::models::depth_history_model::{DERIVED_FIELDS, FIELDS};
+use crate::services::{
+    db::Database,
+    query_builder::{build_filter, build_projection, build_sort, parse_chain, parse_fill, parse_group_by, parse_pools, HistoryQuery},
+};
 use actix_web::{
     get,
     web::{Data, Query},
@@ -13,18 +17,30 @@ pub struct DepthHistoryQueryParams {
     pub start_time: Option<i64>,
     #[schema(example = 1666592610)]
     pub end_time: Option<i64>,
-    #[schema(example = "BTC.BTC")]
+    #[schema(example = "BTC.BTC,ETH.ETH")]
     pub pool: Option<String>,
+    #[schema(example = "ETH")]
+    pub chain: Option<String>,
     #[schema(example = 1, minimum = 1)]
     pub page: Option<u32>,
     #[schema(example = 10, minimum = 1, maximum = 100)]
     pub limit: Option<u32>,
-    #[schema(example = "asset_price")]
+    #[schema(example = "pool,-asset_price")]
     pub sort_by: Option<String>,
     #[schema(example = "asc")]
     pub order: Option<String>,
     #[schema(example = "day")]
     pub interval: Option<String>,
+    #[schema(example = "start_time,asset_price_usd,rune_depth")]
+    pub fields: Option<String>,
+    #[schema(example = "members_count<100")]
+    pub filter: Option<String>,
+    #[schema(example = "pool")]
+    pub group_by: Option<String>,
+    #[schema(example = "previous")]
+    pub fill: Option<String>,
+    #[schema(example = true)]
+    pub derived: Option<bool>,
 }
 
 #[derive(utoipa::ToSchema)]
@@ -81,6 +97,22 @@ pub struct PoolDepthPriceHistoryResponse {
     /// Total number of units in the pool
     #[schema(example = 999999.99)]
     pub units: f64,
+
+    /// Pool TVL in USD, both sides valued at asset_price_usd. Only returned when requested
+    #[schema(example = 24691357.6)]
+    pub tvl_usd: Option<f64>,
+
+    /// synth_supply / asset_depth. Only returned when requested
+    #[schema(example = 0.046)]
+    pub synth_utilization: Option<f64>,
+
+    /// RUNE price in USD implied by the depth ratio and asset_price_usd. Only returned when requested
+    #[schema(example = 1.52)]
+    pub implied_rune_price_usd: Option<f64>,
+
+    /// liquidity_units / members_count. Only returned when requested
+    #[schema(example = 400.0)]
+    pub units_per_member: Option<f64>,
 }
 
 
@@ -91,12 +123,18 @@ pub struct PoolDepthPriceHistoryResponse {
     params(
         ("start_time" = Option<i64>, Query, description = "Start time Unix timestamp"),
         ("end_time" = Option<i64>, Query, description = "End time Unix timestamp"),
-        ("pool" = Option<String>, Query, description = "Pool identifier"),
+        ("pool" = Option<String>, Query, description = "Comma separated pool identifiers"),
+        ("chain" = Option<String>, Query, description = "Only pools of this chain, e.g. ETH matches every ETH.* pool"),
         ("page" = Option<u32>, Query, description = "Page number (minimum: 1)"),
         ("limit" = Option<u32>, Query, description = "Items per page (1-100)"),
-        ("sort_by" = Option<String>, Query, description = "Field to sort by"),
+        ("sort_by" = Option<String>, Query, description = "Comma separated fields to sort by, prefix a field with - to sort it descending."),
         ("order" = Option<String>, Query, description = "Sort order (asc or desc)"),
-        ("interval" = Option<String>, Query, description = "Time interval for aggregation (hour, day, week, month, quarter, year)")
+        ("interval" = Option<String>, Query, description = "Time interval for aggregation (hour, day, week, month, quarter, year)"),
+        ("fields" = Option<String>, Query, description = "Comma separated fields to return, all fields when omitted. Accepts the same fields as sort_by"),
+        ("filter" = Option<String>, Query, description = "Comma separated numeric conditions on the returned rows, e.g. members_count<100,asset_depth>=1000000. Operators: >=, <=, !=, >, <, ="),
+        ("group_by" = Option<String>, Query, description = "Set to pool to return a separate row per pool within each interval instead of merging all pools"),
+        ("fill" = Option<String>, Query, description = "Emit every bucket of the range (none, zero, null, previous), e.g. previous to carry the last depth snapshot forward. Filled buckets have filled set to true"),
+        ("derived" = Option<bool>, Query, description = "Set to true to add the computed fields tvl_usd, synth_utilization, implied_rune_price_usd and units_per_member to every row. Naming one of them in fields, sort_by or filter adds them as well")
     ),
     responses(
         (status = 200, description = "List of pool depth price history", body = Vec<PoolDepthPriceHistoryResponse>),
@@ -146,17 +184,76 @@ pub async fn get_pool_depth_price_history(
         }
     }
 
+    let allowed_fields: Vec<&str> = FIELDS.iter().chain(DERIVED_FIELDS).copied().collect();
+
+    let mut sort_doc = match build_sort(query.sort_by.as_deref(), sort_order, &allowed_fields) {
+        Ok(sort_doc) => sort_doc,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+
+    let mut projection = match build_projection(query.fields.as_deref(), &allowed_fields) {
+        Ok(projection) => projection,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+
+    let filter = match build_filter(query.filter.as_deref(), &allowed_fields) {
+        Ok(filter) => filter,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+
+    let derived = query.derived.unwrap_or(false)
+        || sort_doc
+            .keys()
+            .chain(filter.keys())
+            .chain(projection.iter().flat_map(|projection| projection.keys()))
+            .any(|key| DERIVED_FIELDS.contains(&key.as_str()));
+
+    if query.derived == Some(true) {
+        if let Some(ref mut projection) = projection {
+            for field in DERIVED_FIELDS {
+                projection.insert(*field, 1);
+            }
+        }
+    }
+
+    let chain = match parse_chain(query.chain.as_deref()) {
+        Ok(chain) => chain,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+
+    let group_by_pool = match parse_group_by(query.group_by.as_deref()) {
+        Ok(group_by_pool) => group_by_pool,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+
+    let fill = match parse_fill(query.fill.as_deref()) {
+        Ok(fill) => fill,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+
+    // Keep the rows of one interval in a stable pool order
+    if group_by_pool && !sort_doc.contains_key("pool") {
+        sort_doc.insert("pool", 1);
+    }
+
+    let options = HistoryQuery {
+        start_time: query.start_time,
+        end_time: query.end_time,
+        pools: parse_pools(query.pool.as_deref()),
+        chain,
+        group_by_pool,
+        page,
+        limit: Some(limit),
+        sort_doc,
+        interval: query.interval.clone(),
+        projection,
+        filter,
+        fill,
+        derived,
+    };
+
     match db
-        .get_pool_depth_price_history(
-            query.start_time,
-            query.end_time,
-            query.pool.clone(),
-            page,
-            limit,
-            query.sort_by.clone(),
-            sort_order,
-            query.interval.clone()
-        )
+        .get_pool_depth_price_history(options)
         .await
     {
         Ok(history) => HttpResponse::Ok().json(history),
@@ -29,7 +29,7 @@ pub async fn fetch_and_store_depth(
         return HttpResponse::Unauthorized().body("Wrong secret key.");
     }
 
-    match services::depth_history_fetcher::fetch_and_store_depth_history(&db, &params.pool, &params.interval, params.start_time).await {
+    match services::depth_history_fetcher::fetch_and_store_depth_history(&db, &params.pool, &params.interval, params.start_time, false).await {
         Ok(()) => HttpResponse::Ok().body("Depth history fetched and stored successfully."),
         Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
     }
@@ -28,7 +28,7 @@ pub async fn fetch_and_store_earnings(
         return HttpResponse::Unauthorized().body("Wrong secret key.");
     }
 
-    match services::earnings_fetcher::fetch_and_store_earnings(&db, &params.interval, params.start_time).await {
+    match services::earnings_fetcher::fetch_and_store_earnings(&db, &params.interval, params.start_time, false).await {
         Ok(()) => HttpResponse::Ok().body("Earnings fetched and stored successfully."),
         Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
     }
@@ -1,4 +1,8 @@
-use crate::services::db::Database;
+use crate::models::{earning_summary_model::MERGED_FIELDS, earnings_model::FIELDS};
+use crate::services::{
+    db::{interval_duration, number, Database},
+    query_builder::{build_filter, build_projection, build_sort, parse_chain, parse_group_by, parse_pools, Fill, HistoryQuery},
+};
 use actix_web::{
     get,
     web::{Data, Query},
@@ -13,22 +17,86 @@ pub struct EarningsQueryParams {
     pub start_time: Option<i64>,
     #[schema(example = 1666592610)]
     pub end_time: Option<i64>,
-    #[schema(example = "BTC.BTC")]
+    #[schema(example = "BTC.BTC,ETH.ETH")]
     pub pool: Option<String>,
+    #[schema(example = "ETH")]
+    pub chain: Option<String>,
     #[schema(example = 1, minimum = 1)]
     pub page: Option<u32>,
     #[schema(example = 10, minimum = 1, maximum = 100)]
     pub limit: Option<u32>,
-    #[schema(example = "saver_earning")]
+    #[schema(example = "pool,-saver_earning")]
     pub sort_by: Option<String>,
     #[schema(example = "asc")]
     pub order: Option<String>,
     #[schema(example = "day")]
     pub interval: Option<String>,
+    #[schema(example = "start_time,rewards,saver_earning")]
+    pub fields: Option<String>,
+    #[schema(example = "saver_earning>0")]
+    pub filter: Option<String>,
+    #[schema(example = "pool")]
+    pub group_by: Option<String>,
     #[schema(example = false)]
     pub summary: Option<bool>,
 }
 
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct EarningsSummaryQueryParams {
+    #[schema(example = 1653373410)]
+    pub start_time: Option<i64>,
+    #[schema(example = 1666592610)]
+    pub end_time: Option<i64>,
+    #[schema(example = "day")]
+    pub interval: Option<String>,
+    #[schema(example = 100, minimum = 1, maximum = 1000)]
+    pub limit: Option<i64>,
+}
+
+#[derive(utoipa::ToSchema)]
+#[allow(dead_code)]
+pub struct EarningsSummaryResponse {
+    /// Start time of the interval (UNIX timestamp)
+    #[schema(example = 1653350400)]
+    pub start_time: i64,
+
+    /// End time of the interval (UNIX timestamp)
+    #[schema(example = 1653436800)]
+    pub end_time: i64,
+
+    /// Block rewards paid over the interval
+    #[schema(example = 1234.56)]
+    pub block_rewards: f64,
+
+    /// Average node count over the interval
+    #[schema(example = 50.0)]
+    pub avg_node_count: f64,
+
+    /// Earnings paid to bonded nodes over the interval
+    #[schema(example = 7890.12)]
+    pub bonding_earnings: f64,
+
+    /// Earnings paid to liquidity providers over the interval
+    #[schema(example = 4567.89)]
+    pub liquidity_earnings: f64,
+
+    /// Liquidity fees collected over the interval
+    #[schema(example = 123456.78)]
+    pub liquidity_fees: f64,
+
+    /// RUNE price in USD at the end of the interval
+    #[schema(example = 8.99)]
+    pub rune_price_usd: f64,
+
+    /// bonding_earnings / avg_node_count, null without nodes
+    #[schema(example = 157.8)]
+    pub bonding_earnings_per_node: Option<f64>,
+
+    /// bonding_earnings / liquidity_earnings, null without liquidity earnings
+    #[schema(example = 1.73)]
+    pub bonding_to_liquidity_ratio: Option<f64>,
+}
+
 #[derive(utoipa::ToSchema)]
 #[allow(dead_code)]
 pub struct PoolEarningsResponse {
@@ -93,12 +161,16 @@ pub struct PoolEarningsResponse {
     params(
         ("start_time" = Option<i64>, Query, description = "Start time Unix timestamp"),
         ("end_time" = Option<i64>, Query, description = "End time Unix timestamp"),
-        ("pool" = Option<String>, Query, description = "Pool identifier"),
+        ("pool" = Option<String>, Query, description = "Comma separated pool identifiers"),
+        ("chain" = Option<String>, Query, description = "Only pools of this chain, e.g. ETH matches every ETH.* pool"),
         ("page" = Option<u32>, Query, description = "Page number (minimum: 1)"),
         ("limit" = Option<u32>, Query, description = "Items per page (1-100)"),
-        ("sort_by" = Option<String>, Query, description = "Field to sort by"),
+        ("sort_by" = Option<String>, Query, description = "Comma separated fields to sort by, prefix a field with - to sort it descending."),
         ("order" = Option<String>, Query, description = "Sort order (asc or desc)"),
         ("interval" = Option<String>, Query, description = "Time interval for aggregation (hour, day, week, month, quarter, year)"),
+        ("fields" = Option<String>, Query, description = "Comma separated fields to return, all fields when omitted. Accepts the same fields as sort_by, and with summary the merged summary fields as well"),
+        ("filter" = Option<String>, Query, description = "Comma separated numeric conditions on the returned rows, e.g. saver_earning>0,rewards>=1000000. Operators: >=, <=, !=, >, <, ="),
+        ("group_by" = Option<String>, Query, description = "Set to pool to return a separate row per pool within each interval instead of merging all pools"),
         ("summary" = Option<bool>, Query, description = "Whether to include summary in response")
     ),
     responses(
@@ -149,23 +221,130 @@ pub async fn get_pool_earnings_api(
         }
     }
 
+    let mut sort_doc = match build_sort(query.sort_by.as_deref(), sort_order, FIELDS) {
+        Ok(sort_doc) => sort_doc,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+
     let include_summary = query.summary.unwrap_or(false);
 
+    // Summary fields can be picked once they are merged into the rows
+    let projection_fields: Vec<&str> = match include_summary {
+        true => FIELDS.iter().chain(MERGED_FIELDS).copied().collect(),
+        false => FIELDS.to_vec(),
+    };
+
+    let projection = match build_projection(query.fields.as_deref(), &projection_fields) {
+        Ok(projection) => projection,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+
+    let filter = match build_filter(query.filter.as_deref(), FIELDS) {
+        Ok(filter) => filter,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+
+    let chain = match parse_chain(query.chain.as_deref()) {
+        Ok(chain) => chain,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+
+    let group_by_pool = match parse_group_by(query.group_by.as_deref()) {
+        Ok(group_by_pool) => group_by_pool,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+
+    // Keep the rows of one interval in a stable pool order
+    if group_by_pool && !sort_doc.contains_key("pool") {
+        sort_doc.insert("pool", 1);
+    }
+
+    let options = HistoryQuery {
+        start_time: query.start_time,
+        end_time: query.end_time,
+        pools: parse_pools(query.pool.as_deref()),
+        chain,
+        group_by_pool,
+        page,
+        limit: Some(limit),
+        sort_doc,
+        interval: query.interval.clone(),
+        projection,
+        filter,
+        fill: Fill::None,
+        derived: false,
+    };
+
     match db
-        .get_pool_earnings(
-            query.start_time,
-            query.end_time,
-            query.pool.clone(),
-            page,
-            limit,
-            query.sort_by.clone(),
-            sort_order,
-            query.interval.clone(),
-            include_summary,
-        )
+        .get_pool_earnings(options, include_summary)
         .await
     {
         Ok(earnings) => HttpResponse::Ok().json(earnings),
         Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
     }
 }
+
+/// Get network earnings and node economics
+#[utoipa::path(
+    get,
+    path = "/earnings/summary",
+    params(
+        ("start_time" = Option<i64>, Query, description = "Start time Unix timestamp. Without it the latest intervals are returned"),
+        ("end_time" = Option<i64>, Query, description = "End time Unix timestamp"),
+        ("interval" = Option<String>, Query, description = "Time interval for aggregation (hour, day, week, month, quarter, year), defaults to hour"),
+        ("limit" = Option<i64>, Query, description = "Maximum number of intervals (1-1000), defaults to 100")
+    ),
+    responses(
+        (status = 200, description = "Network earnings per interval, oldest first", body = Vec<EarningsSummaryResponse>),
+        (status = 400, description = "Bad request - Invalid parameters"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Earnings History"
+)]
+#[get("/earnings/summary")]
+pub async fn get_earnings_summary(
+    db: Data<Database>,
+    query: Query<EarningsSummaryQueryParams>,
+) -> HttpResponse {
+    if let (Some(start), Some(end)) = (query.start_time, query.end_time) {
+        if start >= end {
+            return HttpResponse::BadRequest().body("start_time must be less than end_time.");
+        }
+    }
+
+    let valid_interval = vec!["hour", "day", "week", "month", "quarter", "year"];
+    let interval = query.interval.as_deref().unwrap_or("hour");
+    if !valid_interval.contains(&interval) {
+        return HttpResponse::BadRequest().body(format!("interval must be one of: {:?}", valid_interval));
+    }
+
+    let limit = query.limit.unwrap_or(100);
+    if !(1..=1000).contains(&limit) {
+        return HttpResponse::BadRequest().body("limit must be between 1 and 1000.");
+    }
+
+    let intervals = db
+        .get_earnings_summary_intervals(query.start_time, query.end_time, Some(interval_duration(interval)), limit)
+        .await;
+
+    match intervals {
+        Ok(mut rows) => {
+            for row in rows.iter_mut() {
+                let bonding_earnings = number(row, "bonding_earnings");
+                let avg_node_count = number(row, "avg_node_count");
+                let liquidity_earnings = number(row, "liquidity_earnings");
+
+                row.insert(
+                    "bonding_earnings_per_node",
+                    (avg_node_count > 0.0).then(|| bonding_earnings / avg_node_count),
+                );
+                row.insert(
+                    "bonding_to_liquidity_ratio",
+                    (liquidity_earnings != 0.0).then(|| bonding_earnings / liquidity_earnings),
+                );
+            }
+            HttpResponse::Ok().json(rows)
+        }
+        Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
+    }
+}
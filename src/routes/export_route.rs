@@ -0,0 +1,281 @@
+use crate::models::{depth_history_model, earnings_model, rpmuh_model, swap_history_model};
+use crate::services::{
+    db::{time_range_query, Database},
+    parquet_export::{self, DATASETS},
+    query_builder::{build_filter, build_projection, build_sort, parse_chain, parse_group_by, parse_pools, Fill, HistoryQuery},
+};
+use actix_web::{
+    get,
+    web::{Bytes, Data, Path, Query},
+    HttpResponse,
+};
+use futures_util::stream::{self, StreamExt};
+use mongodb::bson::{Bson, Document};
+use serde::Deserialize;
+
+
+#[derive(Debug, Deserialize, utoipa::ToSchema)]
+pub struct ExportQueryParams {
+    #[schema(example = 1653373410)]
+    pub start_time: Option<i64>,
+    #[schema(example = 1666592610)]
+    pub end_time: Option<i64>,
+    #[schema(example = "BTC.BTC,ETH.ETH")]
+    pub pool: Option<String>,
+    #[schema(example = "ETH")]
+    pub chain: Option<String>,
+    #[schema(example = "start_time")]
+    pub sort_by: Option<String>,
+    #[schema(example = "asc")]
+    pub order: Option<String>,
+    #[schema(example = "day")]
+    pub interval: Option<String>,
+    #[schema(example = "pool,start_time,total_volume_usd")]
+    pub fields: Option<String>,
+    #[schema(example = "total_volume_usd>1000000")]
+    pub filter: Option<String>,
+    #[schema(example = "pool")]
+    pub group_by: Option<String>,
+    #[schema(example = "csv")]
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Deserialize, utoipa::ToSchema)]
+pub struct ParquetQueryParams {
+    #[schema(example = 1653373410)]
+    pub start_time: Option<i64>,
+    #[schema(example = 1666592610)]
+    pub end_time: Option<i64>,
+    #[schema(example = "BTC.BTC")]
+    pub pool: Option<String>,
+}
+
+fn csv_value(value: Option<&Bson>) -> String {
+    match value {
+        Some(Bson::String(text)) if text.contains([',', '"', '\n']) => format!("\"{}\"", text.replace('"', "\"\"")),
+        Some(Bson::String(text)) => text.clone(),
+        Some(Bson::Double(value)) => value.to_string(),
+        Some(Bson::Int32(value)) => value.to_string(),
+        Some(Bson::Int64(value)) => value.to_string(),
+        Some(other) => other.to_string(),
+        None => String::new(),
+    }
+}
+
+fn csv_row(doc: &Document, columns: &[String]) -> String {
+    let values: Vec<String> = columns.iter().map(|column| csv_value(doc.get(column))).collect();
+    format!("{}\n", values.join(","))
+}
+
+/// Stream a whole dataset as CSV or NDJSON
+#[utoipa::path(
+    get,
+    path = "/export/{dataset}",
+    params(
+        ("dataset" = String, Path, description = "Dataset to export (swaps, depths, earnings, runepool)"),
+        ("start_time" = Option<i64>, Query, description = "Start time Unix timestamp"),
+        ("end_time" = Option<i64>, Query, description = "End time Unix timestamp"),
+        ("pool" = Option<String>, Query, description = "Comma separated pool identifiers, not available for runepool"),
+        ("chain" = Option<String>, Query, description = "Only pools of this chain, e.g. ETH matches every ETH.* pool"),
+        ("sort_by" = Option<String>, Query, description = "Comma separated fields to sort by, prefix a field with - to sort it descending. Accepts the fields of the matching query endpoint"),
+        ("order" = Option<String>, Query, description = "Sort order (asc or desc)"),
+        ("interval" = Option<String>, Query, description = "Time interval for aggregation (hour, day, week, month, quarter, year)"),
+        ("fields" = Option<String>, Query, description = "Comma separated fields to export, these are also the CSV columns. All fields when omitted"),
+        ("filter" = Option<String>, Query, description = "Comma separated numeric conditions on the exported rows, e.g. total_fees>1000"),
+        ("group_by" = Option<String>, Query, description = "Set to pool to export a separate row per pool within each interval"),
+        ("format" = Option<String>, Query, description = "Output format (csv or ndjson), defaults to csv")
+    ),
+    responses(
+        (status = 200, description = "Chunked CSV or NDJSON stream of the matching rows"),
+        (status = 400, description = "Bad request - Invalid parameters"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Export"
+)]
+#[get("/export/{dataset}")]
+pub async fn export_dataset(
+    db: Data<Database>,
+    dataset: Path<String>,
+    query: Query<ExportQueryParams>,
+) -> HttpResponse {
+    let dataset = dataset.into_inner();
+
+    let fields: &[&str] = match dataset.as_str() {
+        "swaps" => swap_history_model::FIELDS,
+        "depths" => depth_history_model::FIELDS,
+        "earnings" => earnings_model::FIELDS,
+        "runepool" => rpmuh_model::FIELDS,
+        _ => return HttpResponse::BadRequest().body("dataset must be one of: [\"swaps\", \"depths\", \"earnings\", \"runepool\"]"),
+    };
+
+    if let (Some(start), Some(end)) = (query.start_time, query.end_time) {
+        if start >= end {
+            return HttpResponse::BadRequest().body("start_time must be less than end_time.");
+        }
+    }
+
+    let sort_order = match query.order.as_deref() {
+        Some("asc") => 1,
+        Some("desc") => -1,
+        _ => 1,
+    };
+
+    let valid_ordering = vec!["asc", "desc"];
+    if let Some(ref order) = query.order {
+        if !valid_ordering.contains(&order.as_str()) {
+            return HttpResponse::BadRequest().body(format!("order must be one of: {:?}", valid_ordering));
+        }
+    }
+
+    let valid_interval = vec!["hour", "day", "week", "month", "quarter", "year"];
+    if let Some(ref interval) = query.interval {
+        if !valid_interval.contains(&interval.as_str()) {
+            return HttpResponse::BadRequest().body(format!("interval must be one of: {:?}", valid_interval));
+        }
+    }
+
+    let valid_format = vec!["csv", "ndjson"];
+    let format = query.format.as_deref().unwrap_or("csv");
+    if !valid_format.contains(&format) {
+        return HttpResponse::BadRequest().body(format!("format must be one of: {:?}", valid_format));
+    }
+
+    if dataset == "runepool" && (query.pool.is_some() || query.chain.is_some() || query.group_by.is_some()) {
+        return HttpResponse::BadRequest().body("runepool has no pool, so pool, chain and group_by are not supported.");
+    }
+
+    let mut sort_doc = match build_sort(query.sort_by.as_deref(), sort_order, fields) {
+        Ok(sort_doc) => sort_doc,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+
+    // Always project explicitly so the CSV columns and the NDJSON keys match
+    let requested_fields = query.fields.clone().unwrap_or_else(|| fields.join(","));
+    let projection = match build_projection(Some(&requested_fields), fields) {
+        Ok(Some(projection)) => projection,
+        Ok(None) => return HttpResponse::BadRequest().body("fields must not be empty."),
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+
+    let filter = match build_filter(query.filter.as_deref(), fields) {
+        Ok(filter) => filter,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+
+    let chain = match parse_chain(query.chain.as_deref()) {
+        Ok(chain) => chain,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+
+    let group_by_pool = match parse_group_by(query.group_by.as_deref()) {
+        Ok(group_by_pool) => group_by_pool,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+
+    if group_by_pool && !sort_doc.contains_key("pool") {
+        sort_doc.insert("pool", 1);
+    }
+
+    let columns: Vec<String> = projection.keys().filter(|key| *key != "_id").cloned().collect();
+
+    let options = HistoryQuery {
+        start_time: query.start_time,
+        end_time: query.end_time,
+        pools: parse_pools(query.pool.as_deref()),
+        chain,
+        group_by_pool,
+        page: 1,
+        limit: None,
+        sort_doc,
+        interval: query.interval.clone(),
+        projection: Some(projection),
+        filter,
+        fill: Fill::None,
+        derived: false,
+    };
+
+    let cursor = match dataset.as_str() {
+        "swaps" => db.pool_swap_history_cursor(options).await,
+        "depths" => db.pool_depth_price_history_cursor(options).await,
+        "earnings" => db.pool_earnings_cursor(options).await,
+        _ => db.rune_pool_history_cursor(options).await,
+    };
+
+    let cursor = match cursor {
+        Ok(cursor) => cursor,
+        Err(err) => return HttpResponse::InternalServerError().body(err.to_string()),
+    };
+
+    let csv = format == "csv";
+    let header = csv.then(|| Ok(Bytes::from(format!("{}\n", columns.join(",")))));
+
+    // Rows are written as the cursor yields them, so the export never holds the full result
+    let rows = cursor.map(move |result| match result {
+        Ok(doc) if csv => Ok(Bytes::from(csv_row(&doc, &columns))),
+        Ok(doc) => serde_json::to_string(&doc)
+            .map(|line| Bytes::from(format!("{}\n", line)))
+            .map_err(actix_web::error::ErrorInternalServerError),
+        Err(err) => Err(actix_web::error::ErrorInternalServerError(err)),
+    });
+
+    let (content_type, extension) = if csv {
+        ("text/csv; charset=utf-8", "csv")
+    } else {
+        ("application/x-ndjson", "ndjson")
+    };
+
+    HttpResponse::Ok()
+        .content_type(content_type)
+        .insert_header(("Content-Disposition", format!("attachment; filename=\"{}.{}\"", dataset, extension)))
+        .streaming(stream::iter(header).chain(rows))
+}
+
+/// Download the raw rows of a collection as a Parquet file
+#[utoipa::path(
+    get,
+    path = "/parquet/{dataset}",
+    params(
+        ("dataset" = String, Path, description = "Collection to export (swap_history, depth_history, earnings, earnings_summary, rpmuh)"),
+        ("start_time" = Option<i64>, Query, description = "Start time Unix timestamp"),
+        ("end_time" = Option<i64>, Query, description = "End time Unix timestamp"),
+        ("pool" = Option<String>, Query, description = "Pool identifier, not available for earnings_summary and rpmuh")
+    ),
+    responses(
+        (status = 200, description = "Snappy compressed Parquet file of the matching rows, ordered by pool and start_time"),
+        (status = 400, description = "Bad request - Invalid parameters"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Export"
+)]
+#[get("/parquet/{dataset}")]
+pub async fn export_parquet(
+    db: Data<Database>,
+    dataset: Path<String>,
+    query: Query<ParquetQueryParams>,
+) -> HttpResponse {
+    let dataset = dataset.into_inner();
+
+    if !DATASETS.contains(&dataset.as_str()) {
+        return HttpResponse::BadRequest().body(format!("dataset must be one of: {:?}", DATASETS));
+    }
+
+    if let (Some(start), Some(end)) = (query.start_time, query.end_time) {
+        if start >= end {
+            return HttpResponse::BadRequest().body("start_time must be less than end_time.");
+        }
+    }
+
+    if query.pool.is_some() && !parquet_export::has_pool(&dataset) {
+        return HttpResponse::BadRequest().body(format!("{} has no pool, so pool is not supported.", dataset));
+    }
+
+    let filter = time_range_query(query.pool.clone(), query.start_time, query.end_time);
+
+    match parquet_export::export_to_bytes(&db, &dataset, filter).await {
+        Ok(bytes) => HttpResponse::Ok()
+            .content_type("application/vnd.apache.parquet")
+            .insert_header(("Content-Disposition", format!("attachment; filename=\"{}.parquet\"", dataset)))
+            .body(bytes),
+        Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
+    }
+}
@@ -0,0 +1,87 @@
+use crate::services::{
+    db::Database,
+    graphql::{self, MetricsSchema},
+};
+use actix_web::{
+    get, post,
+    web::{Data, Json},
+    HttpResponse,
+};
+use async_graphql::http::GraphiQLSource;
+
+#[derive(utoipa::ToSchema)]
+#[allow(dead_code)]
+pub struct GraphQLRequest {
+    /// GraphQL document, e.g. a depths, swaps and earnings selection for the same pool and range
+    #[schema(example = "{ depths(args: { pool: \"BTC.BTC\", interval: \"day\" }) { start_time asset_depth } swaps(args: { pool: \"BTC.BTC\", interval: \"day\" }) { start_time total_volume_usd } earnings(args: { pool: \"BTC.BTC\", interval: \"day\" }) { start_time rewards summary { rune_price_usd } } }")]
+    pub query: String,
+
+    /// Operation to run when the document holds several
+    pub operation_name: Option<String>,
+
+    /// Values of the document's variables
+    #[schema(value_type = Object)]
+    pub variables: Option<serde_json::Value>,
+}
+
+#[derive(utoipa::ToSchema)]
+#[allow(dead_code)]
+pub struct GraphQLResponse {
+    /// Selected fields, keyed by root field or alias
+    #[schema(value_type = Object)]
+    pub data: serde_json::Value,
+
+    /// Validation and resolver errors, e.g. an invalid interval, omitted when there are none
+    #[schema(value_type = Vec<Object>)]
+    pub errors: Option<Vec<serde_json::Value>>,
+}
+
+/// Query depths, swaps, earnings, earnings summaries and RUNEPool history in one request
+#[utoipa::path(
+    post,
+    path = "/graphql",
+    request_body = GraphQLRequest,
+    responses(
+        (status = 200, description = "GraphQL response. Invalid arguments are reported in errors", body = GraphQLResponse)
+    ),
+    tag = "GraphQL"
+)]
+#[post("/graphql")]
+pub async fn graphql_query(
+    db: Data<Database>,
+    schema: Data<MetricsSchema>,
+    request: Json<async_graphql::Request>,
+) -> HttpResponse {
+    let request = request.into_inner().data(graphql::summary_loader(db));
+    HttpResponse::Ok().json(schema.execute(request).await)
+}
+
+/// GraphiQL explorer for the GraphQL endpoint
+#[utoipa::path(
+    get,
+    path = "/graphql",
+    responses(
+        (status = 200, description = "GraphiQL page", content_type = "text/html")
+    ),
+    tag = "GraphQL"
+)]
+#[get("/graphql")]
+pub async fn graphql_explorer() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(GraphiQLSource::build().endpoint("/graphql").finish())
+}
+
+/// Get the GraphQL schema in SDL
+#[utoipa::path(
+    get,
+    path = "/graphql/schema",
+    responses(
+        (status = 200, description = "Schema definition language of the GraphQL endpoint", content_type = "text/plain")
+    ),
+    tag = "GraphQL"
+)]
+#[get("/graphql/schema")]
+pub async fn graphql_schema(schema: Data<MetricsSchema>) -> HttpResponse {
+    HttpResponse::Ok().content_type("text/plain; charset=utf-8").body(schema.sdl())
+}
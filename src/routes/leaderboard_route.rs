@@ -0,0 +1,133 @@
+use crate::services::db::{Database, RankBy, EARNINGS_SUM_FIELDS, SWAP_SUM_FIELDS};
+use actix_web::{
+    get,
+    web::{Data, Query},
+    HttpResponse,
+};
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+const DEPTH_METRICS: &[(&str, &str, RankBy)] = &[
+    ("members_growth", "members_count", RankBy::Growth),
+    ("units_growth", "units", RankBy::Growth),
+    ("liquidity_units_growth", "liquidity_units", RankBy::Growth),
+    ("members_count", "members_count", RankBy::Closing),
+    ("asset_depth", "asset_depth", RankBy::Closing),
+    ("rune_depth", "rune_depth", RankBy::Closing),
+];
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct LeaderboardQueryParams {
+    #[schema(example = "total_volume_usd")]
+    pub metric: String,
+    #[schema(example = 1653373410)]
+    pub start_time: Option<i64>,
+    #[schema(example = 1666592610)]
+    pub end_time: Option<i64>,
+    #[schema(example = 10, minimum = 1, maximum = 100)]
+    pub n: Option<i64>,
+    #[schema(example = "desc")]
+    pub order: Option<String>,
+}
+
+#[derive(utoipa::ToSchema)]
+#[allow(dead_code)]
+pub struct LeaderboardEntryResponse {
+    /// Position on the leaderboard, starting at 1
+    #[schema(example = 1)]
+    pub rank: i64,
+
+    /// Pool identifier
+    #[schema(example = "BTC.BTC")]
+    pub pool: String,
+
+    /// Value of the metric over the period
+    #[schema(example = 123456789.0)]
+    pub value: f64,
+
+    /// First interval of the pool within the period (UNIX timestamp)
+    #[schema(example = 1653373410)]
+    pub start_time: i64,
+
+    /// Last interval end of the pool within the period (UNIX timestamp)
+    #[schema(example = 1666592610)]
+    pub end_time: i64,
+}
+
+// Maps a metric onto the collection, field and reduction it is ranked by
+fn resolve_metric(metric: &str) -> Option<(&'static str, &'static str, RankBy)> {
+    if let Some(field) = SWAP_SUM_FIELDS.iter().find(|field| **field == metric) {
+        return Some(("swaps", field, RankBy::Sum));
+    }
+
+    if let Some(field) = EARNINGS_SUM_FIELDS.iter().find(|field| **field == metric) {
+        return Some(("earnings", field, RankBy::Sum));
+    }
+
+    DEPTH_METRICS
+        .iter()
+        .find(|(name, _, _)| *name == metric)
+        .map(|(_, field, rank_by)| ("depths", *field, *rank_by))
+}
+
+/// Get the top pools by a metric
+#[utoipa::path(
+    get,
+    path = "/leaderboard",
+    params(
+        ("metric" = String, Query, description = "Metric to rank by: a summed swaps field (e.g. total_volume_usd, total_fees, total_count), a summed earnings field (e.g. total_liquidity_fees_rune, rewards, saver_earning), members_growth, units_growth, liquidity_units_growth, or a closing depth (members_count, asset_depth, rune_depth)"),
+        ("start_time" = Option<i64>, Query, description = "Start time Unix timestamp"),
+        ("end_time" = Option<i64>, Query, description = "End time Unix timestamp"),
+        ("n" = Option<i64>, Query, description = "Number of pools (1-100), defaults to 10"),
+        ("order" = Option<String>, Query, description = "desc for the highest values first (default), asc for the lowest")
+    ),
+    responses(
+        (status = 200, description = "Ranked pools", body = [LeaderboardEntryResponse]),
+        (status = 400, description = "Bad request - Invalid parameters"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Leaderboard"
+)]
+#[get("/leaderboard")]
+pub async fn get_leaderboard(
+    db: Data<Database>,
+    query: Query<LeaderboardQueryParams>,
+) -> HttpResponse {
+    let Some((dataset, field, rank_by)) = resolve_metric(&query.metric) else {
+        let metrics: Vec<&str> = SWAP_SUM_FIELDS
+            .iter()
+            .chain(EARNINGS_SUM_FIELDS)
+            .copied()
+            .chain(DEPTH_METRICS.iter().map(|(name, _, _)| *name))
+            .collect();
+        return HttpResponse::BadRequest().body(format!("metric must be one of: {:?}", metrics));
+    };
+
+    if let (Some(start), Some(end)) = (query.start_time, query.end_time) {
+        if start >= end {
+            return HttpResponse::BadRequest().body("start_time must be less than end_time.");
+        }
+    }
+
+    let valid_ordering = vec!["asc", "desc"];
+    let order = match query.order.as_deref() {
+        None | Some("desc") => -1,
+        Some("asc") => 1,
+        Some(_) => return HttpResponse::BadRequest().body(format!("order must be one of: {:?}", valid_ordering)),
+    };
+
+    let n = query.n.unwrap_or(10);
+    if !(1..=100).contains(&n) {
+        return HttpResponse::BadRequest().body("n must be between 1 and 100.");
+    }
+
+    match db.get_leaderboard(dataset, field, rank_by, query.start_time, query.end_time, n, order).await {
+        Ok(mut rows) => {
+            for (index, row) in rows.iter_mut().enumerate() {
+                row.insert("rank", index as i64 + 1);
+            }
+            HttpResponse::Ok().json(rows)
+        }
+        Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
+    }
+}
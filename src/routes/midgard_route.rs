@@ -0,0 +1,180 @@
+use crate::services::{db::{interval_duration, Database}, midgard_proxy};
+use actix_web::{
+    get,
+    web::{Data, Path, Query},
+    HttpResponse,
+};
+use serde::Deserialize;
+
+
+#[derive(Debug, Deserialize, utoipa::ToSchema)]
+pub struct MidgardHistoryQueryParams {
+    #[schema(example = "day")]
+    pub interval: Option<String>,
+    #[schema(example = 10, minimum = 1, maximum = 400)]
+    pub count: Option<i64>,
+    #[schema(example = 1653373410)]
+    pub from: Option<i64>,
+    #[schema(example = 1666592610)]
+    pub to: Option<i64>,
+    #[schema(example = "BTC.BTC")]
+    pub pool: Option<String>,
+}
+
+// Validates the Midgard style query and resolves it into a bucket size and a count
+fn parse_midgard_query(query: &MidgardHistoryQueryParams) -> Result<(Option<i64>, i64), String> {
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from >= to {
+            return Err("from must be less than to.".to_string());
+        }
+    }
+
+    let valid_interval = vec!["hour", "day", "week", "month", "quarter", "year"];
+    if let Some(ref interval) = query.interval {
+        if !valid_interval.contains(&interval.as_str()) {
+            return Err(format!("interval must be one of: {:?}", valid_interval));
+        }
+    }
+
+    let count = query.count.unwrap_or(midgard_proxy::MAX_COUNT);
+    if !(1..=midgard_proxy::MAX_COUNT).contains(&count) {
+        return Err(format!("count must be between 1 and {}.", midgard_proxy::MAX_COUNT));
+    }
+
+    Ok((query.interval.as_deref().map(interval_duration), count))
+}
+
+/// Midgard compatible swaps history
+#[utoipa::path(
+    get,
+    path = "/v2/history/swaps",
+    params(
+        ("interval" = Option<String>, Query, description = "Interval of the buckets (hour, day, week, month, quarter, year). Without it a single interval covers the whole range"),
+        ("count" = Option<i64>, Query, description = "Number of intervals to return (1-400)"),
+        ("from" = Option<i64>, Query, description = "Start time Unix timestamp"),
+        ("to" = Option<i64>, Query, description = "End time Unix timestamp"),
+        ("pool" = Option<String>, Query, description = "Pool identifier, all pools are summed when omitted")
+    ),
+    responses(
+        (status = 200, description = "Swaps history in the Midgard v2 format (meta and intervals, camelCase, numbers as strings)"),
+        (status = 400, description = "Bad request - Invalid parameters"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Midgard Compatible"
+)]
+#[get("/v2/history/swaps")]
+pub async fn get_midgard_swaps_history(
+    db: Data<Database>,
+    query: Query<MidgardHistoryQueryParams>,
+) -> HttpResponse {
+    let (interval_duration, count) = match parse_midgard_query(&query) {
+        Ok(parsed) => parsed,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+
+    match midgard_proxy::get_swaps_history(&db, query.pool.clone(), query.from, query.to, interval_duration, count).await {
+        Ok(history) => HttpResponse::Ok().json(history),
+        Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
+    }
+}
+
+/// Midgard compatible depth and price history
+#[utoipa::path(
+    get,
+    path = "/v2/history/depths/{pool}",
+    params(
+        ("pool" = String, Path, description = "Pool identifier"),
+        ("interval" = Option<String>, Query, description = "Interval of the buckets (hour, day, week, month, quarter, year). Without it a single interval covers the whole range"),
+        ("count" = Option<i64>, Query, description = "Number of intervals to return (1-400)"),
+        ("from" = Option<i64>, Query, description = "Start time Unix timestamp"),
+        ("to" = Option<i64>, Query, description = "End time Unix timestamp")
+    ),
+    responses(
+        (status = 200, description = "Depth and price history in the Midgard v2 format (meta and intervals, camelCase, numbers as strings)"),
+        (status = 400, description = "Bad request - Invalid parameters"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Midgard Compatible"
+)]
+#[get("/v2/history/depths/{pool}")]
+pub async fn get_midgard_depths_history(
+    db: Data<Database>,
+    pool: Path<String>,
+    query: Query<MidgardHistoryQueryParams>,
+) -> HttpResponse {
+    let (interval_duration, count) = match parse_midgard_query(&query) {
+        Ok(parsed) => parsed,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+
+    match midgard_proxy::get_depths_history(&db, pool.into_inner(), query.from, query.to, interval_duration, count).await {
+        Ok(history) => HttpResponse::Ok().json(history),
+        Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
+    }
+}
+
+/// Midgard compatible earnings history
+#[utoipa::path(
+    get,
+    path = "/v2/history/earnings",
+    params(
+        ("interval" = Option<String>, Query, description = "Interval of the buckets (hour, day, week, month, quarter, year). Without it a single interval covers the whole range"),
+        ("count" = Option<i64>, Query, description = "Number of intervals to return (1-400)"),
+        ("from" = Option<i64>, Query, description = "Start time Unix timestamp"),
+        ("to" = Option<i64>, Query, description = "End time Unix timestamp")
+    ),
+    responses(
+        (status = 200, description = "Earnings history in the Midgard v2 format (meta and intervals, camelCase, numbers as strings)"),
+        (status = 400, description = "Bad request - Invalid parameters"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Midgard Compatible"
+)]
+#[get("/v2/history/earnings")]
+pub async fn get_midgard_earnings_history(
+    db: Data<Database>,
+    query: Query<MidgardHistoryQueryParams>,
+) -> HttpResponse {
+    let (interval_duration, count) = match parse_midgard_query(&query) {
+        Ok(parsed) => parsed,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+
+    match midgard_proxy::get_earnings_history(&db, query.from, query.to, interval_duration, count).await {
+        Ok(history) => HttpResponse::Ok().json(history),
+        Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
+    }
+}
+
+/// Midgard compatible RUNEPool members and units history
+#[utoipa::path(
+    get,
+    path = "/v2/history/runepool",
+    params(
+        ("interval" = Option<String>, Query, description = "Interval of the buckets (hour, day, week, month, quarter, year). Without it a single interval covers the whole range"),
+        ("count" = Option<i64>, Query, description = "Number of intervals to return (1-400)"),
+        ("from" = Option<i64>, Query, description = "Start time Unix timestamp"),
+        ("to" = Option<i64>, Query, description = "End time Unix timestamp")
+    ),
+    responses(
+        (status = 200, description = "RUNEPool history in the Midgard v2 format (meta and intervals, camelCase, numbers as strings)"),
+        (status = 400, description = "Bad request - Invalid parameters"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Midgard Compatible"
+)]
+#[get("/v2/history/runepool")]
+pub async fn get_midgard_rune_pool_history(
+    db: Data<Database>,
+    query: Query<MidgardHistoryQueryParams>,
+) -> HttpResponse {
+    let (interval_duration, count) = match parse_midgard_query(&query) {
+        Ok(parsed) => parsed,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+
+    match midgard_proxy::get_rune_pool_history(&db, query.from, query.to, interval_duration, count).await {
+        Ok(history) => HttpResponse::Ok().json(history),
+        Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
+    }
+}
@@ -6,4 +6,17 @@ pub mod rune_pool_scraper;
 pub mod swaps_route;
 pub mod rpmuh_route;
 pub mod earnings_route;
-pub mod scraper_cron;
\ No newline at end of file
+pub mod scraper_cron;
+pub mod midgard_route;
+pub mod export_route;
+pub mod analytics_route;
+pub mod candles_route;
+pub mod quote_route;
+pub mod compare_route;
+pub mod leaderboard_route;
+pub mod snapshot_route;
+pub mod rune_price_route;
+pub mod anomalies_route;
+pub mod alerts_route;
+pub mod stream_route;
+pub mod graphql_route;
\ No newline at end of file
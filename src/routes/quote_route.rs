@@ -0,0 +1,61 @@
+use crate::services::{db::Database, quote};
+use actix_web::{
+    get,
+    web::{Data, Query},
+    HttpResponse,
+};
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct QuoteQueryParams {
+    #[schema(example = "BTC.BTC")]
+    pub from_asset: String,
+    #[schema(example = "ETH.ETH")]
+    pub to_asset: String,
+    #[schema(example = 1.5)]
+    pub amount: f64,
+    #[schema(example = 1666592610)]
+    pub at: Option<i64>,
+}
+
+/// Estimate a swap from the stored pool depths
+#[utoipa::path(
+    get,
+    path = "/quote",
+    params(
+        ("from_asset" = String, Query, description = "Asset to swap from, a pool identifier or THOR.RUNE"),
+        ("to_asset" = String, Query, description = "Asset to swap to, a pool identifier or THOR.RUNE. Two pools make a double swap through RUNE"),
+        ("amount" = f64, Query, description = "Amount to swap in whole units of from_asset"),
+        ("at" = Option<i64>, Query, description = "Unix timestamp of the depths to quote against, defaults to the latest snapshot")
+    ),
+    responses(
+        (status = 200, description = "Expected output, liquidity fees and slip per leg", body = quote::Quote),
+        (status = 400, description = "Bad request - Invalid parameters"),
+        (status = 404, description = "No depth history for a pool on the route at the requested time"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Quote"
+)]
+#[get("/quote")]
+pub async fn get_quote(
+    db: Data<Database>,
+    query: Query<QuoteQueryParams>,
+) -> HttpResponse {
+    let from_asset = query.from_asset.to_uppercase();
+    let to_asset = query.to_asset.to_uppercase();
+
+    if from_asset == to_asset {
+        return HttpResponse::BadRequest().body("from_asset and to_asset must differ.");
+    }
+
+    if !(query.amount.is_finite() && query.amount > 0.0) {
+        return HttpResponse::BadRequest().body("amount must be a positive number.");
+    }
+
+    match quote::get_quote(&db, &from_asset, &to_asset, query.amount, query.at).await {
+        Ok(Some(quote)) => HttpResponse::Ok().json(quote),
+        Ok(None) => HttpResponse::NotFound().body("No depth history for the pools on this route at the requested time."),
+        Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
+    }
+}
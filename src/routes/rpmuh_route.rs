@@ -1,4 +1,5 @@
-use crate::services::db::Database;
+use crate::models::rpmuh_model::FIELDS;
+use crate::services::{db::Database, query_builder::{build_filter, build_projection, build_sort, Fill, HistoryQuery}};
 use actix_web::{
     get,
     web::{Data, Query},
@@ -17,12 +18,16 @@ pub struct RunePoolHistoryQueryParams {
     pub page: Option<u32>,
     #[schema(example = 10, minimum = 1, maximum = 100)]
     pub limit: Option<u32>,
-    #[schema(example = "units")]
+    #[schema(example = "-units")]
     pub sort_by: Option<String>,
     #[schema(example = "asc")]
     pub order: Option<String>,
     #[schema(example = "day")]
     pub interval: Option<String>,
+    #[schema(example = "start_time,units")]
+    pub fields: Option<String>,
+    #[schema(example = "count>=300")]
+    pub filter: Option<String>,
 }
 
 
@@ -55,9 +60,11 @@ pub struct RunePoolHistoryResponse {
         ("end_time" = Option<i64>, Query, description = "End time Unix timestamp"),
         ("page" = Option<u32>, Query, description = "Page number (minimum: 1)"),
         ("limit" = Option<u32>, Query, description = "Items per page (1-100)"),
-        ("sort_by" = Option<String>, Query, description = "Field to sort by"),
+        ("sort_by" = Option<String>, Query, description = "Comma separated fields to sort by, prefix a field with - to sort it descending."),
         ("order" = Option<String>, Query, description = "Sort order (asc or desc)"),
-        ("interval" = Option<String>, Query, description = "Time interval for aggregation (hour, day, week, month, quarter, year)")
+        ("interval" = Option<String>, Query, description = "Time interval for aggregation (hour, day, week, month, quarter, year)"),
+        ("fields" = Option<String>, Query, description = "Comma separated fields to return, all fields when omitted. Accepts the same fields as sort_by"),
+        ("filter" = Option<String>, Query, description = "Comma separated numeric conditions on the returned rows, e.g. count>=300,units>0. Operators: >=, <=, !=, >, <, =")
     ),
     responses(
         (status = 200, description = "List of rune pool history", body = Vec<RunePoolHistoryResponse>),
@@ -107,16 +114,39 @@ pub async fn get_rune_pool_history(
         }
     }
 
+    let sort_doc = match build_sort(query.sort_by.as_deref(), sort_order, FIELDS) {
+        Ok(sort_doc) => sort_doc,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+
+    let projection = match build_projection(query.fields.as_deref(), FIELDS) {
+        Ok(projection) => projection,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+
+    let filter = match build_filter(query.filter.as_deref(), FIELDS) {
+        Ok(filter) => filter,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+
+    let options = HistoryQuery {
+        start_time: query.start_time,
+        end_time: query.end_time,
+        pools: Vec::new(),
+        chain: None,
+        group_by_pool: false,
+        page,
+        limit: Some(limit),
+        sort_doc,
+        interval: query.interval.clone(),
+        projection,
+        filter,
+        fill: Fill::None,
+        derived: false,
+    };
+
     match db
-        .get_rune_pool_history(
-            query.start_time,
-            query.end_time,
-            page,
-            limit,
-            query.sort_by.clone(),
-            sort_order,
-            query.interval.clone()
-        )
+        .get_rune_pool_history(options)
         .await
     {
         Ok(history) => HttpResponse::Ok().json(history),
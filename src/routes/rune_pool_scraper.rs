@@ -28,7 +28,7 @@ pub async fn fetch_and_store_rune_pool(
         return HttpResponse::Unauthorized().body("Wrong secret key.");
     }
 
-    match services::rpmuh_fetcher::fetch_and_store_rune_pool_history(&db, &params.interval, params.start_time).await {
+    match services::rpmuh_fetcher::fetch_and_store_rune_pool_history(&db, &params.interval, params.start_time, false).await {
         Ok(()) => HttpResponse::Ok().body("Rune pool history fetched and stored successfully."),
         Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
     }
@@ -0,0 +1,65 @@
+use crate::services::{analytics, db::{interval_duration, Database}};
+use actix_web::{
+    get,
+    web::{Data, Query},
+    HttpResponse,
+};
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RunePriceQueryParams {
+    #[schema(example = "day")]
+    pub interval: Option<String>,
+    #[schema(example = 1653373410)]
+    pub start_time: Option<i64>,
+    #[schema(example = 1666592610)]
+    pub end_time: Option<i64>,
+    #[schema(example = 500, minimum = 1, maximum = 1000)]
+    pub limit: Option<i64>,
+}
+
+/// Get the RUNE price history in USD
+#[utoipa::path(
+    get,
+    path = "/rune_price",
+    params(
+        ("interval" = Option<String>, Query, description = "Candle size (hour, day, week, month, quarter, year), defaults to day"),
+        ("start_time" = Option<i64>, Query, description = "Start time Unix timestamp. Without it the latest candles are returned"),
+        ("end_time" = Option<i64>, Query, description = "End time Unix timestamp"),
+        ("limit" = Option<i64>, Query, description = "Maximum number of candles (1-1000), defaults to 500")
+    ),
+    responses(
+        (status = 200, description = "Candles oldest first. Each hour is read from the earnings summary, or from the swap history when the summary has no price for it", body = [analytics::RunePriceCandle]),
+        (status = 400, description = "Bad request - Invalid parameters"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "RUNE Price"
+)]
+#[get("/rune_price")]
+pub async fn get_rune_price(
+    db: Data<Database>,
+    query: Query<RunePriceQueryParams>,
+) -> HttpResponse {
+    if let (Some(start), Some(end)) = (query.start_time, query.end_time) {
+        if start >= end {
+            return HttpResponse::BadRequest().body("start_time must be less than end_time.");
+        }
+    }
+
+    let valid_interval = vec!["hour", "day", "week", "month", "quarter", "year"];
+    let interval = query.interval.as_deref().unwrap_or("day");
+    if !valid_interval.contains(&interval) {
+        return HttpResponse::BadRequest().body(format!("interval must be one of: {:?}", valid_interval));
+    }
+
+    let limit = query.limit.unwrap_or(500);
+    if !(1..=1000).contains(&limit) {
+        return HttpResponse::BadRequest().body("limit must be between 1 and 1000.");
+    }
+
+    match analytics::get_rune_price(&db, query.start_time, query.end_time, interval_duration(interval), limit).await {
+        Ok(candles) => HttpResponse::Ok().json(candles),
+        Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
+    }
+}
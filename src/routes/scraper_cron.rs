@@ -38,18 +38,21 @@ pub async fn run_all_jobs(
         &params.pool,
         &interval,
         one_hour_ago,
+        true,
     ).await;
 
     let rune_pool_result = services::rpmuh_fetcher::fetch_and_store_rune_pool_history(
         &db,
         &interval,
         one_hour_ago,
+        true,
     ).await;
 
     let earnings_result = services::earnings_fetcher::fetch_and_store_earnings(
         &db,
         &interval,
         one_hour_ago,
+        true,
     ).await;
 
     let depth_result = services::depth_history_fetcher::fetch_and_store_depth_history(
@@ -57,6 +60,7 @@ pub async fn run_all_jobs(
         &params.pool,
         &interval,
         one_hour_ago,
+        true,
     ).await;
 
     let results = vec![swap_result, rune_pool_result, earnings_result, depth_result];
@@ -0,0 +1,83 @@
+use crate::routes::{depth_route::PoolDepthPriceHistoryResponse, rpmuh_route::RunePoolHistoryResponse};
+use crate::services::{
+    db::{number, Database},
+    query_builder::parse_pools,
+};
+use actix_web::{
+    get,
+    web::{Data, Query},
+    HttpResponse,
+};
+use serde::Deserialize;
+use serde_json::json;
+use utoipa::ToSchema;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SnapshotQueryParams {
+    #[schema(example = 1666592610)]
+    pub at: Option<i64>,
+    #[schema(example = "BTC.BTC,ETH.ETH")]
+    pub pool: Option<String>,
+}
+
+#[derive(utoipa::ToSchema)]
+#[allow(dead_code)]
+pub struct SnapshotResponse {
+    /// Requested time (UNIX timestamp), null for the latest state
+    #[schema(example = 1666592610)]
+    pub at: Option<i64>,
+
+    /// RUNE price in USD from the earnings summary in effect at that time
+    #[schema(example = 1.52)]
+    pub rune_price_usd: Option<f64>,
+
+    /// RUNEPool row in effect at that time
+    pub rune_pool: Option<RunePoolHistoryResponse>,
+
+    /// Depth row in effect at that time for every pool, ordered by pool
+    pub pools: Vec<PoolDepthPriceHistoryResponse>,
+}
+
+/// Get the state of every pool at a point in time
+#[utoipa::path(
+    get,
+    path = "/snapshot",
+    params(
+        ("at" = Option<i64>, Query, description = "Unix timestamp, the latest row starting at or before it is used. Defaults to the latest state"),
+        ("pool" = Option<String>, Query, description = "Comma separated pool identifiers, all pools when omitted")
+    ),
+    responses(
+        (status = 200, description = "Pool depths, RUNEPool and RUNE price as of the requested time", body = SnapshotResponse),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "Snapshot"
+)]
+#[get("/snapshot")]
+pub async fn get_snapshot(
+    db: Data<Database>,
+    query: Query<SnapshotQueryParams>,
+) -> HttpResponse {
+    let pools = parse_pools(query.pool.as_deref());
+
+    let depths = match db.depth_snapshots_at(&pools, query.at).await {
+        Ok(depths) => depths,
+        Err(err) => return HttpResponse::InternalServerError().body(err.to_string()),
+    };
+
+    let rune_pool = match db.rune_pool_snapshot_at(query.at).await {
+        Ok(rune_pool) => rune_pool,
+        Err(err) => return HttpResponse::InternalServerError().body(err.to_string()),
+    };
+
+    let rune_price_usd = match db.earnings_summary_at(query.at).await {
+        Ok(summary) => summary.map(|summary| number(&summary, "rune_price_usd")),
+        Err(err) => return HttpResponse::InternalServerError().body(err.to_string()),
+    };
+
+    HttpResponse::Ok().json(json!({
+        "at": query.at,
+        "rune_price_usd": rune_price_usd,
+        "rune_pool": rune_pool,
+        "pools": depths,
+    }))
+}
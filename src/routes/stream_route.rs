@@ -0,0 +1,168 @@
+use crate::services::{
+    db::Database,
+    live_feed::{self, LiveEvent, LiveFilter},
+    query_builder::parse_pools,
+};
+use actix_web::{
+    get,
+    rt,
+    web::{Bytes, Data, Payload, Query},
+    HttpRequest, HttpResponse,
+};
+use futures_util::stream::{self, StreamExt};
+use serde::Deserialize;
+use tokio::{
+    sync::broadcast::{error::RecvError, Receiver},
+    time::{interval, Duration, Interval},
+};
+use utoipa::ToSchema;
+
+/// Idle connections get a keepalive so proxies do not close them
+const KEEPALIVE: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct StreamQueryParams {
+    #[schema(example = "swaps,depths")]
+    pub dataset: Option<String>,
+    #[schema(example = "BTC.BTC,ETH.ETH")]
+    pub pool: Option<String>,
+}
+
+fn parse_filter(query: &StreamQueryParams) -> Result<LiveFilter, String> {
+    Ok(LiveFilter {
+        datasets: live_feed::parse_datasets(query.dataset.as_deref())?,
+        pools: parse_pools(query.pool.as_deref()),
+    })
+}
+
+enum Next {
+    Event(LiveEvent),
+    Skipped(u64),
+    Keepalive,
+    Closed,
+}
+
+// Waits for the next event passing the filter, or for the keepalive to come due
+async fn next_event(receiver: &mut Receiver<LiveEvent>, filter: &LiveFilter, keepalive: &mut Interval) -> Next {
+    loop {
+        tokio::select! {
+            received = receiver.recv() => match received {
+                Ok(event) if filter.matches(&event) => return Next::Event(event),
+                Ok(_) => continue,
+                Err(RecvError::Lagged(skipped)) => return Next::Skipped(skipped),
+                Err(RecvError::Closed) => return Next::Closed,
+            },
+            _ = keepalive.tick() => return Next::Keepalive,
+        }
+    }
+}
+
+/// Subscribe to newly stored intervals as server-sent events
+#[utoipa::path(
+    get,
+    path = "/stream",
+    params(
+        ("dataset" = Option<String>, Query, description = "Comma separated datasets (swaps, depths, earnings, earnings_summary, runepool), all when omitted"),
+        ("pool" = Option<String>, Query, description = "Comma separated pool identifiers, all pools when omitted. Datasets without a pool are left out when set")
+    ),
+    responses(
+        (status = 200, description = "text/event-stream of closed intervals as the hourly ingestion inserts them, the hour in progress, refreshed and imported intervals are not sent. The event name is the dataset, the data a JSON LiveEvent", body = LiveEvent),
+        (status = 400, description = "Bad request - Invalid parameters")
+    ),
+    tag = "Stream"
+)]
+#[get("/stream")]
+pub async fn stream_events(
+    db: Data<Database>,
+    query: Query<StreamQueryParams>,
+) -> HttpResponse {
+    let filter = match parse_filter(&query) {
+        Ok(filter) => filter,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+
+    let state = (db.subscribe(), filter, interval(KEEPALIVE));
+
+    let events = stream::unfold(state, |(mut receiver, filter, mut keepalive)| async move {
+        let chunk = match next_event(&mut receiver, &filter, &mut keepalive).await {
+            Next::Event(event) => {
+                let data = serde_json::to_string(&event).unwrap_or_default();
+                format!("event: {}\ndata: {}\n\n", event.dataset, data)
+            }
+            Next::Skipped(skipped) => format!(": skipped {} events\n\n", skipped),
+            Next::Keepalive => ": keepalive\n\n".to_string(),
+            Next::Closed => return None,
+        };
+
+        Some((Ok::<_, actix_web::Error>(Bytes::from(chunk)), (receiver, filter, keepalive)))
+    });
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(events)
+}
+
+/// Subscribe to newly stored intervals over a WebSocket
+#[utoipa::path(
+    get,
+    path = "/stream/ws",
+    params(
+        ("dataset" = Option<String>, Query, description = "Comma separated datasets (swaps, depths, earnings, earnings_summary, runepool), all when omitted"),
+        ("pool" = Option<String>, Query, description = "Comma separated pool identifiers, all pools when omitted. Datasets without a pool are left out when set")
+    ),
+    responses(
+        (status = 101, description = "WebSocket sending every closed interval the hourly ingestion inserts as a JSON LiveEvent text message", body = LiveEvent),
+        (status = 400, description = "Bad request - Invalid parameters")
+    ),
+    tag = "Stream"
+)]
+#[get("/stream/ws")]
+pub async fn stream_websocket(
+    db: Data<Database>,
+    query: Query<StreamQueryParams>,
+    req: HttpRequest,
+    body: Payload,
+) -> Result<HttpResponse, actix_web::Error> {
+    let filter = match parse_filter(&query) {
+        Ok(filter) => filter,
+        Err(message) => return Ok(HttpResponse::BadRequest().body(message)),
+    };
+
+    let (response, mut session, mut messages) = actix_ws::handle(&req, body)?;
+    let mut receiver = db.subscribe();
+
+    rt::spawn(async move {
+        let mut keepalive = interval(KEEPALIVE);
+
+        loop {
+            tokio::select! {
+                next = next_event(&mut receiver, &filter, &mut keepalive) => {
+                    let sent = match next {
+                        Next::Event(event) => session.text(serde_json::to_string(&event).unwrap_or_default()).await,
+                        Next::Skipped(_) => Ok(()),
+                        Next::Keepalive => session.ping(b"").await,
+                        Next::Closed => break,
+                    };
+
+                    if sent.is_err() {
+                        return;
+                    }
+                }
+                message = messages.next() => match message {
+                    Some(Ok(actix_ws::Message::Ping(bytes))) => {
+                        if session.pong(&bytes).await.is_err() {
+                            return;
+                        }
+                    }
+                    Some(Ok(actix_ws::Message::Close(_))) | None | Some(Err(_)) => break,
+                    Some(Ok(_)) => {}
+                },
+            }
+        }
+
+        let _ = session.close(None).await;
+    });
+
+    Ok(response)
+}
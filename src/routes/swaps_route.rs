@@ -1,4 +1,8 @@
-use crate::services::db::Database;
+use crate::models::swap_history_model::FIELDS;
+use crate::services::{
+    db::Database,
+    query_builder::{build_filter, build_projection, build_sort, parse_chain, parse_fill, parse_group_by, parse_pools, HistoryQuery},
+};
 use actix_web::{
     get,
     web::{Data, Query},
@@ -13,18 +17,28 @@ pub struct SwapHistoryQueryParams {
     pub start_time: Option<i64>,
     #[schema(example = 1666592610)]
     pub end_time: Option<i64>,
-    #[schema(example = "BTC.BTC")]
+    #[schema(example = "BTC.BTC,ETH.ETH")]
     pub pool: Option<String>,
+    #[schema(example = "ETH")]
+    pub chain: Option<String>,
     #[schema(example = 1, minimum = 1)]
     pub page: Option<u32>,
     #[schema(example = 10, minimum = 1, maximum = 100)]
     pub limit: Option<u32>,
-    #[schema(example = "synth_mint_volume")]
+    #[schema(example = "pool,-total_volume_usd")]
     pub sort_by: Option<String>,
     #[schema(example = "asc")]
     pub order: Option<String>,
     #[schema(example = "day")]
     pub interval: Option<String>,
+    #[schema(example = "pool,start_time,total_volume_usd")]
+    pub fields: Option<String>,
+    #[schema(example = "total_volume_usd>1000000")]
+    pub filter: Option<String>,
+    #[schema(example = "pool")]
+    pub group_by: Option<String>,
+    #[schema(example = "zero")]
+    pub fill: Option<String>,
 }
 
 #[derive(utoipa::ToSchema)]
@@ -194,12 +208,17 @@ pub struct PoolSwapHistoryResponse {
     params(
         ("start_time" = Option<i64>, Query, description = "Start time Unix timestamp"),
         ("end_time" = Option<i64>, Query, description = "End time Unix timestamp"),
-        ("pool" = Option<String>, Query, description = "Pool identifier"),
+        ("pool" = Option<String>, Query, description = "Comma separated pool identifiers"),
+        ("chain" = Option<String>, Query, description = "Only pools of this chain, e.g. ETH matches every ETH.* pool"),
         ("page" = Option<u32>, Query, description = "Page number (minimum: 1)"),
         ("limit" = Option<u32>, Query, description = "Items per page (1-100)"),
-        ("sort_by" = Option<String>, Query, description = "Field to sort by"),
+        ("sort_by" = Option<String>, Query, description = "Comma separated fields to sort by, prefix a field with - to sort it descending."),
         ("order" = Option<String>, Query, description = "Sort order (asc or desc)"),
-        ("interval" = Option<String>, Query, description = "Time interval for aggregation (hour, day, week, month, quarter, year)")
+        ("interval" = Option<String>, Query, description = "Time interval for aggregation (hour, day, week, month, quarter, year)"),
+        ("fields" = Option<String>, Query, description = "Comma separated fields to return, all fields when omitted. Accepts the same fields as sort_by"),
+        ("filter" = Option<String>, Query, description = "Comma separated numeric conditions on the returned rows, e.g. total_fees>1000,average_slip<=20. Operators: >=, <=, !=, >, <, ="),
+        ("group_by" = Option<String>, Query, description = "Set to pool to return a separate row per pool within each interval instead of merging all pools"),
+        ("fill" = Option<String>, Query, description = "Emit every bucket of the range (none, zero, null, previous), e.g. zero to emit empty buckets with zero volumes. Filled buckets have filled set to true")
     ),
     responses(
         (status = 200, description = "List of pool swap history", body = Vec<PoolSwapHistoryResponse>),
@@ -249,17 +268,59 @@ pub async fn get_pool_swap_history(
         }
     }
 
+    let mut sort_doc = match build_sort(query.sort_by.as_deref(), sort_order, FIELDS) {
+        Ok(sort_doc) => sort_doc,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+
+    let projection = match build_projection(query.fields.as_deref(), FIELDS) {
+        Ok(projection) => projection,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+
+    let filter = match build_filter(query.filter.as_deref(), FIELDS) {
+        Ok(filter) => filter,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+
+    let chain = match parse_chain(query.chain.as_deref()) {
+        Ok(chain) => chain,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+
+    let group_by_pool = match parse_group_by(query.group_by.as_deref()) {
+        Ok(group_by_pool) => group_by_pool,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+
+    let fill = match parse_fill(query.fill.as_deref()) {
+        Ok(fill) => fill,
+        Err(message) => return HttpResponse::BadRequest().body(message),
+    };
+
+    // Keep the rows of one interval in a stable pool order
+    if group_by_pool && !sort_doc.contains_key("pool") {
+        sort_doc.insert("pool", 1);
+    }
+
+    let options = HistoryQuery {
+        start_time: query.start_time,
+        end_time: query.end_time,
+        pools: parse_pools(query.pool.as_deref()),
+        chain,
+        group_by_pool,
+        page,
+        limit: Some(limit),
+        sort_doc,
+        interval: query.interval.clone(),
+        projection,
+        filter,
+        fill,
+        derived: false,
+    };
+
     match db
-        .get_pool_swap_history(
-            query.start_time,
-            query.end_time,
-            query.pool.clone(),
-            page,
-            limit,
-            query.sort_by.clone(),
-            sort_order,
-            query.interval.clone()
-        )
+        .get_pool_swap_history(options)
         .await
     {
         Ok(history) => HttpResponse::Ok().json(history),
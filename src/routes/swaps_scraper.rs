@@ -29,7 +29,7 @@ pub async fn fetch_and_store_swaps(
         return HttpResponse::Unauthorized().body("Wrong secret key.");
     }
 
-    match services::swaps_history_fetcher::fetch_and_store_swaps_history(&db, &params.pool, &params.interval, params.start_time).await {
+    match services::swaps_history_fetcher::fetch_and_store_swaps_history(&db, &params.pool, &params.interval, params.start_time, false).await {
         Ok(()) => HttpResponse::Ok().body("Swaps fetched and stored successfully."),
         Err(err) => HttpResponse::InternalServerError().body(err.to_string()),
     }
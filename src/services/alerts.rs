@@ -0,0 +1,395 @@
+use std::time::Duration;
+
+use actix_web::{rt, web::Data};
+use chrono::Utc;
+use serde::Serialize;
+use serde_json::json;
+use utoipa::ToSchema;
+
+use crate::models::{
+    alert_model::{AlertDelivery, AlertDeliveryRequest, AlertRule},
+    depth_history_model, earnings_model, rpmuh_model, swap_history_model,
+};
+
+use super::db::{number, Database};
+
+pub const DATASETS: &[&str] = &["swaps", "depths", "earnings", "runepool"];
+
+/// change: percentage change of `metric` over `window` seconds,
+/// threshold: value of `metric` in the latest closed interval,
+/// stale: seconds since the latest closed row ended
+pub const KINDS: &[&str] = &["change", "threshold", "stale"];
+
+pub const OPERATORS: &[&str] = &[">", ">=", "<", "<="];
+
+const MAX_ATTEMPTS: i64 = 3;
+
+/// Attempts an event gets across evaluations before it is given up on
+const MAX_EVENT_ATTEMPTS: i64 = 9;
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AlertRuleView {
+    #[schema(example = "6717c0f4a1b2c3d4e5f60718")]
+    pub id: String,
+
+    #[schema(example = "BTC depth drop")]
+    pub name: String,
+
+    /// change, threshold or stale
+    #[schema(example = "change")]
+    pub kind: String,
+
+    /// swaps, depths, earnings or runepool
+    #[schema(example = "depths")]
+    pub dataset: String,
+
+    /// Pool the rule watches, null for runepool or for a stale rule across all pools
+    #[schema(example = "BTC.BTC")]
+    pub pool: Option<String>,
+
+    /// Field the rule watches, null for stale rules
+    #[schema(example = "asset_depth")]
+    pub metric: Option<String>,
+
+    /// Fires when the observed value compares to threshold with this operator (>, >=, <, <=)
+    #[schema(example = "<=")]
+    pub operator: String,
+
+    /// Percent for change rules, the metric's unit for threshold rules, seconds for stale rules
+    #[schema(example = -20.0)]
+    pub threshold: f64,
+
+    /// Seconds the change is measured over, change rules only
+    #[schema(example = 3600)]
+    pub window: Option<i64>,
+
+    #[schema(example = "https://hooks.example.com/alerts")]
+    pub webhook_url: String,
+
+    #[schema(example = true)]
+    pub enabled: bool,
+
+    #[schema(example = 1729300000)]
+    pub created_at: i64,
+
+    #[schema(example = 1729300000)]
+    pub updated_at: i64,
+}
+
+impl From<AlertRule> for AlertRuleView {
+    fn from(rule: AlertRule) -> Self {
+        Self {
+            id: rule._id.to_hex(),
+            name: rule.name,
+            kind: rule.kind,
+            dataset: rule.dataset,
+            pool: rule.pool,
+            metric: rule.metric,
+            operator: rule.operator,
+            threshold: rule.threshold,
+            window: rule.window,
+            webhook_url: rule.webhook_url,
+            enabled: rule.enabled,
+            created_at: rule.created_at,
+            updated_at: rule.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AlertDeliveryView {
+    #[schema(example = "6717c1a2a1b2c3d4e5f60719")]
+    pub id: String,
+
+    #[schema(example = "6717c0f4a1b2c3d4e5f60718")]
+    pub rule_id: String,
+
+    /// Identifies the event, an event is delivered at most once
+    #[schema(example = "change:1729299600")]
+    pub event_key: String,
+
+    /// Observed value that fired the rule
+    #[schema(example = -23.4)]
+    pub value: f64,
+
+    #[schema(example = "https://hooks.example.com/alerts")]
+    pub webhook_url: String,
+
+    /// delivered or failed
+    #[schema(example = "delivered")]
+    pub status: String,
+
+    #[schema(example = 1)]
+    pub attempts: i64,
+
+    /// HTTP status of the last attempt, null when no response was received
+    #[schema(example = 200)]
+    pub response_status: Option<i64>,
+
+    /// Error of the last attempt
+    #[schema(example = json!(null))]
+    pub error: Option<String>,
+
+    #[schema(example = 1729303200)]
+    pub fired_at: i64,
+}
+
+impl From<AlertDelivery> for AlertDeliveryView {
+    fn from(delivery: AlertDelivery) -> Self {
+        Self {
+            id: delivery._id.to_hex(),
+            rule_id: delivery.rule_id.to_hex(),
+            event_key: delivery.event_key,
+            value: delivery.value,
+            webhook_url: delivery.webhook_url,
+            status: delivery.status,
+            attempts: delivery.attempts,
+            response_status: delivery.response_status,
+            error: delivery.error,
+            fired_at: delivery.fired_at,
+        }
+    }
+}
+
+fn metric_fields(dataset: &str) -> &'static [&'static str] {
+    match dataset {
+        "swaps" => swap_history_model::FIELDS,
+        "depths" => depth_history_model::FIELDS,
+        "earnings" => earnings_model::FIELDS,
+        _ => rpmuh_model::FIELDS,
+    }
+}
+
+/// Checks that a rule can be evaluated
+pub fn validate_rule(rule: &AlertRule) -> Result<(), String> {
+    if rule.name.trim().is_empty() {
+        return Err("name must not be empty.".to_string());
+    }
+
+    if !KINDS.contains(&rule.kind.as_str()) {
+        return Err(format!("kind must be one of: {:?}", KINDS));
+    }
+
+    if !DATASETS.contains(&rule.dataset.as_str()) {
+        return Err(format!("dataset must be one of: {:?}", DATASETS));
+    }
+
+    if !OPERATORS.contains(&rule.operator.as_str()) {
+        return Err(format!("operator must be one of: {:?}", OPERATORS));
+    }
+
+    if rule.dataset == "runepool" && rule.pool.is_some() {
+        return Err("runepool has no pool, so pool is not supported.".to_string());
+    }
+
+    if rule.kind == "stale" {
+        if rule.metric.is_some() {
+            return Err("metric is not supported by stale rules.".to_string());
+        }
+    } else {
+        let metrics: Vec<&str> = metric_fields(&rule.dataset)
+            .iter()
+            .copied()
+            .filter(|field| !["pool", "start_time", "end_time"].contains(field))
+            .collect();
+
+        match rule.metric.as_deref() {
+            Some(metric) if metrics.contains(&metric) => {}
+            _ => return Err(format!("metric must be one of: {:?}", metrics)),
+        }
+
+        if rule.dataset != "runepool" && rule.pool.is_none() {
+            return Err(format!("{} rules on {} require a pool.", rule.kind, rule.dataset));
+        }
+    }
+
+    match (rule.kind.as_str(), rule.window) {
+        ("change", Some(window)) if window > 0 => {}
+        ("change", _) => return Err("change rules require a positive window in seconds.".to_string()),
+        (_, Some(_)) => return Err("window is only supported by change rules.".to_string()),
+        _ => {}
+    }
+
+    if !rule.webhook_url.starts_with("http://") && !rule.webhook_url.starts_with("https://") {
+        return Err("webhook_url must be an http or https URL.".to_string());
+    }
+
+    Ok(())
+}
+
+fn compare(value: f64, operator: &str, threshold: f64) -> bool {
+    match operator {
+        ">" => value > threshold,
+        ">=" => value >= threshold,
+        "<" => value < threshold,
+        "<=" => value <= threshold,
+        _ => false,
+    }
+}
+
+// Observed value of a rule with the key of the event it belongs to, None when there is not enough
+// data to evaluate it
+async fn observe(db: &Database, rule: &AlertRule) -> Result<Option<(f64, String)>, mongodb::error::Error> {
+    let Some(latest) = db.latest_metric_row(&rule.dataset, rule.pool.as_deref(), None).await? else {
+        return Ok(None);
+    };
+
+    let start_time = number(&latest, "start_time") as i64;
+    let metric = rule.metric.as_deref().unwrap_or_default();
+
+    match rule.kind.as_str() {
+        "stale" => {
+            // Keyed on the latest row, so a gap is reported once until new rows arrive
+            let end_time = number(&latest, "end_time") as i64;
+            Ok(Some(((Utc::now().timestamp() - end_time) as f64, format!("stale:{}", end_time))))
+        }
+        "threshold" => Ok(Some((number(&latest, metric), format!("threshold:{}", start_time)))),
+        _ => {
+            let window = rule.window.unwrap_or_default();
+            let Some(previous) = db
+                .latest_metric_row(&rule.dataset, rule.pool.as_deref(), Some(start_time - window))
+                .await?
+            else {
+                return Ok(None);
+            };
+
+            let previous_value = number(&previous, metric);
+            if previous_value == 0.0 {
+                return Ok(None);
+            }
+
+            let change = (number(&latest, metric) - previous_value) / previous_value.abs() * 100.0;
+            Ok(Some((change, format!("change:{}", start_time))))
+        }
+    }
+}
+
+// Posts the event to the rule's webhook, retrying with a growing delay up to `max_attempts` times
+async fn deliver(
+    client: &reqwest::Client,
+    rule: &AlertRule,
+    value: f64,
+    event_key: &str,
+    max_attempts: i64,
+) -> (i64, Option<i64>, Option<String>, bool) {
+    let payload = json!({
+        "rule_id": rule._id.to_hex(),
+        "name": rule.name,
+        "kind": rule.kind,
+        "dataset": rule.dataset,
+        "pool": rule.pool,
+        "metric": rule.metric,
+        "operator": rule.operator,
+        "threshold": rule.threshold,
+        "window": rule.window,
+        "value": value,
+        "event_key": event_key,
+        "fired_at": Utc::now().timestamp(),
+    });
+
+    let mut response_status = None;
+    let mut error = None;
+
+    for attempt in 1..=max_attempts {
+        match client.post(&rule.webhook_url).json(&payload).send().await {
+            Ok(response) if response.status().is_success() => {
+                return (attempt, Some(response.status().as_u16() as i64), None, true);
+            }
+            Ok(response) => {
+                response_status = Some(response.status().as_u16() as i64);
+                error = Some(format!("webhook responded with {}", response.status()));
+            }
+            Err(e) => {
+                response_status = None;
+                error = Some(e.to_string());
+            }
+        }
+
+        if attempt < max_attempts {
+            tokio::time::sleep(RETRY_DELAY * attempt as u32).await;
+        }
+    }
+
+    (max_attempts, response_status, error, false)
+}
+
+// Delivers one event and logs the outcome
+async fn deliver_and_log(
+    db: Data<Database>,
+    client: reqwest::Client,
+    rule: AlertRule,
+    value: f64,
+    event_key: String,
+    max_attempts: i64,
+) {
+    let (attempts, response_status, error, delivered) = deliver(&client, &rule, value, &event_key, max_attempts).await;
+
+    let delivery = AlertDelivery::try_from(AlertDeliveryRequest {
+        rule_id: rule._id,
+        event_key,
+        value,
+        webhook_url: rule.webhook_url.clone(),
+        status: if delivered { "delivered" } else { "failed" }.to_string(),
+        attempts,
+        response_status,
+        error,
+        fired_at: Utc::now().timestamp(),
+    })
+    .unwrap();
+
+    if let Err(e) = db.create_alert_delivery(delivery).await {
+        eprintln!("Error inserting alert delivery: {:?}", e);
+    }
+}
+
+/// Evaluates every enabled rule and delivers the events that fired and were not delivered yet.
+/// Deliveries run on their own tasks, so a slow webhook holds up neither the other rules nor the
+/// caller. Failed deliveries are logged and retried on the next evaluation until the event used up
+/// its attempts.
+pub async fn evaluate_rules(db: &Data<Database>) {
+    let rules = match db.get_alert_rules(true).await {
+        Ok(rules) => rules,
+        Err(e) => {
+            eprintln!("Error loading alert rules: {:?}", e);
+            return;
+        }
+    };
+
+    let client = match reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("Error building webhook client: {:?}", e);
+            return;
+        }
+    };
+
+    for rule in rules {
+        let (value, event_key) = match observe(db, &rule).await {
+            Ok(Some(observation)) => observation,
+            Ok(None) => continue,
+            Err(e) => {
+                eprintln!("Error evaluating alert rule {}: {:?}", rule._id, e);
+                continue;
+            }
+        };
+
+        if !compare(value, &rule.operator, rule.threshold) {
+            continue;
+        }
+
+        let max_attempts = match db.alert_delivery_state(rule._id, &event_key).await {
+            Ok((true, _)) => continue,
+            Ok((false, attempts)) if attempts >= MAX_EVENT_ATTEMPTS => continue,
+            Ok((false, attempts)) => MAX_ATTEMPTS.min(MAX_EVENT_ATTEMPTS - attempts),
+            Err(e) => {
+                eprintln!("Error reading alert deliveries: {:?}", e);
+                continue;
+            }
+        };
+
+        rt::spawn(deliver_and_log(db.clone(), client.clone(), rule, value, event_key, max_attempts));
+    }
+}
+
@@ -0,0 +1,403 @@
+use std::collections::{BTreeMap, HashMap};
+
+use mongodb::bson::Document;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::db::{number, Database, SWAP_FEE_FIELDS};
+
+pub const SECONDS_PER_YEAR: f64 = 365.0 * 86400.0;
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PoolYield {
+    /// Pool identifier
+    #[schema(example = "BTC.BTC")]
+    pub pool: String,
+
+    /// Start of the earnings covered by the window (UNIX timestamp)
+    #[schema(example = 1653373410)]
+    pub start_time: i64,
+
+    /// End of the earnings covered by the window (UNIX timestamp)
+    #[schema(example = 1666592610)]
+    pub end_time: i64,
+
+    /// Liquidity fees plus rewards paid to LPs over the window, in RUNE base units
+    #[schema(example = 123456789.0)]
+    pub lp_earnings: f64,
+
+    /// Average pool value over the window (twice the RUNE depth), in RUNE base units
+    #[schema(example = 987654321.0)]
+    pub avg_pool_depth: f64,
+
+    /// Annualized LP return without compounding
+    #[schema(example = 0.0512)]
+    pub lp_apr: f64,
+
+    /// Annualized LP return compounded daily
+    #[schema(example = 0.0525)]
+    pub lp_apy: f64,
+
+    /// Earnings paid to savers over the window, in RUNE base units
+    #[schema(example = 1234567.0)]
+    pub saver_earning: f64,
+
+    /// Annualized saver return without compounding, null when the pool has no savers
+    #[schema(example = 0.0123)]
+    pub saver_apr: Option<f64>,
+
+    /// Annualized saver return compounded daily, null when the pool has no savers
+    #[schema(example = 0.0124)]
+    pub saver_apy: Option<f64>,
+}
+
+// Same convention as Midgard: an APR compounded once a day
+pub fn apr_to_apy(apr: f64) -> f64 {
+    (1.0 + apr / 365.0).powf(365.0) - 1.0
+}
+
+fn annualize(earnings: f64, depth: f64, seconds: i64) -> Option<f64> {
+    if depth > 0.0 && seconds > 0 {
+        Some(earnings / depth * SECONDS_PER_YEAR / seconds as f64)
+    } else {
+        None
+    }
+}
+
+/// Annualized LP and saver yield per pool over `from..to`, highest LP yield first
+pub async fn get_pool_yields(
+    db: &Database,
+    pools: &[String],
+    from: i64,
+    to: i64,
+) -> Result<Vec<PoolYield>, mongodb::error::Error> {
+    let earnings = db.get_pool_earnings_totals(pools, from, to).await?;
+    let depths: HashMap<String, Document> = db
+        .get_pool_depth_averages(pools, from, to)
+        .await?
+        .into_iter()
+        .filter_map(|doc| Some((doc.get_str("pool").ok()?.to_string(), doc)))
+        .collect();
+
+    let mut yields: Vec<PoolYield> = earnings
+        .into_iter()
+        .filter_map(|totals| {
+            let pool = totals.get_str("pool").ok()?.to_string();
+            let depth = depths.get(&pool)?;
+
+            let start_time = totals.get_i64("start_time").ok()?;
+            let end_time = totals.get_i64("end_time").ok()?;
+            let covered = end_time - start_time;
+
+            let lp_earnings = number(&totals, "total_liquidity_fees_rune") + number(&totals, "rewards");
+            let avg_pool_depth = 2.0 * number(depth, "avg_rune_depth");
+            let lp_apr = annualize(lp_earnings, avg_pool_depth, covered)?;
+
+            let saver_earning = number(&totals, "saver_earning");
+            let saver_apr = annualize(saver_earning, number(depth, "avg_saver_depth"), covered);
+
+            Some(PoolYield {
+                pool,
+                start_time,
+                end_time,
+                lp_earnings,
+                avg_pool_depth,
+                lp_apr,
+                lp_apy: apr_to_apy(lp_apr),
+                saver_earning,
+                saver_apr,
+                saver_apy: saver_apr.map(apr_to_apy),
+            })
+        })
+        .collect();
+
+    yields.sort_by(|a, b| b.lp_apr.total_cmp(&a.lp_apr));
+
+    Ok(yields)
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Candle {
+    /// Start of the candle (UNIX timestamp)
+    #[schema(example = 1653350400)]
+    pub time: i64,
+
+    /// First price in the candle
+    #[schema(example = 29123.45)]
+    pub open: f64,
+
+    /// Highest price in the candle
+    #[schema(example = 29876.54)]
+    pub high: f64,
+
+    /// Lowest price in the candle
+    #[schema(example = 28765.43)]
+    pub low: f64,
+
+    /// Last price in the candle
+    #[schema(example = 29345.67)]
+    pub close: f64,
+
+    /// Swap volume of the pool within the candle, in whole RUNE or USD following the currency
+    #[schema(example = 1234567.89)]
+    pub volume: f64,
+}
+
+// Price field, volume field and the volume's base units per whole unit for a candle currency.
+// Midgard reports RUNE volume in 1e8 base units and USD volume in cents.
+fn candle_fields(currency: &str) -> (&'static str, &'static str, f64) {
+    match currency {
+        "rune" => ("asset_price", "total_volume", 1e8),
+        _ => ("asset_price_usd", "total_volume_usd", 1e2),
+    }
+}
+
+/// OHLC candles of the pool price with the swap volume of each candle, oldest first
+pub async fn get_candles(
+    db: &Database,
+    pool: String,
+    currency: &str,
+    from: Option<i64>,
+    to: Option<i64>,
+    interval_duration: i64,
+    count: i64,
+) -> Result<Vec<Candle>, mongodb::error::Error> {
+    let (price_field, volume_field, volume_scale) = candle_fields(currency);
+
+    let prices = db
+        .get_price_candle_intervals(pool.clone(), price_field, from, to, interval_duration, count)
+        .await?;
+
+    let (Some(first), Some(last)) = (prices.first(), prices.last()) else {
+        return Ok(Vec::new());
+    };
+
+    // Volume is only needed for the range the candles ended up covering
+    let volumes: HashMap<i64, f64> = db
+        .get_swap_volume_intervals(
+            pool,
+            volume_field,
+            number(first, "start_time") as i64,
+            number(last, "end_time") as i64,
+            interval_duration,
+        )
+        .await?
+        .iter()
+        .map(|doc| (number(doc, "start_time") as i64, number(doc, "volume") / volume_scale))
+        .collect();
+
+    Ok(prices
+        .iter()
+        .map(|doc| {
+            let time = number(doc, "start_time") as i64;
+
+            Candle {
+                time,
+                open: number(doc, "open"),
+                high: number(doc, "high"),
+                low: number(doc, "low"),
+                close: number(doc, "close"),
+                volume: volumes.get(&time).copied().unwrap_or(0.0),
+            }
+        })
+        .collect())
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RunePriceCandle {
+    /// Start of the candle (UNIX timestamp)
+    #[schema(example = 1653350400)]
+    pub time: i64,
+
+    /// First RUNE price in the candle, in USD
+    #[schema(example = 1.48)]
+    pub open: f64,
+
+    /// Highest RUNE price in the candle, in USD
+    #[schema(example = 1.56)]
+    pub high: f64,
+
+    /// Lowest RUNE price in the candle, in USD
+    #[schema(example = 1.45)]
+    pub low: f64,
+
+    /// Last RUNE price in the candle, in USD
+    #[schema(example = 1.52)]
+    pub close: f64,
+
+    /// Where the hourly prices of the candle came from: earnings_summary, swap_history or both
+    #[schema(example = json!(["earnings_summary"]))]
+    pub sources: Vec<String>,
+}
+
+/// OHLC candles of the RUNE price in USD, oldest first
+pub async fn get_rune_price(
+    db: &Database,
+    from: Option<i64>,
+    to: Option<i64>,
+    interval_duration: i64,
+    count: i64,
+) -> Result<Vec<RunePriceCandle>, mongodb::error::Error> {
+    let prices = db.get_rune_price_intervals(from, to, interval_duration, count).await?;
+
+    Ok(prices
+        .iter()
+        .map(|doc| {
+            let mut sources: Vec<String> = doc
+                .get_array("sources")
+                .map(|sources| sources.iter().filter_map(|source| source.as_str().map(str::to_string)).collect())
+                .unwrap_or_default();
+            sources.sort();
+
+            RunePriceCandle {
+                time: number(doc, "start_time") as i64,
+                open: number(doc, "open"),
+                high: number(doc, "high"),
+                low: number(doc, "low"),
+                close: number(doc, "close"),
+                sources,
+            }
+        })
+        .collect())
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FeeAmount {
+    /// Amount in RUNE
+    #[schema(example = 1234.56)]
+    pub rune: f64,
+
+    /// Amount in USD, valued at the RUNE price of each hour it was earned in
+    #[schema(example = 1876.53)]
+    pub usd: f64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PoolFees {
+    /// Pool identifier
+    #[schema(example = "BTC.BTC")]
+    pub pool: String,
+
+    /// Start of the period (UNIX timestamp)
+    #[schema(example = 1653350400)]
+    pub start_time: i64,
+
+    /// End of the period (UNIX timestamp)
+    #[schema(example = 1653436800)]
+    pub end_time: i64,
+
+    /// Swap fees per swap type: to_asset, to_rune, to_trade, from_trade, synth_mint, synth_redeem
+    pub swap_fees: BTreeMap<String, FeeAmount>,
+
+    /// Swap fees of all swap types
+    pub total_swap_fees: FeeAmount,
+
+    /// Liquidity fees paid on the asset side, in whole units of the asset
+    #[schema(example = 0.0123)]
+    pub asset_liquidity_fees: f64,
+
+    /// Liquidity fees paid on the RUNE side, in RUNE
+    #[schema(example = 617.28)]
+    pub rune_liquidity_fees: f64,
+
+    /// Liquidity fees of both sides
+    pub liquidity_fees: FeeAmount,
+
+    /// Share of the protocol wide liquidity fees earned by the pool in the period, null when the
+    /// protocol earned none
+    #[schema(example = 0.087)]
+    pub protocol_fee_share: Option<f64>,
+}
+
+// Fees are stored in 1e8 base units, amounts are reported in whole units
+fn fee_amount(row: &Document, field: &str) -> FeeAmount {
+    FeeAmount {
+        rune: number(row, field) / 1e8,
+        usd: number(row, &format!("{}_usd", field)) / 1e8,
+    }
+}
+
+/// Fee revenue per pool and period, split by swap type and liquidity side. Without an interval the
+/// whole range is a single period. Ordered by period, then pool.
+pub async fn get_pool_fees(
+    db: &Database,
+    pools: &[String],
+    from: i64,
+    to: i64,
+    interval_duration: Option<i64>,
+) -> Result<Vec<PoolFees>, mongodb::error::Error> {
+    let swaps = db.get_swap_fee_intervals(pools, from, to, interval_duration).await?;
+    let liquidity = db.get_liquidity_fee_intervals(pools, from, to, interval_duration).await?;
+    let protocol = db.get_protocol_fee_intervals(from, to, interval_duration).await?;
+
+    // Rows of a period are matched on the bucket start, which is `from` when the range is not bucketed
+    let period = |row: &Document| match interval_duration {
+        Some(_) => number(row, "start_time") as i64,
+        None => from,
+    };
+
+    let protocol_fees: HashMap<i64, f64> = protocol
+        .iter()
+        .map(|row| (period(row), number(row, "liquidity_fees")))
+        .collect();
+
+    let mut rows: BTreeMap<(i64, String), (Document, Document)> = BTreeMap::new();
+
+    for row in swaps {
+        let Ok(pool) = row.get_str("pool").map(str::to_string) else { continue };
+        let key = (period(&row), pool);
+        rows.entry(key).or_default().0 = row;
+    }
+
+    for row in liquidity {
+        let Ok(pool) = row.get_str("pool").map(str::to_string) else { continue };
+        let key = (period(&row), pool);
+        rows.entry(key).or_default().1 = row;
+    }
+
+    Ok(rows
+        .into_iter()
+        .map(|((start_time, pool), (swap, liquidity))| {
+            let swap_fees = SWAP_FEE_FIELDS
+                .iter()
+                .filter(|field| **field != "total_fees")
+                .map(|field| (field.trim_end_matches("_fees").to_string(), fee_amount(&swap, field)))
+                .collect();
+
+            let protocol_fees = protocol_fees.get(&start_time).copied().unwrap_or(0.0);
+            let pool_fees = number(&liquidity, "total_liquidity_fees_rune");
+
+            PoolFees {
+                pool,
+                start_time,
+                end_time: interval_duration.map_or(to, |duration| start_time + duration),
+                swap_fees,
+                total_swap_fees: fee_amount(&swap, "total_fees"),
+                asset_liquidity_fees: number(&liquidity, "asset_liquidity_fees") / 1e8,
+                rune_liquidity_fees: number(&liquidity, "rune_liquidity_fees") / 1e8,
+                liquidity_fees: FeeAmount {
+                    rune: pool_fees / 1e8,
+                    usd: number(&liquidity, "total_liquidity_fees_usd") / 1e8,
+                },
+                protocol_fee_share: (protocol_fees > 0.0).then(|| pool_fees / protocol_fees),
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn candle_volume_is_scaled_per_currency() {
+        // 28403399 cents of USD volume is 284,033.99 USD
+        let (_, volume_field, scale) = candle_fields("usd");
+        assert_eq!(volume_field, "total_volume_usd");
+        assert!((28403399.0 / scale - 284033.99).abs() < 1e-6);
+
+        let (_, volume_field, scale) = candle_fields("rune");
+        assert_eq!(volume_field, "total_volume");
+        assert!((5905400000000.0 / scale - 59054.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,98 @@
+use chrono::Utc;
+use mongodb::bson::to_document;
+use serde::Serialize;
+
+use crate::models::anomaly_model::{Anomaly, AnomalyRequest};
+
+use super::db::{number, Database};
+
+/// Metrics scored per dataset
+pub const SWAP_METRICS: &[&str] = &["total_volume_usd", "average_slip"];
+pub const DEPTH_METRICS: &[&str] = &["asset_depth"];
+
+/// Each interval is scored against the same pool over the preceding 7 days
+const WINDOW: i64 = 7 * 86400;
+
+/// Fewer rows than a day's worth make too noisy a baseline
+const MIN_SAMPLES: i64 = 24;
+
+/// Absolute z-score from which an interval is flagged
+pub const THRESHOLD: f64 = 4.0;
+
+/// The spread scored against is at least this share of the mean, so a metric that barely moved over
+/// the window is not flagged for a tiny change
+const RELATIVE_SPREAD_FLOOR: f64 = 0.01;
+
+/// And at least this, so a metric that was flat at 0 is flagged once it moves
+const ABSOLUTE_SPREAD_FLOOR: f64 = 1.0;
+
+/// Values of `metrics` in an interval row, read by name so they follow the metric lists above
+pub fn metric_values<T: Serialize>(metrics: &[&'static str], record: &T) -> Vec<(&'static str, f64)> {
+    let Ok(row) = to_document(record) else {
+        return Vec::new();
+    };
+
+    metrics.iter().map(|metric| (*metric, number(&row, metric))).collect()
+}
+
+/// Scores the metrics of a newly stored interval of `pool` against the window before it and
+/// stores the ones outside the threshold. Metrics that did not move over the window are scored
+/// against the spread floor instead of their zero spread.
+pub async fn score_interval(
+    db: &Database,
+    dataset: &str,
+    pool: &str,
+    start_time: i64,
+    end_time: i64,
+    values: &[(&str, f64)],
+) {
+    let metrics: Vec<&str> = values.iter().map(|(metric, _)| *metric).collect();
+
+    let stats = match db.get_window_stats(dataset, pool, &metrics, start_time - WINDOW, start_time).await {
+        Ok(Some(stats)) => stats,
+        Ok(None) => return,
+        Err(e) => {
+            eprintln!("Error reading window statistics: {:?}", e);
+            return;
+        }
+    };
+
+    let samples = number(&stats, "samples") as i64;
+    if samples < MIN_SAMPLES {
+        return;
+    }
+
+    for (metric, value) in values {
+        let mean = number(&stats, &format!("{}_mean", metric));
+        let std_dev = number(&stats, &format!("{}_std_dev", metric));
+
+        let spread = std_dev
+            .max(mean.abs() * RELATIVE_SPREAD_FLOOR)
+            .max(ABSOLUTE_SPREAD_FLOOR);
+
+        let z_score = (value - mean) / spread;
+        if z_score.abs() < THRESHOLD {
+            continue;
+        }
+
+        let anomaly = Anomaly::try_from(AnomalyRequest {
+            dataset: dataset.to_string(),
+            pool: pool.to_string(),
+            metric: metric.to_string(),
+            start_time,
+            end_time,
+            value: *value,
+            mean,
+            std_dev,
+            z_score,
+            samples,
+            detected_at: Utc::now().timestamp(),
+        })
+        .unwrap();
+
+        match db.create_anomaly(anomaly).await {
+            Ok(id) => println!("Flagged anomaly: {:?}", id),
+            Err(e) => eprintln!("Error inserting anomaly: {:?}", e),
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::BTreeMap;
+
+use mongodb::bson::Document;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::db::{number, Database};
+
+pub const DATASETS: &[&str] = &["swaps", "depths", "earnings", "runepool"];
+
+const KEY_FIELDS: &[&str] = &["pool", "start_time", "end_time"];
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MetricChange {
+    #[schema(example = 1250000.0)]
+    pub current: f64,
+
+    #[schema(example = 1000000.0)]
+    pub previous: f64,
+
+    /// current - previous
+    #[schema(example = 250000.0)]
+    pub change: f64,
+
+    /// Change relative to the previous value in percent, null when the previous value is zero
+    #[schema(example = 25.0)]
+    pub change_pct: Option<f64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PoolComparison {
+    /// Pool identifier, null for runepool
+    #[schema(example = "BTC.BTC")]
+    pub pool: Option<String>,
+
+    /// Change per metric, sums for swaps and earnings, closing values for depths and runepool
+    pub metrics: BTreeMap<String, MetricChange>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Period {
+    #[schema(example = 1666000000)]
+    pub start_time: i64,
+
+    #[schema(example = 1666604800)]
+    pub end_time: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Comparison {
+    #[schema(example = "swaps")]
+    pub dataset: String,
+
+    pub current: Period,
+
+    pub previous: Period,
+
+    pub pools: Vec<PoolComparison>,
+}
+
+fn by_pool(rows: Vec<Document>) -> BTreeMap<Option<String>, Document> {
+    rows.into_iter()
+        .map(|row| (row.get_str("pool").ok().map(str::to_string), row))
+        .collect()
+}
+
+fn metric_change(current: f64, previous: f64) -> MetricChange {
+    MetricChange {
+        current,
+        previous,
+        change: current - previous,
+        change_pct: (previous != 0.0).then(|| (current - previous) / previous.abs() * 100.0),
+    }
+}
+
+/// Aggregates `dataset` over both periods and compares them per pool and metric.
+/// A pool missing from one period counts as zero there.
+pub async fn compare_periods(
+    db: &Database,
+    dataset: &str,
+    pools: &[String],
+    current: Period,
+    previous: Period,
+) -> Result<Comparison, mongodb::error::Error> {
+    let mut current_rows = by_pool(db.get_period_totals(dataset, pools, current.start_time, current.end_time).await?);
+    let mut previous_rows = by_pool(db.get_period_totals(dataset, pools, previous.start_time, previous.end_time).await?);
+
+    let keys: Vec<Option<String>> = current_rows.keys().chain(previous_rows.keys()).cloned().collect();
+    let mut comparisons = BTreeMap::new();
+
+    for pool in keys {
+        if comparisons.contains_key(&pool) {
+            continue;
+        }
+
+        let current_row = current_rows.remove(&pool).unwrap_or_default();
+        let previous_row = previous_rows.remove(&pool).unwrap_or_default();
+
+        let metrics = current_row
+            .keys()
+            .chain(previous_row.keys())
+            .filter(|field| !KEY_FIELDS.contains(&field.as_str()))
+            .map(|field| (field.clone(), metric_change(number(&current_row, field), number(&previous_row, field))))
+            .collect();
+
+        comparisons.insert(pool.clone(), PoolComparison { pool, metrics });
+    }
+
+    Ok(Comparison {
+        dataset: dataset.to_string(),
+        current,
+        previous,
+        pools: comparisons.into_values().collect(),
+    })
+}
@@ -1,22 +1,42 @@
 use std::env;
 
 
+use chrono::Utc;
 use dotenv::dotenv;
 use futures_util::stream::StreamExt;
 use mongodb::{
-    bson::{doc, oid::ObjectId, to_document,  Document}, results::InsertOneResult, Client, Collection
+    bson::{doc, oid::ObjectId, to_document, Bson, Document}, options::{IndexOptions, ReturnDocument}, Client, Collection, Cursor,
+    IndexModel,
 };
+use serde::Serialize;
 
+use tokio::sync::broadcast;
+
+use super::{
+    live_feed::{self, LiveEvent},
+    query_builder::{Fill, HistoryQuery},
+};
 use crate::models::{
-    depth_history_model::PoolDepthPriceHistory, earning_summary_model::EarningsSummary, earnings_model::PoolEarnings, rpmuh_model::RunePoolHistory, swap_history_model::PoolSwapHistory
+    alert_model::{AlertDelivery, AlertRule}, anomaly_model::Anomaly, depth_history_model::{self, PoolDepthPriceHistory}, earning_summary_model::EarningsSummary, earnings_model::PoolEarnings, rpmuh_model::RunePoolHistory, swap_history_model::{self, PoolSwapHistory}
 };
 
+/// Outcome of storing an interval
+pub struct StoredInterval {
+    pub id: ObjectId,
+    /// False when the interval was already stored and only refreshed
+    pub inserted: bool,
+}
+
 pub struct Database {
     depth_history: Collection<PoolDepthPriceHistory>,
     earnings: Collection<PoolEarnings>,
     earnings_summary: Collection<EarningsSummary>,
     swap_history: Collection<PoolSwapHistory>,
     rpmuh: Collection<RunePoolHistory>,
+    anomalies: Collection<Anomaly>,
+    alert_rules: Collection<AlertRule>,
+    alert_deliveries: Collection<AlertDelivery>,
+    live: broadcast::Sender<LiveEvent>,
 }
 
 impl Database {
@@ -35,6 +55,17 @@ impl Database {
         let earnings_summary: Collection<EarningsSummary> = db.collection("earnings_summary");
         let swap_history: Collection<PoolSwapHistory> = db.collection("swap_history");
         let rpmuh: Collection<RunePoolHistory> = db.collection("rpmuh");
+        let anomalies: Collection<Anomaly> = db.collection("anomalies");
+        let alert_rules: Collection<AlertRule> = db.collection("alert_rules");
+        let alert_deliveries: Collection<AlertDelivery> = db.collection("alert_deliveries");
+
+        // Back the keys `upsert_interval` matches on, so upserts use an index and cannot race into duplicates
+        ensure_unique_index(&depth_history, doc! { "pool": 1, "start_time": 1 }).await;
+        ensure_unique_index(&earnings, doc! { "pool": 1, "start_time": 1 }).await;
+        ensure_unique_index(&earnings_summary, doc! { "start_time": 1 }).await;
+        ensure_unique_index(&swap_history, doc! { "pool": 1, "start_time": 1 }).await;
+        ensure_unique_index(&rpmuh, doc! { "start_time": 1 }).await;
+        ensure_unique_index(&anomalies, doc! { "pool": 1, "metric": 1, "start_time": 1 }).await;
 
         Database {
             depth_history,
@@ -42,15 +73,43 @@ impl Database {
             earnings_summary,
             swap_history,
             rpmuh,
+            anomalies,
+            alert_rules,
+            alert_deliveries,
+            live: live_feed::channel(),
+        }
+    }
+
+    /// Receives every closed interval the ingestion cycle inserts from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<LiveEvent> {
+        self.live.subscribe()
+    }
+
+    fn publish<T: Serialize>(&self, dataset: &str, pool: Option<&str>, record: &T) {
+        if self.live.receiver_count() == 0 {
+            return;
+        }
+
+        if let Some(event) = live_feed::event(dataset, pool, record) {
+            // Only fails when the last subscriber left in the meantime
+            let _ = self.live.send(event);
         }
     }
 
     pub async fn create_depth_history(
         &self,
-        depth_history: PoolDepthPriceHistory
-    ) -> Result<InsertOneResult, mongodb::error::Error> {
-        match self.depth_history.insert_one(depth_history).await {
-            Ok(result) => Ok(result),
+        depth_history: PoolDepthPriceHistory,
+        live: bool,
+    ) -> Result<StoredInterval, mongodb::error::Error> {
+        let key = doc! { "pool": &depth_history.pool, "start_time": depth_history.start_time };
+
+        match upsert_interval(&self.depth_history, key, &depth_history).await {
+            Ok(stored) => {
+                if live && stored.inserted {
+                    self.publish("depths", Some(&depth_history.pool), &depth_history);
+                }
+                Ok(stored)
+            }
             Err(e) => {
                 eprintln!("Error creating depth history: {:?}", e); 
                 Err(e)
@@ -60,143 +119,176 @@ impl Database {
 
     pub async fn get_pool_depth_price_history(
         &self,
-        start_time: Option<i64>,
-        end_time: Option<i64>,
-        pool: Option<String>,
-        page: u32,
-        limit: u32,
-        sort_by: Option<String>,
-        sort_order: i32,
-        interval: Option<String>,
+        options: HistoryQuery,
     ) -> Result<Vec<Document>, mongodb::error::Error> {
-        let mut query = doc! {};
-        
-        if let Some(pool_value) = pool {
-            query.insert("pool", pool_value);
-        }
-    
-        if let Some(from_timestamp) = start_time {
-            query.insert("start_time", doc! { "$gte": from_timestamp });
-        }
-        
-        if let Some(to_timestamp) = end_time {
-            query.insert("end_time", doc! { "$lte": to_timestamp });
+        let mut cursor = self.pool_depth_price_history_cursor(options).await?;
+        let mut results = Vec::new();
+
+        while let Some(result) = cursor.next().await {
+            match result {
+                Ok(doc) => results.push(doc),
+                Err(e) => eprintln!("Error parsing document: {:?}", e),
+            }
         }
+
+        Ok(results)
+    }
+
+    pub async fn pool_depth_price_history_cursor(
+        &self,
+        options: HistoryQuery,
+    ) -> Result<Cursor<Document>, mongodb::error::Error> {
+        let mut query = history_match(&options);
+
+        // A zero limit leaves the cursor unbounded, as it does for find
+        let limit = options.limit.unwrap_or(0);
+        let skip = (options.page - 1) * limit;
+        let sort_doc = options.sort_doc;
+        let filter = options.filter;
+        let projection = options.projection;
+
+        let interval_unit = options.interval.as_deref().unwrap_or("hour");
+        let fill = options.fill;
+        let derived = options.derived;
     
-        let skip = (page - 1) * limit;
-    
-        let sort_doc = sort_by.map(|field| {
-            let order = if sort_order == 1 { 1 } else { -1 };
-            doc! { field: order }
-        }).unwrap_or_else(|| doc! { "end_time": -1 });  
-    
-        let interval_unit = interval.as_deref().unwrap_or("hour");
-    
-        if interval_unit == "hour" {
-            let mut cursor = self.depth_history
+        if interval_unit == "hour" && fill == Fill::None && !derived {
+            if !filter.is_empty() {
+                query.insert("$and", vec![filter]);
+            }
+
+            return self.depth_history
+                .clone_with_type::<Document>()
                 .find(query)
                 .skip(skip as u64)
                 .limit(limit as i64)
                 .sort(sort_doc)
-                .await?;
+                .projection(projection.unwrap_or_else(|| doc! { "_id": 0 }))
+                .await;
+        }
     
-            let mut results = Vec::new();
+        let interval_duration = interval_duration(interval_unit);
     
-            while let Some(result) = cursor.next().await {
-                match result {
-                    Ok(doc) => {
-                        let mut doc = to_document(&doc).unwrap();
-                        doc.remove("_id");
-                        results.push(doc);
-                    },
-                    Err(e) => eprintln!("Error parsing document: {:?}", e),
+        let mut group_id = doc! {
+            // Subtracting 1ms to include the last element as well
+            "interval_start": {
+                "$subtract": [
+                    { "$add": ["$end_time", 1] },
+                    { "$mod": [
+                        { "$subtract": ["$end_time", 1] },
+                        interval_duration
+                    ] }
+                ]
+            }
+        };
+
+        if options.group_by_pool {
+            group_id.insert("pool", "$pool");
+        }
+
+        let mut pipeline = vec![doc! { "$match": query }];
+
+        if let Some(ref projection) = projection {
+            let mut early = pipeline_projection(projection, &sort_doc, &filter);
+            if derived {
+                for field in DERIVED_SOURCE_FIELDS {
+                    early.insert(*field, 1);
                 }
             }
-    
-            return Ok(results);
+            pipeline.push(doc! { "$project": early });
         }
-    
-        let interval_duration = match interval_unit {
-            "day" => 86400,
-            "week" => 604800,
-            "month" => 2678400, 
-            "quarter" => 7948800, 
-            "year" => 31622400,  
-            _ => 3600, 
-        };
-    
-        let pipeline = vec![
-            doc! { "$match": query },
-    
-            doc! { "$group": {
-                "_id": {
-                    // Subtracting 1ms to include the last element as well
-                   "interval_start": { 
-                        "$subtract": [ 
-                            { "$add": ["$end_time", 1] }, 
-                            { "$mod": [ 
-                                { "$subtract": ["$end_time", 1] },  
-                                interval_duration 
-                            ] }
+
+        // Hourly rows are already one per pool and hour, only coarser intervals are bucketed
+        if interval_unit != "hour" {
+            pipeline.extend(vec![
+    
+                doc! { "$group": {
+                    "_id": group_id,
+                    "last_entry": { "$last": "$$ROOT" }  
+                }},
+            
+                doc! { "$project": {
+                    "_id": 0,
+                    "pool": "$last_entry.pool",
+                    "asset_depth": "$last_entry.asset_depth",
+                    "asset_price": "$last_entry.asset_price",
+                    "asset_price_usd": "$last_entry.asset_price_usd",
+                    "liquidity_units": "$last_entry.liquidity_units",
+                    "luvi": "$last_entry.luvi",
+                    "members_count": "$last_entry.members_count",
+                    "rune_depth": "$last_entry.rune_depth",
+                    "synth_supply": "$last_entry.synth_supply",
+                    "synth_units": "$last_entry.synth_units",
+                    "units": "$last_entry.units",
+                    "start_time": {
+                        "$subtract": [ "$last_entry.start_time", { "$mod": [ "$last_entry.start_time", interval_duration ] }]
+                    },
+                    "end_time": {
+                        "$add": [
+                            { "$subtract": [ "$last_entry.start_time", { "$mod": [ "$last_entry.start_time", interval_duration ] }] },
+                            interval_duration
                         ]
                     }
-                },
-                "last_entry": { "$last": "$$ROOT" }  
-            }},
-            
-            doc! { "$project": {
-                "_id": 0,
-                "pool": "$last_entry.pool",
-                "asset_depth": "$last_entry.asset_depth",
-                "asset_price": "$last_entry.asset_price",
-                "asset_price_usd": "$last_entry.asset_price_usd",
-                "liquidity_units": "$last_entry.liquidity_units",
-                "luvi": "$last_entry.luvi",
-                "members_count": "$last_entry.members_count",
-                "rune_depth": "$last_entry.rune_depth",
-                "synth_supply": "$last_entry.synth_supply",
-                "synth_units": "$last_entry.synth_units",
-                "units": "$last_entry.units",
-                "start_time": {
-                    "$subtract": [ "$last_entry.start_time", { "$mod": [ "$last_entry.start_time", interval_duration ] }]
-                },
-                "end_time": {
-                    "$add": [
-                        { "$subtract": [ "$last_entry.start_time", { "$mod": [ "$last_entry.start_time", interval_duration ] }] },
-                        interval_duration
-                    ]
+                }},
+            ]);
+        }
+
+        if fill != Fill::None {
+            pipeline.extend(fill_stages(
+                fill,
+                depth_history_model::FIELDS,
+                interval_duration,
+                options.start_time,
+                options.end_time,
+                options.group_by_pool || interval_unit == "hour",
+            ));
+        }
+
+        // Computed after bucketing and filling so every interval derives them from its own closing row
+        if derived {
+            pipeline.push(doc! { "$addFields": depth_derived_fields() });
+        }
+
+        // Filters apply to the bucketed rows, the same way they apply to the hourly rows
+        if !filter.is_empty() {
+            pipeline.push(doc! { "$match": filter });
+        }
+
+        pipeline.push(doc! { "$sort": sort_doc });
+
+        if limit > 0 {
+            pipeline.push(doc! { "$skip": skip as i64 });
+            pipeline.push(doc! { "$limit": limit as i64 });
+        }
+
+        // Derived hourly rows reach this point with their _id, drop it as the find does
+        match projection {
+            Some(mut projection) => {
+                if fill != Fill::None {
+                    projection.insert("filled", 1);
                 }
-            }},
-            
-            doc! { "$sort": sort_doc },
-    
-            doc! { "$skip": skip as i64 },
-            doc! { "$limit": limit as i64 },
-        ];
-    
-        let mut cursor = self.depth_history.aggregate(pipeline).await?;
-        let mut results = Vec::new();
-    
-        while let Some(result) = cursor.next().await {
-            match result {
-                Ok(mut doc) => {
-                    doc.remove("_id");  
-                    results.push(doc);  
-                },
-                Err(e) => eprintln!("Error parsing document: {:?}", e),
+                pipeline.push(doc! { "$project": projection });
             }
+            None => pipeline.push(doc! { "$project": { "_id": 0 } }),
         }
-    
-        Ok(results)
+
+        self.depth_history.aggregate(pipeline).allow_disk_use(true).await
     }
     
 
     pub async fn create_pool_earnings(
         &self,
-        pool_earnings: PoolEarnings
-    ) -> Result<InsertOneResult, mongodb::error::Error> {
-        match self.earnings.insert_one(pool_earnings).await {
-            Ok(result) => Ok(result),
+        pool_earnings: PoolEarnings,
+        live: bool,
+    ) -> Result<StoredInterval, mongodb::error::Error> {
+        let key = doc! { "pool": &pool_earnings.pool, "start_time": pool_earnings.start_time };
+
+        match upsert_interval(&self.earnings, key, &pool_earnings).await {
+            Ok(stored) => {
+                if live && stored.inserted {
+                    self.publish("earnings", Some(&pool_earnings.pool), &pool_earnings);
+                }
+                Ok(stored)
+            }
             Err(e) => {
                 eprintln!("Error creating pool earnings: {:?}", e); 
                 Err(e)
@@ -206,10 +298,18 @@ impl Database {
 
     pub async fn create_earnings_summary(
         &self,
-        earnings_summary: EarningsSummary
-    ) -> Result<InsertOneResult, mongodb::error::Error> {
-        match self.earnings_summary.insert_one(earnings_summary).await {
-            Ok(result) => Ok(result),
+        earnings_summary: EarningsSummary,
+        live: bool,
+    ) -> Result<StoredInterval, mongodb::error::Error> {
+        let key = doc! { "start_time": earnings_summary.start_time };
+
+        match upsert_interval(&self.earnings_summary, key, &earnings_summary).await {
+            Ok(stored) => {
+                if live && stored.inserted {
+                    self.publish("earnings_summary", None, &earnings_summary);
+                }
+                Ok(stored)
+            }
             Err(e) => {
                 eprintln!("Error creating earnings summary: {:?}", e); 
                 Err(e)
@@ -219,287 +319,353 @@ impl Database {
 
 
 
-    pub async fn get_pool_earnings(
+    pub async fn create_anomaly(
         &self,
-        start_time: Option<i64>,
-        end_time: Option<i64>,
-        pool: Option<String>,
+        anomaly: Anomaly
+    ) -> Result<ObjectId, mongodb::error::Error> {
+        let key = doc! { "pool": &anomaly.pool, "metric": &anomaly.metric, "start_time": anomaly.start_time };
+
+        match upsert_interval(&self.anomalies, key, &anomaly).await {
+            Ok(stored) => Ok(stored.id),
+            Err(e) => {
+                eprintln!("Error creating anomaly: {:?}", e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Flagged anomalies, latest first. `min_score` applies to the absolute z-score.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn get_anomalies(
+        &self,
+        pools: &[String],
+        metric: Option<&str>,
+        from: Option<i64>,
+        to: Option<i64>,
+        min_score: Option<f64>,
         page: u32,
         limit: u32,
-        sort_by: Option<String>,
-        sort_order: i32,
-        interval: Option<String>, 
-        include_summary: bool,
     ) -> Result<Vec<Document>, mongodb::error::Error> {
-        let mut query = doc! {};
-    
-        if let Some(pool_value) = pool {
-            query.insert("pool", pool_value);
-        }
-    
-        if let Some(from_timestamp) = start_time {
-            query.insert("start_time", doc! { "$gte": from_timestamp });
-        }
-    
-        if let Some(to_timestamp) = end_time {
-            query.insert("end_time", doc! { "$lte": to_timestamp });
+        let mut query = pools_time_range_query(pools, from, to);
+
+        if let Some(metric) = metric {
+            query.insert("metric", metric);
         }
-    
-        let skip = (page - 1) * limit;
-    
-        let sort_doc = sort_by.map(|field| {
-            let order = if sort_order == 1 { 1 } else { -1 };
-            doc! { field: order }
-        }).unwrap_or_else(|| doc! { "end_time": -1 });
-    
-       
-        let interval_unit = interval.as_deref().unwrap_or("hour");
-    
-        
-        if interval_unit == "hour" {
-            let mut cursor = self.earnings
-                .find(query)
-                .skip(skip as u64)
-                .limit(limit as i64)
-                .sort(sort_doc)
-                .await?;
-    
-            let mut results = Vec::new();
-    
-            while let Some(result) = cursor.next().await {
-                match result {
-                    Ok( doc) => {
-                        let mut doc = to_document(&doc).unwrap();
-                        doc.remove("_id");
-    
-                        if include_summary {
-                            if let Some(earnings_summary_id) = doc.get_object_id("earnings_summary_id").ok() {
-                                if let Ok(Some(summary_doc)) = self.get_earnings_summary(earnings_summary_id).await {
-                                    for (key, value) in summary_doc.iter() {
-                                        doc.insert(key.clone(), value.clone());
-                                    }
-                                }
-                            }
-                        }
-                        doc.remove("earnings_summary_id");
-                        results.push(doc);
-                    },
-                    Err(e) => eprintln!("Error parsing document: {:?}", e),
-                }
-            }
-    
-            return Ok(results);
+
+        if let Some(min_score) = min_score {
+            query.insert("$or", vec![
+                doc! { "z_score": { "$gte": min_score } },
+                doc! { "z_score": { "$lte": -min_score } },
+            ]);
         }
-    
-        let interval_duration = match interval_unit {
-            "day" => 86400,
-            "week" => 604800,
-            "month" => 2678400, 
-            "quarter" => 7948800, 
-            "year" => 31622400,  
-            _ => 3600, 
-        };
-    
-        
-        let pipeline = vec![
-            doc! { "$match": query },
-    
-            doc! { "$group": {
-                "_id": {
-                    "interval_start": { 
-                        "$subtract": [ 
-                            { "$add": ["$end_time", 1] }, 
-                            { "$mod": [ 
-                                { "$subtract": ["$end_time", 1] },  
-                                interval_duration 
-                            ] }
-                        ]
-                    }
-                },
-                "last_entry": { "$last": "$$ROOT" }  
-            }},
-            
-            doc! { "$project": {
-                "_id": 0,
-                "pool": "$last_entry.pool",
-                "asset_liquidity_fees": "$last_entry.asset_liquidity_fees",
-                "rune_liquidity_fees": "$last_entry.rune_liquidity_fees",
-                "total_liquidity_fees_rune": "$last_entry.total_liquidity_fees_rune",
-                "saver_earning": "$last_entry.saver_earning",
-                "rewards": "$last_entry.rewards",
-                "earnings_summary_id": "$last_entry.earnings_summary_id",
-                "start_time": {
-                    "$subtract": [ "$last_entry.start_time", { "$mod": [ "$last_entry.start_time", interval_duration ] }]
-                },
-                "end_time": {
-                    "$add": [
-                        { "$subtract": [ "$last_entry.start_time", { "$mod": [ "$last_entry.start_time", interval_duration ] }] },
-                        interval_duration
-                    ]
-                }
-            }},
-            
-            doc! { "$sort": sort_doc },
-    
-            doc! { "$skip": skip as i64 },
-            doc! { "$limit": limit as i64 },
-        ];
-    
 
-    
-        let mut cursor = self.earnings.aggregate(pipeline).await?;
+        let mut cursor = self.anomalies
+            .clone_with_type::<Document>()
+            .find(query)
+            .sort(doc! { "start_time": -1, "pool": 1, "metric": 1 })
+            .skip(((page - 1) * limit) as u64)
+            .limit(limit as i64)
+            .projection(doc! { "_id": 0 })
+            .await?;
+
         let mut results = Vec::new();
-    
+
         while let Some(result) = cursor.next().await {
             match result {
-                Ok( doc) => {
-                    let mut doc = to_document(&doc).unwrap();
-                    doc.remove("_id");
-
-                    if include_summary {
-                        if let Some(earnings_summary_id) = doc.get_object_id("earnings_summary_id").ok() {
-                            if let Ok(Some(summary_doc)) = self.get_earnings_summary(earnings_summary_id).await {
-                                for (key, value) in summary_doc.iter() {
-                                    doc.insert(key.clone(), value.clone());
-                                }
-                            }
-                        }
-                    }
-                    doc.remove("earnings_summary_id");
-                    results.push(doc);
-                },
+                Ok(doc) => results.push(doc),
                 Err(e) => eprintln!("Error parsing document: {:?}", e),
             }
         }
-    
+
         Ok(results)
     }
-    
-    pub async fn get_earnings_summary(
+
+    pub async fn create_alert_rule(
         &self,
-        earnings_summary_id: ObjectId,
-    ) -> Result<Option<Document>, mongodb::error::Error> {
-        let filter = doc! { "_id": earnings_summary_id };
-    
-        match self.earnings_summary.find_one(filter).await {
-            Ok(Some(summary_doc)) => {
-                let mut summary = to_document(&summary_doc).unwrap();
-                summary.remove("_id"); 
-                summary.remove("start_time");
-                summary.remove("end_time");
-                Ok(Some(summary))
-            }
-            Ok(None) => {
-                Ok(None)
+        rule: AlertRule
+    ) -> Result<ObjectId, mongodb::error::Error> {
+        match self.alert_rules.insert_one(&rule).await {
+            Ok(_) => Ok(rule._id),
+            Err(e) => {
+                eprintln!("Error creating alert rule: {:?}", e);
+                Err(e)
             }
-            Err(err) => {
-                Err(err)
+        }
+    }
+
+    pub async fn get_alert_rules(&self, enabled_only: bool) -> Result<Vec<AlertRule>, mongodb::error::Error> {
+        let query = if enabled_only { doc! { "enabled": true } } else { doc! {} };
+        let mut cursor = self.alert_rules.find(query).sort(doc! { "created_at": 1 }).await?;
+        let mut results = Vec::new();
+
+        while let Some(result) = cursor.next().await {
+            match result {
+                Ok(rule) => results.push(rule),
+                Err(e) => eprintln!("Error parsing document: {:?}", e),
             }
         }
+
+        Ok(results)
     }
 
+    pub async fn get_alert_rule(&self, id: ObjectId) -> Result<Option<AlertRule>, mongodb::error::Error> {
+        self.alert_rules.find_one(doc! { "_id": id }).await
+    }
 
-    pub async fn create_swap_history(
+    /// Replaces a rule, returning false when it does not exist
+    pub async fn update_alert_rule(&self, rule: &AlertRule) -> Result<bool, mongodb::error::Error> {
+        let result = self.alert_rules.replace_one(doc! { "_id": rule._id }, rule).await?;
+        Ok(result.matched_count > 0)
+    }
+
+    /// Deletes a rule and its delivery log, returning false when it does not exist
+    pub async fn delete_alert_rule(&self, id: ObjectId) -> Result<bool, mongodb::error::Error> {
+        let result = self.alert_rules.delete_one(doc! { "_id": id }).await?;
+        self.alert_deliveries.delete_many(doc! { "rule_id": id }).await?;
+        Ok(result.deleted_count > 0)
+    }
+
+    pub async fn create_alert_delivery(
         &self,
-        swap_history: PoolSwapHistory
-    ) -> Result<InsertOneResult, mongodb::error::Error> {
-        match self.swap_history.insert_one(swap_history).await {
-            Ok(result) => Ok(result),
+        delivery: AlertDelivery
+    ) -> Result<ObjectId, mongodb::error::Error> {
+        match self.alert_deliveries.insert_one(&delivery).await {
+            Ok(_) => Ok(delivery._id),
             Err(e) => {
-                eprintln!("Error creating swap history: {:?}", e); 
+                eprintln!("Error creating alert delivery: {:?}", e);
                 Err(e)
             }
         }
     }
 
-    pub async fn get_pool_swap_history(
+    /// Whether the event of a rule was already delivered successfully, and the attempts logged for it
+    pub async fn alert_delivery_state(
         &self,
-        start_time: Option<i64>,
-        end_time: Option<i64>,
-        pool: Option<String>,
+        rule_id: ObjectId,
+        event_key: &str,
+    ) -> Result<(bool, i64), mongodb::error::Error> {
+        let pipeline = vec![
+            doc! { "$match": { "rule_id": rule_id, "event_key": event_key } },
+            doc! { "$group": {
+                "_id": Bson::Null,
+                "delivered": { "$max": { "$eq": ["$status", "delivered"] } },
+                "attempts": { "$sum": "$attempts" },
+            }},
+        ];
+
+        let mut cursor = self.alert_deliveries.aggregate(pipeline).await?;
+
+        match cursor.next().await.transpose()? {
+            Some(state) => Ok((
+                state.get_bool("delivered").unwrap_or(false),
+                state.get_i64("attempts").unwrap_or_default(),
+            )),
+            None => Ok((false, 0)),
+        }
+    }
+
+    /// Delivery log, latest first
+    pub async fn get_alert_deliveries(
+        &self,
+        rule_id: Option<ObjectId>,
         page: u32,
         limit: u32,
-        sort_by: Option<String>,
-        sort_order: i32,
-        interval: Option<String>,
-    ) -> Result<Vec<mongodb::bson::Document>, mongodb::error::Error> {
-        let mut query = doc! {};
-    
-        if let Some(pool_value) = pool {
-            query.insert("pool", pool_value);
+    ) -> Result<Vec<AlertDelivery>, mongodb::error::Error> {
+        let query = match rule_id {
+            Some(rule_id) => doc! { "rule_id": rule_id },
+            None => doc! {},
+        };
+
+        let mut cursor = self.alert_deliveries
+            .find(query)
+            .sort(doc! { "fired_at": -1 })
+            .skip(((page - 1) * limit) as u64)
+            .limit(limit as i64)
+            .await?;
+        let mut results = Vec::new();
+
+        while let Some(result) = cursor.next().await {
+            match result {
+                Ok(delivery) => results.push(delivery),
+                Err(e) => eprintln!("Error parsing document: {:?}", e),
+            }
         }
-    
-        if let Some(from_timestamp) = start_time {
-            query.insert("start_time", doc! { "$gte": from_timestamp });
+
+        Ok(results)
+    }
+
+    /// Latest closed row of a dataset starting at or before `at`, for one pool or across all pools.
+    /// The hour in progress is left out, its values are still partial.
+    pub async fn latest_metric_row(
+        &self,
+        dataset: &str,
+        pool: Option<&str>,
+        at: Option<i64>,
+    ) -> Result<Option<Document>, mongodb::error::Error> {
+        let mut query = time_range_query(pool.map(str::to_string), None, Some(Utc::now().timestamp()));
+        if let Some(at) = at {
+            query.insert("start_time", doc! { "$lte": at });
         }
-    
-        if let Some(to_timestamp) = end_time {
-            query.insert("end_time", doc! { "$lte": to_timestamp });
+
+        let collection = match dataset {
+            "swaps" => self.swap_history.clone_with_type::<Document>(),
+            "depths" => self.depth_history.clone_with_type::<Document>(),
+            "earnings" => self.earnings.clone_with_type::<Document>(),
+            _ => self.rpmuh.clone_with_type::<Document>(),
+        };
+
+        collection
+            .find_one(query)
+            .sort(doc! { "start_time": -1 })
+            .projection(doc! { "_id": 0 })
+            .await
+    }
+
+    /// Mean, population standard deviation and sample count of `fields` over the rows of `pool`
+    /// starting in `from..before`, as `<field>_mean`, `<field>_std_dev` and `samples`
+    pub async fn get_window_stats(
+        &self,
+        dataset: &str,
+        pool: &str,
+        fields: &[&str],
+        from: i64,
+        before: i64,
+    ) -> Result<Option<Document>, mongodb::error::Error> {
+        let mut group = doc! { "_id": Bson::Null, "samples": { "$sum": 1 } };
+        for field in fields {
+            group.insert(format!("{}_mean", field), doc! { "$avg": format!("${}", field) });
+            group.insert(format!("{}_std_dev", field), doc! { "$stdDevPop": format!("${}", field) });
         }
+
+        let pipeline = vec![
+            doc! { "$match": { "pool": pool, "start_time": { "$gte": from, "$lt": before } } },
+            doc! { "$group": group },
+        ];
+
+        let mut cursor = match dataset {
+            "depths" => self.depth_history.aggregate(pipeline).await?,
+            _ => self.swap_history.aggregate(pipeline).await?,
+        };
+
+        cursor.next().await.transpose()
+    }
+
+    pub async fn get_pool_earnings(
+        &self,
+        mut options: HistoryQuery,
+        include_summary: bool,
+    ) -> Result<Vec<Document>, mongodb::error::Error> {
+        // Summary fields are merged after the query, so they follow the requested projection here
+        let summary_fields: Option<Vec<String>> = options.projection.as_ref().map(|projection| {
+            projection.keys().cloned().collect()
+        });
+
+        if include_summary {
+            if let Some(ref mut projection) = options.projection {
+                projection.insert("earnings_summary_id", 1);
+            }
+        }
+
+        let mut cursor = self.pool_earnings_cursor(options).await?;
+        let mut results = Vec::new();
+
+        while let Some(result) = cursor.next().await {
+            match result {
+                Ok(mut doc) => {
+                    if include_summary {
+                        if let Ok(earnings_summary_id) = doc.get_object_id("earnings_summary_id") {
+                            if let Ok(Some(summary_doc)) = self.get_earnings_summary(earnings_summary_id).await {
+                                for (key, value) in summary_doc.iter() {
+                                    if summary_fields.as_ref().is_none_or(|fields| fields.contains(key)) {
+                                        doc.insert(key.clone(), value.clone());
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    doc.remove("earnings_summary_id");
+                    results.push(doc);
+                },
+                Err(e) => eprintln!("Error parsing document: {:?}", e),
+            }
+        }
+
+        Ok(results)
+    }
+
+    pub async fn pool_earnings_cursor(
+        &self,
+        options: HistoryQuery,
+    ) -> Result<Cursor<Document>, mongodb::error::Error> {
+        let mut query = history_match(&options);
+
+        // A zero limit leaves the cursor unbounded, as it does for find
+        let limit = options.limit.unwrap_or(0);
+        let skip = (options.page - 1) * limit;
+        let sort_doc = options.sort_doc;
+        let filter = options.filter;
+
+        let projection = options.projection;
+
+        let interval_unit = options.interval.as_deref().unwrap_or("hour");
     
-        let skip = (page - 1) * limit;
-    
-        let sort_doc = sort_by.map(|field| {
-            let order = if sort_order == 1 { 1 } else { -1 };
-            doc! { field: order }
-        }).unwrap_or_else(|| doc! { "end_time": -1 }); 
-    
-        let interval_unit = interval.as_deref().unwrap_or("hour");
-    
+        
         if interval_unit == "hour" {
-            let mut cursor = self.swap_history
+            if !filter.is_empty() {
+                query.insert("$and", vec![filter]);
+            }
+
+            return self.earnings
+                .clone_with_type::<Document>()
                 .find(query)
                 .skip(skip as u64)
                 .limit(limit as i64)
                 .sort(sort_doc)
-                .await?;
+                .projection(projection.unwrap_or_else(|| doc! { "_id": 0 }))
+                .await;
+        }
     
-            let mut results = Vec::new();
+        let interval_duration = interval_duration(interval_unit);
     
-            while let Some(result) = cursor.next().await {
-                match result {
-                    Ok(doc) => {
-                        let mut doc = to_document(&doc).unwrap();
-                        doc.remove("_id");
-                        results.push(doc);
-                    },
-                    Err(e) => eprintln!("Error parsing document: {:?}", e),
-                }
+        
+        let mut group_id = doc! {
+            "interval_start": {
+                "$subtract": [
+                    { "$add": ["$end_time", 1] },
+                    { "$mod": [
+                        { "$subtract": ["$end_time", 1] },
+                        interval_duration
+                    ] }
+                ]
             }
-    
-            return Ok(results);
-        }
-    
-        let interval_duration = match interval_unit {
-            "day" => 86400,
-            "week" => 604800,
-            "month" => 2678400,
-            "quarter" => 7948800,
-            "year" => 31622400,
-            _ => 3600,
         };
-    
-        let pipeline = vec![
-            doc! { "$match": query },
+
+        if options.group_by_pool {
+            group_id.insert("pool", "$pool");
+        }
+
+        let mut pipeline = vec![doc! { "$match": query }];
+
+        if let Some(ref projection) = projection {
+            pipeline.push(doc! { "$project": pipeline_projection(projection, &sort_doc, &filter) });
+        }
+
+        pipeline.extend(vec![
     
             doc! { "$group": {
-                "_id": {
-                    "interval_start": { 
-                        "$subtract": [ 
-                            { "$add": ["$end_time", 1] }, 
-                            { "$mod": [ 
-                                { "$subtract": ["$end_time", 1] },  
-                                interval_duration 
-                            ] }
-                        ]
-                    }
-                },
+                "_id": group_id,
                 "last_entry": { "$last": "$$ROOT" }  
             }},
             
             doc! { "$project": {
-                "_id": "$last_entry._id",
+                "_id": 0,
                 "pool": "$last_entry.pool",
+                "asset_liquidity_fees": "$last_entry.asset_liquidity_fees",
+                "rune_liquidity_fees": "$last_entry.rune_liquidity_fees",
+                "total_liquidity_fees_rune": "$last_entry.total_liquidity_fees_rune",
+                "saver_earning": "$last_entry.saver_earning",
+                "rewards": "$last_entry.rewards",
+                "earnings_summary_id": "$last_entry.earnings_summary_id",
                 "start_time": {
                     "$subtract": [ "$last_entry.start_time", { "$mod": [ "$last_entry.start_time", interval_duration ] }]
                 },
@@ -508,162 +674,355 @@ impl Database {
                         { "$subtract": [ "$last_entry.start_time", { "$mod": [ "$last_entry.start_time", interval_duration ] }] },
                         interval_duration
                     ]
-                },
-                "to_asset_count": "$last_entry.to_asset_count",
-                "to_rune_count": "$last_entry.to_rune_count",
-                "to_trade_count": "$last_entry.to_trade_count",
-                "from_trade_count": "$last_entry.from_trade_count",
-                "synth_mint_count": "$last_entry.synth_mint_count",
-                "synth_redeem_count": "$last_entry.synth_redeem_count",
-                "total_count": "$last_entry.total_count",
-                "to_asset_volume": "$last_entry.to_asset_volume",
-                "to_rune_volume": "$last_entry.to_rune_volume",
-                "to_trade_volume": "$last_entry.to_trade_volume",
-                "from_trade_volume": "$last_entry.from_trade_volume",
-                "synth_mint_volume": "$last_entry.synth_mint_volume",
-                "synth_redeem_volume": "$last_entry.synth_redeem_volume",
-                "total_volume": "$last_entry.total_volume",
-                "to_asset_volume_usd": "$last_entry.to_asset_volume_usd",
-                "to_rune_volume_usd": "$last_entry.to_rune_volume_usd",
-                "to_trade_volume_usd": "$last_entry.to_trade_volume_usd",
-                "from_trade_volume_usd": "$last_entry.from_trade_volume_usd",
-                "synth_mint_volume_usd": "$last_entry.synth_mint_volume_usd",
-                "synth_redeem_volume_usd": "$last_entry.synth_redeem_volume_usd",
-                "total_volume_usd": "$last_entry.total_volume_usd",
-                "to_asset_fees": "$last_entry.to_asset_fees",
-                "to_rune_fees": "$last_entry.to_rune_fees",
-                "to_trade_fees": "$last_entry.to_trade_fees",
-                "from_trade_fees": "$last_entry.from_trade_fees",
-                "synth_mint_fees": "$last_entry.synth_mint_fees",
-                "synth_redeem_fees": "$last_entry.synth_redeem_fees",
-                "total_fees": "$last_entry.total_fees",
-                "to_asset_average_slip": "$last_entry.to_asset_average_slip",
-                "to_rune_average_slip": "$last_entry.to_rune_average_slip",
-                "to_trade_average_slip": "$last_entry.to_trade_average_slip",
-                "from_trade_average_slip": "$last_entry.from_trade_average_slip",
-                "synth_mint_average_slip": "$last_entry.synth_mint_average_slip",
-                "synth_redeem_average_slip": "$last_entry.synth_redeem_average_slip",
-                "average_slip": "$last_entry.average_slip",
-                "rune_price_usd": "$last_entry.rune_price_usd"
+                }
             }},
-            
-            doc! { "$sort": sort_doc },
+        ]);
+
+        // Filters apply to the bucketed rows, the same way they apply to the hourly rows
+        if !filter.is_empty() {
+            pipeline.push(doc! { "$match": filter });
+        }
+
+        pipeline.push(doc! { "$sort": sort_doc });
+
+        if limit > 0 {
+            pipeline.push(doc! { "$skip": skip as i64 });
+            pipeline.push(doc! { "$limit": limit as i64 });
+        }
+
+        if let Some(projection) = projection {
+            pipeline.push(doc! { "$project": projection });
+        }
+
+        self.earnings.aggregate(pipeline).allow_disk_use(true).await
+    }
     
-            doc! { "$skip": skip as i64 },
-            doc! { "$limit": limit as i64 },
-        ];
+    pub async fn get_earnings_summary(
+        &self,
+        earnings_summary_id: ObjectId,
+    ) -> Result<Option<Document>, mongodb::error::Error> {
+        let filter = doc! { "_id": earnings_summary_id };
     
-        let mut cursor = self.swap_history.aggregate(pipeline).await?;
+        match self.earnings_summary.find_one(filter).await {
+            Ok(Some(summary_doc)) => {
+                let mut summary = to_document(&summary_doc).unwrap();
+                summary.remove("_id"); 
+                summary.remove("start_time");
+                summary.remove("end_time");
+                Ok(Some(summary))
+            }
+            Ok(None) => {
+                Ok(None)
+            }
+            Err(err) => {
+                Err(err)
+            }
+        }
+    }
+
+    /// Earnings summaries with any of the given ids, in one query
+    pub async fn get_earnings_summaries(
+        &self,
+        earnings_summary_ids: &[ObjectId],
+    ) -> Result<Vec<Document>, mongodb::error::Error> {
+        let filter = doc! { "_id": { "$in": earnings_summary_ids } };
+        let mut cursor = self.earnings_summary.clone_with_type::<Document>().find(filter).await?;
         let mut results = Vec::new();
-    
+
         while let Some(result) = cursor.next().await {
             match result {
-                Ok(mut doc) => {
-                    doc.remove("_id");
-                    results.push(doc);
-                },
+                Ok(doc) => results.push(doc),
                 Err(e) => eprintln!("Error parsing document: {:?}", e),
             }
         }
-    
+
         Ok(results)
     }
-    
 
-    pub async fn create_rpmuh(
+
+    pub async fn create_swap_history(
         &self,
-        rpmuh: RunePoolHistory
-    ) -> Result<InsertOneResult, mongodb::error::Error> {
-        match self.rpmuh.insert_one(rpmuh).await {
-            Ok(result) => Ok(result),
+        swap_history: PoolSwapHistory,
+        live: bool,
+    ) -> Result<StoredInterval, mongodb::error::Error> {
+        let key = doc! { "pool": &swap_history.pool, "start_time": swap_history.start_time };
+
+        match upsert_interval(&self.swap_history, key, &swap_history).await {
+            Ok(stored) => {
+                if live && stored.inserted {
+                    self.publish("swaps", Some(&swap_history.pool), &swap_history);
+                }
+                Ok(stored)
+            }
             Err(e) => {
-                eprintln!("Error creating RunePool history: {:?}", e); 
+                eprintln!("Error creating swap history: {:?}", e); 
                 Err(e)
             }
         }
     }
-    
-    pub async fn get_rune_pool_history(
+
+    pub async fn get_pool_swap_history(
         &self,
-        start_time: Option<i64>,
-        end_time: Option<i64>,
-        page: u32,
-        limit: u32,
-        sort_by: Option<String>,
-        sort_order: i32,
-        interval: Option<String>,
-    ) -> Result<Vec<mongodb::bson::Document>, mongodb::error::Error> {
-        let mut query = doc! {};
-    
-        if let Some(from_timestamp) = start_time {
-            query.insert("start_time", doc! { "$gte": from_timestamp });
-        }
-    
-        if let Some(to_timestamp) = end_time {
-            query.insert("end_time", doc! { "$lte": to_timestamp });
+        options: HistoryQuery,
+    ) -> Result<Vec<Document>, mongodb::error::Error> {
+        let mut cursor = self.pool_swap_history_cursor(options).await?;
+        let mut results = Vec::new();
+
+        while let Some(result) = cursor.next().await {
+            match result {
+                Ok(doc) => results.push(doc),
+                Err(e) => eprintln!("Error parsing document: {:?}", e),
+            }
         }
+
+        Ok(results)
+    }
+
+    pub async fn pool_swap_history_cursor(
+        &self,
+        options: HistoryQuery,
+    ) -> Result<Cursor<Document>, mongodb::error::Error> {
+        let mut query = history_match(&options);
+
+        // A zero limit leaves the cursor unbounded, as it does for find
+        let limit = options.limit.unwrap_or(0);
+        let skip = (options.page - 1) * limit;
+        let sort_doc = options.sort_doc;
+        let filter = options.filter;
+        let projection = options.projection;
+
+        let interval_unit = options.interval.as_deref().unwrap_or("hour");
+        let fill = options.fill;
     
-        let skip = (page - 1) * limit;
-    
-        let sort_doc = sort_by.map(|field| {
-            let order = if sort_order == 1 { 1 } else { -1 };
-            doc! { field: order }
-        }).unwrap_or_else(|| doc! { "end_time": -1 }); 
-    
-        let interval_unit = interval.as_deref().unwrap_or("hour");
-    
-        if interval_unit == "hour" {
-            let mut cursor = self.rpmuh
+        if interval_unit == "hour" && fill == Fill::None {
+            if !filter.is_empty() {
+                query.insert("$and", vec![filter]);
+            }
+
+            return self.swap_history
+                .clone_with_type::<Document>()
                 .find(query)
                 .skip(skip as u64)
                 .limit(limit as i64)
                 .sort(sort_doc)
-                .await?;
+                .projection(projection.unwrap_or_else(|| doc! { "_id": 0 }))
+                .await;
+        }
     
-            let mut results = Vec::new();
+        let interval_duration = interval_duration(interval_unit);
     
-            while let Some(result) = cursor.next().await {
-                match result {
-                    Ok(doc) => {
-                        let mut doc = to_document(&doc).unwrap();
-                        doc.remove("_id");
-                        results.push(doc);
+        let mut group_id = doc! {
+            "interval_start": {
+                "$subtract": [
+                    { "$add": ["$end_time", 1] },
+                    { "$mod": [
+                        { "$subtract": ["$end_time", 1] },
+                        interval_duration
+                    ] }
+                ]
+            }
+        };
+
+        if options.group_by_pool {
+            group_id.insert("pool", "$pool");
+        }
+
+        let mut pipeline = vec![doc! { "$match": query }];
+
+        if let Some(ref projection) = projection {
+            pipeline.push(doc! { "$project": pipeline_projection(projection, &sort_doc, &filter) });
+        }
+
+        // Hourly rows are already one per pool and hour, only coarser intervals are bucketed
+        if interval_unit != "hour" {
+            pipeline.extend(vec![
+    
+                doc! { "$group": {
+                    "_id": group_id,
+                    "last_entry": { "$last": "$$ROOT" }  
+                }},
+            
+                doc! { "$project": {
+                    "_id": 0,
+                    "pool": "$last_entry.pool",
+                    "start_time": {
+                        "$subtract": [ "$last_entry.start_time", { "$mod": [ "$last_entry.start_time", interval_duration ] }]
                     },
-                    Err(e) => eprintln!("Error parsing document: {:?}", e),
-                }
+                    "end_time": {
+                        "$add": [
+                            { "$subtract": [ "$last_entry.start_time", { "$mod": [ "$last_entry.start_time", interval_duration ] }] },
+                            interval_duration
+                        ]
+                    },
+                    "to_asset_count": "$last_entry.to_asset_count",
+                    "to_rune_count": "$last_entry.to_rune_count",
+                    "to_trade_count": "$last_entry.to_trade_count",
+                    "from_trade_count": "$last_entry.from_trade_count",
+                    "synth_mint_count": "$last_entry.synth_mint_count",
+                    "synth_redeem_count": "$last_entry.synth_redeem_count",
+                    "total_count": "$last_entry.total_count",
+                    "to_asset_volume": "$last_entry.to_asset_volume",
+                    "to_rune_volume": "$last_entry.to_rune_volume",
+                    "to_trade_volume": "$last_entry.to_trade_volume",
+                    "from_trade_volume": "$last_entry.from_trade_volume",
+                    "synth_mint_volume": "$last_entry.synth_mint_volume",
+                    "synth_redeem_volume": "$last_entry.synth_redeem_volume",
+                    "total_volume": "$last_entry.total_volume",
+                    "to_asset_volume_usd": "$last_entry.to_asset_volume_usd",
+                    "to_rune_volume_usd": "$last_entry.to_rune_volume_usd",
+                    "to_trade_volume_usd": "$last_entry.to_trade_volume_usd",
+                    "from_trade_volume_usd": "$last_entry.from_trade_volume_usd",
+                    "synth_mint_volume_usd": "$last_entry.synth_mint_volume_usd",
+                    "synth_redeem_volume_usd": "$last_entry.synth_redeem_volume_usd",
+                    "total_volume_usd": "$last_entry.total_volume_usd",
+                    "to_asset_fees": "$last_entry.to_asset_fees",
+                    "to_rune_fees": "$last_entry.to_rune_fees",
+                    "to_trade_fees": "$last_entry.to_trade_fees",
+                    "from_trade_fees": "$last_entry.from_trade_fees",
+                    "synth_mint_fees": "$last_entry.synth_mint_fees",
+                    "synth_redeem_fees": "$last_entry.synth_redeem_fees",
+                    "total_fees": "$last_entry.total_fees",
+                    "to_asset_average_slip": "$last_entry.to_asset_average_slip",
+                    "to_rune_average_slip": "$last_entry.to_rune_average_slip",
+                    "to_trade_average_slip": "$last_entry.to_trade_average_slip",
+                    "from_trade_average_slip": "$last_entry.from_trade_average_slip",
+                    "synth_mint_average_slip": "$last_entry.synth_mint_average_slip",
+                    "synth_redeem_average_slip": "$last_entry.synth_redeem_average_slip",
+                    "average_slip": "$last_entry.average_slip",
+                    "rune_price_usd": "$last_entry.rune_price_usd"
+                }},
+            ]);
+        }
+
+        if fill != Fill::None {
+            pipeline.extend(fill_stages(
+                fill,
+                swap_history_model::FIELDS,
+                interval_duration,
+                options.start_time,
+                options.end_time,
+                options.group_by_pool || interval_unit == "hour",
+            ));
+        }
+
+        // Filters apply to the bucketed rows, the same way they apply to the hourly rows
+        if !filter.is_empty() {
+            pipeline.push(doc! { "$match": filter });
+        }
+
+        pipeline.push(doc! { "$sort": sort_doc });
+
+        if limit > 0 {
+            pipeline.push(doc! { "$skip": skip as i64 });
+            pipeline.push(doc! { "$limit": limit as i64 });
+        }
+
+        if let Some(mut projection) = projection {
+            if fill != Fill::None {
+                projection.insert("filled", 1);
             }
-    
-            return Ok(results);
+            pipeline.push(doc! { "$project": projection });
         }
+
+        self.swap_history.aggregate(pipeline).allow_disk_use(true).await
+    }
     
-        let interval_duration = match interval_unit {
-            "day" => 86400,
-            "week" => 604800,
-            "month" => 2678400, 
-            "quarter" => 7948800, 
-            "year" => 31622400, 
-            _ => 3600,
-        };
-    
-        let pipeline = vec![
-            doc! { "$match": query },
+
+    pub async fn create_rpmuh(
+        &self,
+        rpmuh: RunePoolHistory,
+        live: bool,
+    ) -> Result<StoredInterval, mongodb::error::Error> {
+        let key = doc! { "start_time": rpmuh.start_time };
+
+        match upsert_interval(&self.rpmuh, key, &rpmuh).await {
+            Ok(stored) => {
+                if live && stored.inserted {
+                    self.publish("runepool", None, &rpmuh);
+                }
+                Ok(stored)
+            }
+            Err(e) => {
+                eprintln!("Error creating RunePool history: {:?}", e); 
+                Err(e)
+            }
+        }
+    }
     
-            doc! { "$group": {
-                "_id": {
-                    "interval_start": { 
-                        "$subtract": [ 
-                            { "$add": ["$end_time", 1] }, 
-                            { "$mod": [ 
-                                { "$subtract": ["$end_time", 1] },  
-                                interval_duration 
-                            ] }
-                        ]
+    pub async fn get_rune_pool_history(
+        &self,
+        options: HistoryQuery,
+    ) -> Result<Vec<Document>, mongodb::error::Error> {
+        let mut cursor = self.rune_pool_history_cursor(options).await?;
+        let mut results = Vec::new();
+
+        while let Some(result) = cursor.next().await {
+            match result {
+                Ok(doc) => results.push(doc),
+                Err(e) => eprintln!("Error parsing document: {:?}", e),
+            }
+        }
+
+        Ok(results)
+    }
+
+    pub async fn rune_pool_history_cursor(
+        &self,
+        options: HistoryQuery,
+    ) -> Result<Cursor<Document>, mongodb::error::Error> {
+        let mut query = history_match(&options);
+
+        // A zero limit leaves the cursor unbounded, as it does for find
+        let limit = options.limit.unwrap_or(0);
+        let skip = (options.page - 1) * limit;
+        let sort_doc = options.sort_doc;
+        let filter = options.filter;
+        let projection = options.projection;
+
+        let interval_unit = options.interval.as_deref().unwrap_or("hour");
+    
+        if interval_unit == "hour" {
+            if !filter.is_empty() {
+                query.insert("$and", vec![filter]);
+            }
+
+            return self.rpmuh
+                .clone_with_type::<Document>()
+                .find(query)
+                .skip(skip as u64)
+                .limit(limit as i64)
+                .sort(sort_doc)
+                .projection(projection.unwrap_or_else(|| doc! { "_id": 0 }))
+                .await;
+        }
+    
+        let interval_duration = interval_duration(interval_unit);
+    
+        let mut pipeline = vec![doc! { "$match": query }];
+
+        if let Some(ref projection) = projection {
+            pipeline.push(doc! { "$project": pipeline_projection(projection, &sort_doc, &filter) });
+        }
+
+        pipeline.extend(vec![
+    
+            doc! { "$group": {
+                "_id": {
+                    "interval_start": { 
+                        "$subtract": [ 
+                            { "$add": ["$end_time", 1] }, 
+                            { "$mod": [ 
+                                { "$subtract": ["$end_time", 1] },  
+                                interval_duration 
+                            ] }
+                        ]
                     }
                 },
                 "last_entry": { "$last": "$$ROOT" }  
             }},
             
             doc! { "$project": {
+                "_id": 0,
                 "start_time": {
                     "$subtract": [ "$last_entry.start_time", { "$mod": [ "$last_entry.start_time", interval_duration ] }]
                 },
@@ -676,27 +1035,1166 @@ impl Database {
                 "count": "$last_entry.count",
                 "units": "$last_entry.units"
             }},
-            
-            doc! { "$sort": sort_doc },
-    
-            doc! { "$skip": skip as i64 },
-            doc! { "$limit": limit as i64 },
+        ]);
+
+        // Filters apply to the bucketed rows, the same way they apply to the hourly rows
+        if !filter.is_empty() {
+            pipeline.push(doc! { "$match": filter });
+        }
+
+        pipeline.push(doc! { "$sort": sort_doc });
+
+        if limit > 0 {
+            pipeline.push(doc! { "$skip": skip as i64 });
+            pipeline.push(doc! { "$limit": limit as i64 });
+        }
+
+        if let Some(projection) = projection {
+            pipeline.push(doc! { "$project": projection });
+        }
+
+        self.rpmuh.aggregate(pipeline).allow_disk_use(true).await
+    }
+
+    pub async fn depth_history_rows(
+        &self,
+        query: Document,
+        sort_doc: Document,
+    ) -> Result<Cursor<PoolDepthPriceHistory>, mongodb::error::Error> {
+        self.depth_history.find(query).sort(sort_doc).await
+    }
+
+    pub async fn swap_history_rows(
+        &self,
+        query: Document,
+        sort_doc: Document,
+    ) -> Result<Cursor<PoolSwapHistory>, mongodb::error::Error> {
+        self.swap_history.find(query).sort(sort_doc).await
+    }
+
+    pub async fn pool_earnings_rows(
+        &self,
+        query: Document,
+        sort_doc: Document,
+    ) -> Result<Cursor<PoolEarnings>, mongodb::error::Error> {
+        self.earnings.find(query).sort(sort_doc).await
+    }
+
+    pub async fn earnings_summary_rows(
+        &self,
+        query: Document,
+        sort_doc: Document,
+    ) -> Result<Cursor<EarningsSummary>, mongodb::error::Error> {
+        self.earnings_summary.find(query).sort(sort_doc).await
+    }
+
+    pub async fn rune_pool_history_rows(
+        &self,
+        query: Document,
+        sort_doc: Document,
+    ) -> Result<Cursor<RunePoolHistory>, mongodb::error::Error> {
+        self.rpmuh.find(query).sort(sort_doc).await
+    }
+
+    /// The depth row in effect at `at`, i.e. the latest one starting at or before it, or the latest overall
+    pub async fn depth_snapshot_at(
+        &self,
+        pool: &str,
+        at: Option<i64>,
+    ) -> Result<Option<PoolDepthPriceHistory>, mongodb::error::Error> {
+        let mut query = doc! { "pool": pool };
+
+        if let Some(at) = at {
+            query.insert("start_time", doc! { "$lte": at });
+        }
+
+        self.depth_history.find_one(query).sort(doc! { "start_time": -1 }).await
+    }
+
+    /// The latest depth row of every pool, or of `pools`, starting at or before `at`
+    pub async fn depth_snapshots_at(
+        &self,
+        pools: &[String],
+        at: Option<i64>,
+    ) -> Result<Vec<Document>, mongodb::error::Error> {
+        let mut query = pools_time_range_query(pools, None, None);
+
+        if let Some(at) = at {
+            query.insert("start_time", doc! { "$lte": at });
+        }
+
+        let pipeline = vec![
+            doc! { "$match": query },
+            doc! { "$sort": { "pool": 1, "start_time": -1 } },
+            doc! { "$group": { "_id": "$pool", "row": { "$first": "$$ROOT" } } },
+            doc! { "$replaceRoot": { "newRoot": "$row" } },
+            doc! { "$project": { "_id": 0 } },
+            doc! { "$sort": { "pool": 1 } },
         ];
-    
-        let mut cursor = self.rpmuh.aggregate(pipeline).await?;
-        let mut results = Vec::new();
-    
-        while let Some(result) = cursor.next().await {
-            match result {
-                Ok(mut doc) => {
-                    doc.remove("_id");
-                    results.push(doc);
-                },
-                Err(e) => eprintln!("Error parsing document: {:?}", e),
+
+        let mut cursor = self.depth_history.aggregate(pipeline).allow_disk_use(true).await?;
+        let mut rows = Vec::new();
+
+        while let Some(row) = cursor.next().await {
+            rows.push(row?);
+        }
+
+        Ok(rows)
+    }
+
+    pub async fn rune_pool_snapshot_at(&self, at: Option<i64>) -> Result<Option<Document>, mongodb::error::Error> {
+        latest_row_at(&self.rpmuh, at).await
+    }
+
+    pub async fn earnings_summary_at(&self, at: Option<i64>) -> Result<Option<Document>, mongodb::error::Error> {
+        latest_row_at(&self.earnings_summary, at).await
+    }
+
+    pub async fn get_swap_intervals(
+        &self,
+        pool: Option<String>,
+        from: Option<i64>,
+        to: Option<i64>,
+        interval_duration: Option<i64>,
+        count: i64,
+    ) -> Result<Vec<Document>, mongodb::error::Error> {
+        let mut accumulators = doc! {};
+
+        for field in SWAP_SUM_FIELDS {
+            accumulators.insert(*field, doc! { "$sum": format!("${}", field) });
+        }
+
+        // Slips are averaged per swap, so keep the count weighted sum and divide once the bucket is known
+        for (slip_field, count_field) in SWAP_SLIP_FIELDS {
+            accumulators.insert(
+                format!("{}_weighted", slip_field),
+                doc! { "$sum": { "$multiply": [format!("${}", slip_field), format!("${}", count_field)] } },
+            );
+        }
+
+        accumulators.insert("rune_price_usd", doc! { "$last": "$rune_price_usd" });
+
+        aggregate_intervals(
+            &self.swap_history,
+            time_range_query(pool, from, to),
+            accumulators,
+            interval_duration,
+            false,
+            Some(count),
+            from.is_none(),
+        )
+        .await
+    }
+
+    pub async fn get_depth_intervals(
+        &self,
+        pool: String,
+        from: Option<i64>,
+        to: Option<i64>,
+        interval_duration: Option<i64>,
+        count: i64,
+    ) -> Result<Vec<Document>, mongodb::error::Error> {
+        let mut accumulators = doc! {};
+
+        for field in DEPTH_SNAPSHOT_FIELDS {
+            accumulators.insert(format!("first_{}", field), doc! { "$first": format!("${}", field) });
+            accumulators.insert(*field, doc! { "$last": format!("${}", field) });
+        }
+
+        aggregate_intervals(
+            &self.depth_history,
+            time_range_query(Some(pool), from, to),
+            accumulators,
+            interval_duration,
+            false,
+            Some(count),
+            from.is_none(),
+        )
+        .await
+    }
+
+    pub async fn get_price_candle_intervals(
+        &self,
+        pool: String,
+        price_field: &str,
+        from: Option<i64>,
+        to: Option<i64>,
+        interval_duration: i64,
+        count: i64,
+    ) -> Result<Vec<Document>, mongodb::error::Error> {
+        let price = format!("${}", price_field);
+        let accumulators = doc! {
+            "open": { "$first": &price },
+            "high": { "$max": &price },
+            "low": { "$min": &price },
+            "close": { "$last": &price },
+        };
+
+        aggregate_intervals(
+            &self.depth_history,
+            time_range_query(Some(pool), from, to),
+            accumulators,
+            Some(interval_duration),
+            false,
+            Some(count),
+            from.is_none(),
+        )
+        .await
+    }
+
+    /// OHLC of the RUNE price merged from the earnings summary and the swap history. Each hour is
+    /// taken from the earnings summary when it has a price and from the swap history otherwise,
+    /// `sources` lists where the hours of a candle came from.
+    pub async fn get_rune_price_intervals(
+        &self,
+        from: Option<i64>,
+        to: Option<i64>,
+        interval_duration: i64,
+        count: i64,
+    ) -> Result<Vec<Document>, mongodb::error::Error> {
+        let query = time_range_query(None, from, to);
+
+        let stages = vec![
+            doc! { "$match": &query },
+            doc! { "$project": {
+                "start_time": 1,
+                "end_time": 1,
+                "price": "$rune_price_usd",
+                "source": { "$literal": "earnings_summary" },
+                "priority": { "$literal": 0 },
+            }},
+            doc! { "$unionWith": {
+                "coll": "swap_history",
+                "pipeline": [
+                    { "$match": &query },
+                    // Every pool of an hour carries the same RUNE price
+                    { "$group": {
+                        "_id": "$start_time",
+                        "end_time": { "$max": "$end_time" },
+                        "price": { "$max": "$rune_price_usd" },
+                    }},
+                    { "$project": {
+                        "_id": 0,
+                        "start_time": "$_id",
+                        "end_time": 1,
+                        "price": 1,
+                        "source": { "$literal": "swap_history" },
+                        "priority": { "$literal": 1 },
+                    }},
+                ],
+            }},
+            doc! { "$match": { "price": { "$gt": 0 } } },
+            doc! { "$sort": { "start_time": 1, "priority": 1 } },
+            doc! { "$group": {
+                "_id": "$start_time",
+                "start_time": { "$first": "$start_time" },
+                "end_time": { "$first": "$end_time" },
+                "price": { "$first": "$price" },
+                "source": { "$first": "$source" },
+            }},
+        ];
+
+        let accumulators = doc! {
+            "open": { "$first": "$price" },
+            "high": { "$max": "$price" },
+            "low": { "$min": "$price" },
+            "close": { "$last": "$price" },
+            "sources": { "$addToSet": "$source" },
+        };
+
+        aggregate_stage_intervals(
+            &self.earnings_summary,
+            stages,
+            accumulators,
+            Some(interval_duration),
+            false,
+            Some(count),
+            from.is_none(),
+        )
+        .await
+    }
+
+    pub async fn get_swap_volume_intervals(
+        &self,
+        pool: String,
+        volume_field: &str,
+        from: i64,
+        to: i64,
+        interval_duration: i64,
+    ) -> Result<Vec<Document>, mongodb::error::Error> {
+        let accumulators = doc! { "volume": { "$sum": format!("${}", volume_field) } };
+
+        aggregate_intervals(
+            &self.swap_history,
+            time_range_query(Some(pool), Some(from), Some(to)),
+            accumulators,
+            Some(interval_duration),
+            false,
+            None,
+            false,
+        )
+        .await
+    }
+
+    pub async fn get_earnings_summary_intervals(
+        &self,
+        from: Option<i64>,
+        to: Option<i64>,
+        interval_duration: Option<i64>,
+        count: i64,
+    ) -> Result<Vec<Document>, mongodb::error::Error> {
+        let accumulators = doc! {
+            "block_rewards": { "$sum": "$block_rewards" },
+            "bonding_earnings": { "$sum": "$bonding_earnings" },
+            "liquidity_earnings": { "$sum": "$liquidity_earnings" },
+            "liquidity_fees": { "$sum": "$liquidity_fees" },
+            "avg_node_count": { "$avg": "$avg_node_count" },
+            "rune_price_usd": { "$last": "$rune_price_usd" },
+        };
+
+        aggregate_intervals(
+            &self.earnings_summary,
+            time_range_query(None, from, to),
+            accumulators,
+            interval_duration,
+            false,
+            Some(count),
+            from.is_none(),
+        )
+        .await
+    }
+
+    pub async fn get_pool_earnings_intervals(
+        &self,
+        from: Option<i64>,
+        to: Option<i64>,
+        interval_duration: Option<i64>,
+    ) -> Result<Vec<Document>, mongodb::error::Error> {
+        let accumulators = doc! {
+            "asset_liquidity_fees": { "$sum": "$asset_liquidity_fees" },
+            "rune_liquidity_fees": { "$sum": "$rune_liquidity_fees" },
+            "total_liquidity_fees_rune": { "$sum": "$total_liquidity_fees_rune" },
+            "saver_earning": { "$sum": "$saver_earning" },
+            "rewards": { "$sum": "$rewards" },
+        };
+
+        aggregate_intervals(
+            &self.earnings,
+            time_range_query(None, from, to),
+            accumulators,
+            interval_duration,
+            true,
+            None,
+            false,
+        )
+        .await
+    }
+
+    pub async fn get_pool_earnings_totals(
+        &self,
+        pools: &[String],
+        from: i64,
+        to: i64,
+    ) -> Result<Vec<Document>, mongodb::error::Error> {
+        let accumulators = doc! {
+            "total_liquidity_fees_rune": { "$sum": "$total_liquidity_fees_rune" },
+            "rewards": { "$sum": "$rewards" },
+            "saver_earning": { "$sum": "$saver_earning" },
+        };
+
+        aggregate_intervals(
+            &self.earnings,
+            pools_time_range_query(pools, Some(from), Some(to)),
+            accumulators,
+            None,
+            true,
+            None,
+            false,
+        )
+        .await
+    }
+
+    /// Swap fees per swap type in RUNE and valued in USD at each interval's RUNE price
+    pub async fn get_swap_fee_intervals(
+        &self,
+        pools: &[String],
+        from: i64,
+        to: i64,
+        interval_duration: Option<i64>,
+    ) -> Result<Vec<Document>, mongodb::error::Error> {
+        let mut accumulators = doc! {};
+        for field in SWAP_FEE_FIELDS {
+            accumulators.insert(*field, doc! { "$sum": format!("${}", field) });
+            accumulators.insert(
+                format!("{}_usd", field),
+                doc! { "$sum": { "$multiply": [format!("${}", field), "$rune_price_usd"] } },
+            );
+        }
+
+        aggregate_intervals(
+            &self.swap_history,
+            pools_time_range_query(pools, Some(from), Some(to)),
+            accumulators,
+            interval_duration,
+            true,
+            None,
+            false,
+        )
+        .await
+    }
+
+    /// Liquidity fees per side, with the RUNE total valued in USD at the RUNE price of the
+    /// earnings summary of the same interval
+    pub async fn get_liquidity_fee_intervals(
+        &self,
+        pools: &[String],
+        from: i64,
+        to: i64,
+        interval_duration: Option<i64>,
+    ) -> Result<Vec<Document>, mongodb::error::Error> {
+        let stages = vec![
+            doc! { "$match": pools_time_range_query(pools, Some(from), Some(to)) },
+            doc! { "$lookup": {
+                "from": "earnings_summary",
+                "localField": "start_time",
+                "foreignField": "start_time",
+                "as": "summary",
+            }},
+            doc! { "$addFields": {
+                "rune_price_usd": { "$ifNull": [{ "$first": "$summary.rune_price_usd" }, 0] },
+            }},
+        ];
+
+        let accumulators = doc! {
+            "asset_liquidity_fees": { "$sum": "$asset_liquidity_fees" },
+            "rune_liquidity_fees": { "$sum": "$rune_liquidity_fees" },
+            "total_liquidity_fees_rune": { "$sum": "$total_liquidity_fees_rune" },
+            "total_liquidity_fees_usd": {
+                "$sum": { "$multiply": ["$total_liquidity_fees_rune", "$rune_price_usd"] }
+            },
+        };
+
+        aggregate_stage_intervals(&self.earnings, stages, accumulators, interval_duration, true, None, false).await
+    }
+
+    /// Protocol wide liquidity fees from the earnings summary
+    pub async fn get_protocol_fee_intervals(
+        &self,
+        from: i64,
+        to: i64,
+        interval_duration: Option<i64>,
+    ) -> Result<Vec<Document>, mongodb::error::Error> {
+        let accumulators = doc! { "liquidity_fees": { "$sum": "$liquidity_fees" } };
+
+        aggregate_intervals(
+            &self.earnings_summary,
+            time_range_query(None, Some(from), Some(to)),
+            accumulators,
+            interval_duration,
+            false,
+            None,
+            false,
+        )
+        .await
+    }
+
+    // Savers hold synths, so their deposits are valued as the synth supply at the pool price in RUNE
+    pub async fn get_pool_depth_averages(
+        &self,
+        pools: &[String],
+        from: i64,
+        to: i64,
+    ) -> Result<Vec<Document>, mongodb::error::Error> {
+        let accumulators = doc! {
+            "avg_rune_depth": { "$avg": "$rune_depth" },
+            "avg_saver_depth": { "$avg": { "$multiply": ["$synth_supply", "$asset_price"] } },
+        };
+
+        aggregate_intervals(
+            &self.depth_history,
+            pools_time_range_query(pools, Some(from), Some(to)),
+            accumulators,
+            None,
+            true,
+            None,
+            false,
+        )
+        .await
+    }
+
+    /// One row per pool summarizing `from..to`: sums for flows, the closing snapshot for depths and RUNEPool
+    pub async fn get_period_totals(
+        &self,
+        dataset: &str,
+        pools: &[String],
+        from: i64,
+        to: i64,
+    ) -> Result<Vec<Document>, mongodb::error::Error> {
+        let query = pools_time_range_query(pools, Some(from), Some(to));
+        let mut accumulators = doc! {};
+
+        match dataset {
+            "swaps" => {
+                for field in SWAP_SUM_FIELDS {
+                    accumulators.insert(*field, doc! { "$sum": format!("${}", field) });
+                }
+                aggregate_intervals(&self.swap_history, query, accumulators, None, true, None, false).await
+            }
+            "depths" => {
+                for field in DEPTH_SNAPSHOT_FIELDS {
+                    accumulators.insert(*field, doc! { "$last": format!("${}", field) });
+                }
+                aggregate_intervals(&self.depth_history, query, accumulators, None, true, None, false).await
             }
+            "earnings" => {
+                for field in EARNINGS_SUM_FIELDS {
+                    accumulators.insert(*field, doc! { "$sum": format!("${}", field) });
+                }
+                aggregate_intervals(&self.earnings, query, accumulators, None, true, None, false).await
+            }
+            "runepool" => {
+                accumulators.insert("count", doc! { "$last": "$count" });
+                accumulators.insert("units", doc! { "$last": "$units" });
+                aggregate_intervals(&self.rpmuh, query, accumulators, None, false, None, false).await
+            }
+            _ => Ok(Vec::new()),
         }
-    
-        Ok(results)
     }
-    
+
+    /// Pools ranked by `field` of `dataset` over `from..to`, best first for `order` -1
+    #[allow(clippy::too_many_arguments)]
+    pub async fn get_leaderboard(
+        &self,
+        dataset: &str,
+        field: &str,
+        rank_by: RankBy,
+        from: Option<i64>,
+        to: Option<i64>,
+        limit: i64,
+        order: i32,
+    ) -> Result<Vec<Document>, mongodb::error::Error> {
+        let value = match rank_by {
+            RankBy::Sum => Bson::String("$total".to_string()),
+            RankBy::Growth => Bson::Document(doc! { "$subtract": ["$last", "$first"] }),
+            RankBy::Closing => Bson::String("$last".to_string()),
+        };
+
+        let field_ref = format!("${}", field);
+        let pipeline = vec![
+            doc! { "$match": time_range_query(None, from, to) },
+            doc! { "$sort": { "start_time": 1 } },
+            doc! { "$group": {
+                "_id": "$pool",
+                "start_time": { "$min": "$start_time" },
+                "end_time": { "$max": "$end_time" },
+                "total": { "$sum": &field_ref },
+                "first": { "$first": &field_ref },
+                "last": { "$last": &field_ref },
+            }},
+            doc! { "$project": { "_id": 0, "pool": "$_id", "value": value, "start_time": 1, "end_time": 1 } },
+            doc! { "$sort": { "value": order, "pool": 1 } },
+            doc! { "$limit": limit },
+        ];
+
+        let mut cursor = match dataset {
+            "swaps" => self.swap_history.aggregate(pipeline).allow_disk_use(true).await?,
+            "earnings" => self.earnings.aggregate(pipeline).allow_disk_use(true).await?,
+            _ => self.depth_history.aggregate(pipeline).allow_disk_use(true).await?,
+        };
+
+        let mut rows = Vec::new();
+        while let Some(row) = cursor.next().await {
+            rows.push(row?);
+        }
+
+        Ok(rows)
+    }
+
+    pub async fn get_rolling_depths(
+        &self,
+        pools: &[String],
+        window: i64,
+        from: Option<i64>,
+        to: Option<i64>,
+        interval_duration: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<Document>, mongodb::error::Error> {
+        let duration = doc! { "$subtract": ["$end_time", "$start_time"] };
+        let outputs = doc! {
+            "weighted_price": { "$sum": { "$multiply": ["$asset_price_usd", &duration] } },
+            "weighted_asset_depth": { "$sum": { "$multiply": ["$asset_depth", &duration] } },
+            "weighted_rune_depth": { "$sum": { "$multiply": ["$rune_depth", &duration] } },
+            "duration": { "$sum": &duration },
+        };
+        // A window of zero length rows has no duration to weigh by, its averages are null
+        let derived = doc! {
+            "twap_asset_price_usd": safe_divide(Bson::String("$weighted_price".to_string()), "$duration"),
+            "twa_asset_depth": safe_divide(Bson::String("$weighted_asset_depth".to_string()), "$duration"),
+            "twa_rune_depth": safe_divide(Bson::String("$weighted_rune_depth".to_string()), "$duration"),
+        };
+
+        aggregate_rolling(
+            &self.depth_history,
+            pools,
+            window,
+            from,
+            to,
+            outputs,
+            derived,
+            interval_duration,
+            limit,
+        )
+        .await
+    }
+
+    pub async fn get_rolling_swaps(
+        &self,
+        pools: &[String],
+        window: i64,
+        from: Option<i64>,
+        to: Option<i64>,
+        interval_duration: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<Document>, mongodb::error::Error> {
+        let outputs = doc! {
+            "rolling_total_volume_usd": { "$sum": "$total_volume_usd" },
+            "rolling_total_fees": { "$sum": "$total_fees" },
+            "rolling_total_count": { "$sum": "$total_count" },
+        };
+
+        aggregate_rolling(
+            &self.swap_history,
+            pools,
+            window,
+            from,
+            to,
+            outputs,
+            doc! {},
+            interval_duration,
+            limit,
+        )
+        .await
+    }
+
+    pub async fn get_rune_pool_intervals(
+        &self,
+        from: Option<i64>,
+        to: Option<i64>,
+        interval_duration: Option<i64>,
+        count: i64,
+    ) -> Result<Vec<Document>, mongodb::error::Error> {
+        let accumulators = doc! {
+            "first_count": { "$first": "$count" },
+            "first_units": { "$first": "$units" },
+            "count": { "$last": "$count" },
+            "units": { "$last": "$units" },
+        };
+
+        aggregate_intervals(
+            &self.rpmuh,
+            time_range_query(None, from, to),
+            accumulators,
+            interval_duration,
+            false,
+            Some(count),
+            from.is_none(),
+        )
+        .await
+    }
+}
+
+pub const SWAP_SUM_FIELDS: &[&str] = &[
+    "to_asset_count", "to_rune_count", "to_trade_count", "from_trade_count",
+    "synth_mint_count", "synth_redeem_count", "total_count",
+    "to_asset_volume", "to_rune_volume", "to_trade_volume", "from_trade_volume",
+    "synth_mint_volume", "synth_redeem_volume", "total_volume",
+    "to_asset_volume_usd", "to_rune_volume_usd", "to_trade_volume_usd", "from_trade_volume_usd",
+    "synth_mint_volume_usd", "synth_redeem_volume_usd", "total_volume_usd",
+    "to_asset_fees", "to_rune_fees", "to_trade_fees", "from_trade_fees",
+    "synth_mint_fees", "synth_redeem_fees", "total_fees",
+];
+
+pub const SWAP_FEE_FIELDS: &[&str] = &[
+    "to_asset_fees", "to_rune_fees", "to_trade_fees", "from_trade_fees",
+    "synth_mint_fees", "synth_redeem_fees", "total_fees",
+];
+
+pub const SWAP_SLIP_FIELDS: &[(&str, &str)] = &[
+    ("to_asset_average_slip", "to_asset_count"),
+    ("to_rune_average_slip", "to_rune_count"),
+    ("to_trade_average_slip", "to_trade_count"),
+    ("from_trade_average_slip", "from_trade_count"),
+    ("synth_mint_average_slip", "synth_mint_count"),
+    ("synth_redeem_average_slip", "synth_redeem_count"),
+    ("average_slip", "total_count"),
+];
+
+pub const EARNINGS_SUM_FIELDS: &[&str] = &[
+    "asset_liquidity_fees", "rune_liquidity_fees", "total_liquidity_fees_rune", "saver_earning", "rewards",
+];
+
+/// How a pool's rows within a period are reduced to the value it is ranked by
+#[derive(Debug, Clone, Copy)]
+pub enum RankBy {
+    Sum,
+    Growth,
+    Closing,
+}
+
+pub const DEPTH_SNAPSHOT_FIELDS: &[&str] = &[
+    "asset_depth", "asset_price", "asset_price_usd", "liquidity_units", "luvi",
+    "members_count", "rune_depth", "synth_supply", "synth_units", "units",
+];
+
+pub fn interval_duration(interval_unit: &str) -> i64 {
+    match interval_unit {
+        "day" => 86400,
+        "week" => 604800,
+        "month" => 2678400,
+        "quarter" => 7948800,
+        "year" => 31622400,
+        _ => 3600,
+    }
+}
+
+// Matches the requested time range plus the pool list and chain filters
+fn history_match(options: &HistoryQuery) -> Document {
+    let mut query = time_range_query(None, options.start_time, options.end_time);
+    let mut pool_filter = doc! {};
+
+    if !options.pools.is_empty() {
+        pool_filter.insert("$in", options.pools.clone());
+    }
+
+    if let Some(ref chain) = options.chain {
+        pool_filter.insert("$regex", format!("^{}\\.", chain));
+    }
+
+    if !pool_filter.is_empty() {
+        query.insert("pool", pool_filter);
+    }
+
+    query
+}
+
+// Creating an index that already exists is a no-op. Rows stored twice before the index existed
+// are removed first, an index that still cannot be created stops the server.
+async fn ensure_unique_index<T: Send + Sync>(collection: &Collection<T>, keys: Document) {
+    if let Err(e) = remove_duplicates(collection, &keys).await {
+        panic!("Error removing duplicate rows from {}: {:?}", collection.name(), e);
+    }
+
+    let index = IndexModel::builder()
+        .keys(keys.clone())
+        .options(IndexOptions::builder().unique(true).build())
+        .build();
+
+    if let Err(e) = collection.create_index(index).await {
+        panic!(
+            "Error creating the unique index {} on {}: {:?}. Remove the rows sharing these keys and restart.",
+            keys,
+            collection.name(),
+            e
+        );
+    }
+}
+
+// Keeps the most recently inserted row of every key. Interval rows were inserted again on every
+// fetch, so the newest row holds the latest values, and for earnings the newest pool rows point at
+// the newest summary.
+async fn remove_duplicates<T: Send + Sync>(collection: &Collection<T>, keys: &Document) -> Result<(), mongodb::error::Error> {
+    let mut group_key = doc! {};
+    for key in keys.keys() {
+        group_key.insert(key, format!("${}", key));
+    }
+
+    let pipeline = vec![
+        doc! { "$sort": { "_id": -1 } },
+        doc! { "$group": { "_id": group_key, "ids": { "$push": "$_id" } } },
+        doc! { "$match": { "ids.1": { "$exists": true } } },
+    ];
+
+    let mut cursor = collection.aggregate(pipeline).allow_disk_use(true).await?;
+    let mut duplicates = Vec::new();
+    while let Some(group) = cursor.next().await {
+        if let Ok(ids) = group?.get_array("ids") {
+            duplicates.extend(ids.iter().skip(1).cloned());
+        }
+    }
+
+    if duplicates.is_empty() {
+        return Ok(());
+    }
+
+    // Chunked so the $in list stays well below the document size limit
+    let mut removed = 0;
+    for ids in duplicates.chunks(10_000) {
+        removed += collection.delete_many(doc! { "_id": { "$in": ids } }).await?.deleted_count;
+    }
+    println!("Removed {} duplicate rows from {}", removed, collection.name());
+
+    Ok(())
+}
+
+// Intervals are keyed on pool and start_time, so storing an interval again refreshes it instead of duplicating it
+async fn upsert_interval<T: Serialize + Send + Sync>(
+    collection: &Collection<T>,
+    key: Document,
+    record: &T,
+) -> Result<StoredInterval, mongodb::error::Error> {
+    let mut fields = to_document(record)?;
+    let id = fields.remove("_id");
+    let new_id = id.as_ref().and_then(Bson::as_object_id);
+
+    let stored = collection
+        .clone_with_type::<Document>()
+        .find_one_and_update(key, doc! { "$set": fields, "$setOnInsert": { "_id": id } })
+        .upsert(true)
+        .return_document(ReturnDocument::After)
+        .projection(doc! { "_id": 1 })
+        .await?;
+
+    let id = stored
+        .and_then(|doc| doc.get_object_id("_id").ok())
+        .ok_or_else(|| mongodb::error::Error::custom("upsert returned no document"))?;
+
+    // An existing row keeps its own _id, only an insert takes the one set on insert
+    Ok(StoredInterval { id, inserted: Some(id) == new_id })
+}
+
+// Reads a numeric field regardless of whether Mongo stored it as a double or an integer
+pub fn number(doc: &Document, key: &str) -> f64 {
+    match doc.get(key) {
+        Some(Bson::Double(value)) => *value,
+        Some(Bson::Int32(value)) => *value as f64,
+        Some(Bson::Int64(value)) => *value as f64,
+        _ => 0.0,
+    }
+}
+
+pub fn time_range_query(pool: Option<String>, from: Option<i64>, to: Option<i64>) -> Document {
+    let mut query = doc! {};
+
+    if let Some(pool_value) = pool {
+        query.insert("pool", pool_value);
+    }
+
+    if let Some(from_timestamp) = from {
+        query.insert("start_time", doc! { "$gte": from_timestamp });
+    }
+
+    if let Some(to_timestamp) = to {
+        query.insert("end_time", doc! { "$lte": to_timestamp });
+    }
+
+    query
+}
+
+// Latest row starting at or before `at`, or the latest row overall
+async fn latest_row_at<T: Send + Sync>(
+    collection: &Collection<T>,
+    at: Option<i64>,
+) -> Result<Option<Document>, mongodb::error::Error> {
+    let query = match at {
+        Some(at) => doc! { "start_time": { "$lte": at } },
+        None => doc! {},
+    };
+
+    collection
+        .clone_with_type::<Document>()
+        .find_one(query)
+        .sort(doc! { "start_time": -1 })
+        .projection(doc! { "_id": 0 })
+        .await
+}
+
+fn pools_time_range_query(pools: &[String], from: Option<i64>, to: Option<i64>) -> Document {
+    let mut query = time_range_query(None, from, to);
+
+    if !pools.is_empty() {
+        query.insert("pool", doc! { "$in": pools });
+    }
+
+    query
+}
+
+// Depth fields the computed fields are derived from
+const DERIVED_SOURCE_FIELDS: &[&str] = &[
+    "asset_depth", "asset_price_usd", "rune_depth", "synth_supply", "liquidity_units", "members_count",
+];
+
+// Divides by a field, null when the field is zero or missing
+fn safe_divide(numerator: Bson, divisor: &str) -> Document {
+    doc! {
+        "$cond": [
+            { "$gt": [{ "$ifNull": [divisor, 0] }, 0] },
+            { "$divide": [numerator, divisor] },
+            Bson::Null
+        ]
+    }
+}
+
+// Computed depth fields, see `depth_history_model::DERIVED_FIELDS`. Depths are in 1e8 units and
+// both sides of a pool hold the same value, so the TVL is twice the asset side.
+fn depth_derived_fields() -> Document {
+    doc! {
+        "tvl_usd": { "$divide": [
+            { "$multiply": [2, { "$ifNull": ["$asset_depth", 0] }, { "$ifNull": ["$asset_price_usd", 0] }] },
+            100_000_000_i64
+        ] },
+        "synth_utilization": safe_divide(Bson::String("$synth_supply".to_string()), "$asset_depth"),
+        "implied_rune_price_usd": safe_divide(
+            Bson::Document(doc! { "$multiply": ["$asset_price_usd", "$asset_depth"] }),
+            "$rune_depth",
+        ),
+        "units_per_member": safe_divide(Bson::String("$liquidity_units".to_string()), "$members_count"),
+    }
+}
+
+// Narrows the rows entering a bucketed pipeline to what the response, the sort, the filter and the
+// bucketing need
+fn pipeline_projection(projection: &Document, sort_doc: &Document, filter: &Document) -> Document {
+    let mut early = projection.clone();
+
+    for key in sort_doc.keys().chain(filter.keys()).map(String::as_str).chain(["pool", "start_time", "end_time"]) {
+        early.insert(key, 1);
+    }
+
+    early
+}
+
+// Emits a row for every missing bucket between `from` and `to`, or between the first and last row
+// when the range is open, and flags it with `filled: true`. Each pool is filled on its own when
+// the rows are per pool.
+fn fill_stages(
+    fill: Fill,
+    fields: &[&str],
+    interval_duration: i64,
+    from: Option<i64>,
+    to: Option<i64>,
+    per_pool: bool,
+) -> Vec<Document> {
+    let values: Vec<&str> = fields
+        .iter()
+        .copied()
+        .filter(|field| !["pool", "start_time", "end_time"].contains(field))
+        .collect();
+
+    let bounds = match (from, to) {
+        (Some(from), Some(to)) => Bson::Array(vec![Bson::Int64(from - from.rem_euclid(interval_duration)), Bson::Int64(to)]),
+        _ if per_pool => Bson::String("partition".to_string()),
+        _ => Bson::String("full".to_string()),
+    };
+
+    let mut densify = doc! {
+        "field": "start_time",
+        "range": { "step": interval_duration, "bounds": bounds },
+    };
+
+    let mut fill_output = doc! { "filled": { "value": true } };
+    for field in &values {
+        match fill {
+            Fill::Zero => fill_output.insert(*field, doc! { "value": 0 }),
+            Fill::Previous => fill_output.insert(*field, doc! { "method": "locf" }),
+            Fill::None | Fill::Null => None,
+        };
+    }
+
+    let mut fill_stage = doc! { "sortBy": { "start_time": 1 }, "output": fill_output };
+
+    if per_pool {
+        densify.insert("partitionByFields", vec!["pool"]);
+        fill_stage.insert("partitionByFields", vec!["pool"]);
+    } else if let Ok(output) = fill_stage.get_document_mut("output") {
+        output.insert("pool", doc! { "method": "locf" });
+    }
+
+    let mut completed = doc! { "end_time": { "$ifNull": ["$end_time", { "$add": ["$start_time", interval_duration] }] } };
+    if fill == Fill::Null {
+        for field in &values {
+            completed.insert(*field, doc! { "$ifNull": [format!("${}", field), Bson::Null] });
+        }
+    }
+
+    // Filled rows have no _id, so hourly rows drop theirs too as the bucketed rows already do
+    vec![
+        doc! { "$unset": "_id" },
+        doc! { "$addFields": { "filled": false } },
+        doc! { "$densify": densify },
+        doc! { "$fill": fill_stage },
+        doc! { "$addFields": completed },
+    ]
+}
+
+// Groups the matched rows into fixed size buckets aligned on start_time. Without an interval
+// every row falls into a single bucket spanning the whole range. When `latest_first` is set the
+// limit keeps the most recent buckets; the result is always returned oldest first.
+async fn aggregate_intervals<T: Send + Sync>(
+    collection: &Collection<T>,
+    query: Document,
+    accumulators: Document,
+    interval_duration: Option<i64>,
+    group_by_pool: bool,
+    limit: Option<i64>,
+    latest_first: bool,
+) -> Result<Vec<Document>, mongodb::error::Error> {
+    aggregate_stage_intervals(
+        collection,
+        vec![doc! { "$match": query }],
+        accumulators,
+        interval_duration,
+        group_by_pool,
+        limit,
+        latest_first,
+    )
+    .await
+}
+
+// Same as `aggregate_intervals`, with `stages` selecting and preparing the rows before they are grouped
+async fn aggregate_stage_intervals<T: Send + Sync>(
+    collection: &Collection<T>,
+    stages: Vec<Document>,
+    accumulators: Document,
+    interval_duration: Option<i64>,
+    group_by_pool: bool,
+    limit: Option<i64>,
+    latest_first: bool,
+) -> Result<Vec<Document>, mongodb::error::Error> {
+    let bucket = match interval_duration {
+        Some(duration) => Bson::Document(doc! {
+            "$subtract": [ "$start_time", { "$mod": [ "$start_time", duration ] } ]
+        }),
+        None => Bson::Null,
+    };
+
+    let (group_id, bucket_ref) = if group_by_pool {
+        (Bson::Document(doc! { "bucket": bucket, "pool": "$pool" }), "$_id.bucket")
+    } else {
+        (bucket, "$_id")
+    };
+
+    let mut group = doc! {
+        "_id": group_id,
+        "start_time": { "$min": "$start_time" },
+        "end_time": { "$max": "$end_time" },
+    };
+    group.extend(accumulators);
+
+    let mut pipeline = stages;
+    pipeline.push(doc! { "$sort": { "start_time": 1 } });
+    pipeline.push(doc! { "$group": group });
+
+    if let Some(duration) = interval_duration {
+        pipeline.push(doc! { "$addFields": {
+            "start_time": bucket_ref,
+            "end_time": { "$add": [bucket_ref, duration] },
+        }});
+    }
+
+    if group_by_pool {
+        pipeline.push(doc! { "$addFields": { "pool": "$_id.pool" } });
+    }
+
+    pipeline.push(doc! { "$sort": { "start_time": if latest_first { -1 } else { 1 } } });
+
+    if let Some(limit) = limit {
+        pipeline.push(doc! { "$limit": limit });
+    }
+
+    pipeline.push(doc! { "$sort": { "start_time": 1, "pool": 1 } });
+    pipeline.push(doc! { "$project": { "_id": 0 } });
+
+    let mut cursor = collection.aggregate(pipeline).await?;
+    let mut results = Vec::new();
+
+    while let Some(result) = cursor.next().await {
+        match result {
+            Ok(doc) => results.push(doc),
+            Err(e) => eprintln!("Error parsing document: {:?}", e),
+        }
+    }
+
+    Ok(results)
+}
+
+// Computes trailing window statistics per pool with $setWindowFields. Every accumulator in `outputs`
+// covers the rows that started within `window` seconds up to the current row; rows before `from`
+// are read only to fill the first windows. When `derived` is given, its fields are computed from
+// the outputs and returned in their place, otherwise the outputs themselves are returned. With
+// an interval only the last row of each pool and interval is kept, and without `from` the limit
+// keeps the latest rows. The result is always returned oldest first.
+#[allow(clippy::too_many_arguments)]
+async fn aggregate_rolling<T: Send + Sync>(
+    collection: &Collection<T>,
+    pools: &[String],
+    window: i64,
+    from: Option<i64>,
+    to: Option<i64>,
+    outputs: Document,
+    derived: Document,
+    interval_duration: Option<i64>,
+    limit: i64,
+) -> Result<Vec<Document>, mongodb::error::Error> {
+    let mut projection = doc! { "_id": 0, "pool": 1, "start_time": 1, "end_time": 1 };
+    let mut window_outputs = doc! {};
+
+    for (field, accumulator) in outputs {
+        if let Bson::Document(mut accumulator) = accumulator {
+            accumulator.insert("window", doc! { "range": [-(window - 1), 0] });
+            window_outputs.insert(field, accumulator);
+        }
+    }
+
+    let returned = if derived.is_empty() { &window_outputs } else { &derived };
+    for field in returned.keys() {
+        projection.insert(field.clone(), 1);
+    }
+
+    let mut pipeline = vec![
+        doc! { "$match": pools_time_range_query(pools, from.map(|from| from - window), to) },
+        doc! { "$setWindowFields": {
+            "partitionBy": "$pool",
+            "sortBy": { "start_time": 1 },
+            "output": window_outputs,
+        }},
+    ];
+
+    if let Some(from) = from {
+        pipeline.push(doc! { "$match": { "start_time": { "$gte": from } } });
+    }
+
+    if !derived.is_empty() {
+        pipeline.push(doc! { "$addFields": derived });
+    }
+
+    if let Some(duration) = interval_duration {
+        pipeline.extend([
+            doc! { "$sort": { "start_time": 1 } },
+            doc! { "$group": {
+                "_id": {
+                    "bucket": { "$subtract": ["$start_time", { "$mod": ["$start_time", duration] }] },
+                    "pool": "$pool",
+                },
+                "row": { "$last": "$$ROOT" },
+            }},
+            doc! { "$replaceRoot": { "newRoot": "$row" } },
+        ]);
+    }
+
+    let latest_first = from.is_none();
+    pipeline.extend([
+        doc! { "$sort": { "start_time": if latest_first { -1 } else { 1 }, "pool": 1 } },
+        doc! { "$limit": limit },
+        doc! { "$sort": { "start_time": 1, "pool": 1 } },
+        doc! { "$project": projection },
+    ]);
+
+    let mut cursor = collection.aggregate(pipeline).allow_disk_use(true).await?;
+    let mut rows = Vec::new();
+
+    while let Some(row) = cursor.next().await {
+        rows.push(row?);
+    }
+
+    Ok(rows)
 }
@@ -4,81 +4,108 @@ use chrono::{DateTime, Utc};
 
 use crate::models::depth_history_model::{PoolDepthPriceHistory, PoolDepthPriceHistoryRequest};
 
-use super::db::Database;
+use super::{anomaly_detector, db::Database, midgard_proxy::parse_number};
 
 
 #[derive(Debug, Serialize, Deserialize)]
-struct Meta {
-    startTime: String,
-    endTime: String,
-    priceShiftLoss: String,
-    luviIncrease: String,
-    startAssetDepth: String,
-    startRuneDepth: String,
-    startLPUnits: String,
-    startMemberCount: String,
-    startSynthUnits: String,
-    endAssetDepth: String,
-    endRuneDepth: String,
-    endLPUnits: String,
-    endMemberCount: String,
-    endSynthUnits: String,
+#[allow(non_snake_case)]
+pub struct Meta {
+    pub startTime: String,
+    pub endTime: String,
+    pub priceShiftLoss: String,
+    pub luviIncrease: String,
+    pub startAssetDepth: String,
+    pub startRuneDepth: String,
+    pub startLPUnits: String,
+    pub startMemberCount: String,
+    pub startSynthUnits: String,
+    pub endAssetDepth: String,
+    pub endRuneDepth: String,
+    pub endLPUnits: String,
+    pub endMemberCount: String,
+    pub endSynthUnits: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-struct Interval {
-    startTime: String,
-    endTime: String,
-    assetDepth: String,
-    runeDepth: String,
-    assetPrice: String,
-    assetPriceUSD: String,
-    liquidityUnits: String,
-    membersCount: String,
-    synthUnits: String,
-    synthSupply: String,
-    units: String,
-    luvi: String,
+#[allow(non_snake_case)]
+pub struct Interval {
+    pub startTime: String,
+    pub endTime: String,
+    pub assetDepth: String,
+    pub runeDepth: String,
+    pub assetPrice: String,
+    pub assetPriceUSD: String,
+    pub liquidityUnits: String,
+    pub membersCount: String,
+    pub synthUnits: String,
+    pub synthSupply: String,
+    pub units: String,
+    pub luvi: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-struct ApiResponse {
-    meta: Meta,
-    intervals: Vec<Interval>,
+pub struct ApiResponse {
+    pub meta: Meta,
+    pub intervals: Vec<Interval>,
 }
 
-async fn store_intervals_in_db(intervals: Vec<Interval>, pool: &String, db: &Database) {
+// Parses the whole response up front, so a malformed interval stores nothing of it
+pub fn parse_intervals(intervals: Vec<Interval>, pool: &str) -> Result<Vec<PoolDepthPriceHistory>, String> {
+    let mut rows = Vec::new();
+
     for interval in intervals {
         let new_depth_history = PoolDepthPriceHistory::try_from(PoolDepthPriceHistoryRequest {
             pool: pool.to_string(), 
         
-            asset_depth: interval.assetDepth.parse::<f64>().expect("Failed to parse assetDepth"),
-            asset_price: interval.assetPrice.parse::<f64>().expect("Failed to parse assetPrice"),
-            asset_price_usd: interval.assetPriceUSD.parse::<f64>().expect("Failed to parse assetPriceUSD"),
+            asset_depth: parse_number(&interval.assetDepth, "assetDepth")?,
+            asset_price: parse_number(&interval.assetPrice, "assetPrice")?,
+            asset_price_usd: parse_number(&interval.assetPriceUSD, "assetPriceUSD")?,
             
-            end_time: interval.endTime.parse::<i64>().expect("Failed to parse endTime"),
-            start_time: interval.startTime.parse::<i64>().expect("Failed to parse startTime"),
+            end_time: parse_number(&interval.endTime, "endTime")?,
+            start_time: parse_number(&interval.startTime, "startTime")?,
             
-            liquidity_units: interval.liquidityUnits.parse::<f64>().expect("Failed to parse liquidityUnits"),
-            luvi: interval.luvi.parse::<f64>().expect("Failed to parse luvi"),
-            members_count: interval.membersCount.parse::<i64>().expect("Failed to parse membersCount"),
+            liquidity_units: parse_number(&interval.liquidityUnits, "liquidityUnits")?,
+            luvi: parse_number(&interval.luvi, "luvi")?,
+            members_count: parse_number(&interval.membersCount, "membersCount")?,
             
-            rune_depth: interval.runeDepth.parse::<f64>().expect("Failed to parse runeDepth"),
-            synth_supply: interval.synthSupply.parse::<f64>().expect("Failed to parse synthSupply"),
-            synth_units: interval.synthUnits.parse::<f64>().expect("Failed to parse synthUnits"),
-            units: interval.units.parse::<f64>().expect("Failed to parse units"),
+            rune_depth: parse_number(&interval.runeDepth, "runeDepth")?,
+            synth_supply: parse_number(&interval.synthSupply, "synthSupply")?,
+            synth_units: parse_number(&interval.synthUnits, "synthUnits")?,
+            units: parse_number(&interval.units, "units")?,
         })
         .unwrap();
-        
 
-        match db.create_depth_history(new_depth_history).await {
-            Ok(result) => println!("Successfully inserted: {:?}", result.inserted_id),
+        rows.push(new_depth_history);
+    }
+
+    Ok(rows)
+}
+
+/// `live` rows are closed intervals from the ingestion cycle, only their inserts reach the live feed and are scored for anomalies.
+/// Returns the number of rows stored, failures are logged.
+pub async fn store_intervals_in_db(rows: Vec<PoolDepthPriceHistory>, pool: &str, db: &Database, live: bool) -> usize {
+    let mut stored_count = 0;
+
+    for new_depth_history in rows {
+        let (start_time, end_time) = (new_depth_history.start_time, new_depth_history.end_time);
+        let values = anomaly_detector::metric_values(anomaly_detector::DEPTH_METRICS, &new_depth_history);
+
+        match db.create_depth_history(new_depth_history, live).await {
+            Ok(stored) => {
+                println!("Successfully stored: {:?}", stored.id);
+                stored_count += 1;
+                if live && stored.inserted {
+                    anomaly_detector::score_interval(db, "depths", pool, start_time, end_time, &values).await;
+                }
+            }
             Err(e) => eprintln!("Error inserting document: {:?}", e),
         }
     }
+
+    stored_count
 }
 
-pub async fn fetch_and_store_depth_history(db: &Database, pool: &String, interval: &String, start_time: i64) -> Result<(), Error> {
+pub async fn fetch_and_store_depth_history(db: &Database, pool: &String, interval: &String, start_time: i64, live: bool) -> Result<(), Error> {
     let mut current_time = start_time;
 
     loop {
@@ -91,7 +118,18 @@ pub async fn fetch_and_store_depth_history(db: &Database, pool: &String, interva
 
         let response = reqwest::get(&url).await?.json::<ApiResponse>().await?;
 
-        store_intervals_in_db(response.intervals, &pool, db).await;
+        match parse_intervals(response.intervals, pool) {
+            Ok(mut rows) => {
+                // The hour in progress is left to the next cycle, so the feed and the anomaly scores
+                // only ever see closed intervals
+                if live {
+                    let now = Utc::now().timestamp();
+                    rows.retain(|row| row.end_time <= now);
+                }
+                store_intervals_in_db(rows, pool, db, live).await;
+            }
+            Err(e) => eprintln!("Error parsing depth intervals: {}", e),
+        }
         let end_time: i64 = response.meta.endTime.parse().unwrap();
 
 
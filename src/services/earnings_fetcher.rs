@@ -4,94 +4,138 @@ use chrono::{DateTime, Utc};
 
 
 use crate::models::{earning_summary_model::{EarningsSummary, EarningsSummaryRequest}, earnings_model::{PoolEarnings, PoolEarningsRequest}};
-use super::db::Database;
+use super::{db::Database, midgard_proxy::parse_number};
 
 #[derive(Debug, Serialize, Deserialize)]
-struct Pool {
-    pool: String,
-    assetLiquidityFees: String,
-    runeLiquidityFees: String,
-    totalLiquidityFeesRune: String,
-    saverEarning: String,
-    rewards: String,
-    earnings: String,
+#[allow(non_snake_case)]
+pub struct Pool {
+    pub pool: String,
+    pub assetLiquidityFees: String,
+    pub runeLiquidityFees: String,
+    pub totalLiquidityFeesRune: String,
+    pub saverEarning: String,
+    pub rewards: String,
+    pub earnings: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-struct Interval {
-    startTime: String,
-    endTime: String,
-    avgNodeCount: String,
-    blockRewards: String,
-    bondingEarnings: String,
-    earnings: String,
-    liquidityEarnings: String,
-    liquidityFees: String,
-    runePriceUSD: String,
-    pools: Vec<Pool>,
+#[allow(non_snake_case)]
+pub struct Interval {
+    pub startTime: String,
+    pub endTime: String,
+    pub avgNodeCount: String,
+    pub blockRewards: String,
+    pub bondingEarnings: String,
+    pub earnings: String,
+    pub liquidityEarnings: String,
+    pub liquidityFees: String,
+    pub runePriceUSD: String,
+    pub pools: Vec<Pool>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-struct Meta {
-    avgNodeCount: String,
-    blockRewards: String,
-    bondingEarnings: String,
-    earnings: String,
-    endTime: String,
-    liquidityEarnings: String,
-    liquidityFees: String,
-    pools: Vec<Pool>,
-    runePriceUSD: String,
-    startTime: String,
+#[allow(non_snake_case)]
+pub struct Meta {
+    pub avgNodeCount: String,
+    pub blockRewards: String,
+    pub bondingEarnings: String,
+    pub earnings: String,
+    pub endTime: String,
+    pub liquidityEarnings: String,
+    pub liquidityFees: String,
+    pub pools: Vec<Pool>,
+    pub runePriceUSD: String,
+    pub startTime: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-struct ApiResponse {
-    intervals: Vec<Interval>,
-    meta: Meta,
+pub struct ApiResponse {
+    pub intervals: Vec<Interval>,
+    pub meta: Meta,
 }
 
-async fn store_earnings_in_db(intervals: Vec<Interval>, meta: Meta, db: &Database) {
+/// An interval's network summary with the earnings of every pool in it
+pub type EarningsInterval = (EarningsSummary, Vec<PoolEarnings>);
+
+// Parses the whole response up front, so a malformed interval stores nothing of it. Pool rows link to
+// their summary's new id until the summary is stored.
+pub fn parse_earnings(intervals: Vec<Interval>) -> Result<Vec<EarningsInterval>, String> {
+    let mut rows = Vec::new();
+
     for interval in intervals {
         let summary_request = EarningsSummaryRequest {
-            start_time: interval.startTime.clone().parse::<i64>().expect("Failed to parse startTime"),
-            end_time: interval.endTime.clone().parse::<i64>().expect("Failed to parse endTime"),
-            avg_node_count: interval.avgNodeCount.clone().parse::<f64>().expect("Failed to parse avgNodeCount"),
-            block_rewards: interval.blockRewards.clone().parse::<f64>().expect("Failed to parse block_rewards"),
-            bonding_earnings: interval.bondingEarnings.clone().parse::<f64>().expect("Failed to parse bonding_earnings"),
-            liquidity_earnings: interval.liquidityEarnings.clone().parse::<f64>().expect("Failed to parse liquidity_earnings"),
-            liquidity_fees: interval.liquidityFees.clone().parse::<f64>().expect("Failed to parseliquidity_fees "),
-            rune_price_usd: interval.runePriceUSD.clone().parse::<f64>().expect("Failed to parse rune_price_usd"),
+            start_time: parse_number(&interval.startTime, "startTime")?,
+            end_time: parse_number(&interval.endTime, "endTime")?,
+            avg_node_count: parse_number(&interval.avgNodeCount, "avgNodeCount")?,
+            block_rewards: parse_number(&interval.blockRewards, "blockRewards")?,
+            bonding_earnings: parse_number(&interval.bondingEarnings, "bondingEarnings")?,
+            liquidity_earnings: parse_number(&interval.liquidityEarnings, "liquidityEarnings")?,
+            liquidity_fees: parse_number(&interval.liquidityFees, "liquidityFees")?,
+            rune_price_usd: parse_number(&interval.runePriceUSD, "runePriceUSD")?,
         };
 
         let earnings_summary = EarningsSummary::try_from(summary_request).unwrap();
-        let inserted_summary_id = db.create_earnings_summary(earnings_summary).await.unwrap().inserted_id;
+        let mut pool_rows = Vec::new();
 
         for pool in interval.pools {
             let pool_request = PoolEarningsRequest {
                 pool: pool.pool.clone(),
-                asset_liquidity_fees: pool.assetLiquidityFees.clone().parse::<f64>().expect("Failed to parse asset_liquidity_fees"),
-                rune_liquidity_fees: pool.runeLiquidityFees.clone().parse::<f64>().expect("Failed to parse rune_liquidity_fees"),
-                total_liquidity_fees_rune: pool.totalLiquidityFeesRune.clone().parse::<f64>().expect("Failed to parse total_liquidity_fees_rune"),
-                saver_earning: pool.saverEarning.clone().parse::<f64>().expect("Failed to parse saver_earning"),
-                rewards: pool.rewards.clone().parse::<f64>().expect("Failed to parse rewards"),
-                start_time:interval.startTime.clone().parse::<i64>().expect("Failed to parse startTime"),
-                end_time: interval.endTime.clone().parse::<i64>().expect("Failed to parse endTime"),
-                earnings_summary_id: inserted_summary_id.as_object_id().unwrap(),
+                asset_liquidity_fees: parse_number(&pool.assetLiquidityFees, "assetLiquidityFees")?,
+                rune_liquidity_fees: parse_number(&pool.runeLiquidityFees, "runeLiquidityFees")?,
+                total_liquidity_fees_rune: parse_number(&pool.totalLiquidityFeesRune, "totalLiquidityFeesRune")?,
+                saver_earning: parse_number(&pool.saverEarning, "saverEarning")?,
+                rewards: parse_number(&pool.rewards, "rewards")?,
+                start_time: earnings_summary.start_time,
+                end_time: earnings_summary.end_time,
+                earnings_summary_id: earnings_summary._id,
             };
 
-            let pool_earnings = PoolEarnings::try_from(pool_request).unwrap();
-            match db.create_pool_earnings(pool_earnings).await {
-                Ok(result) => println!("Successfully inserted pool earnings: {:?}", result.inserted_id),
-                Err(e) => eprintln!("Error inserting pool earnings document: {:?}", e),
+            pool_rows.push(PoolEarnings::try_from(pool_request).unwrap());
+        }
+
+        rows.push((earnings_summary, pool_rows));
+    }
+
+    Ok(rows)
+}
+
+/// `live` rows are closed intervals from the ingestion cycle, only their inserts reach the live feed.
+/// Returns the number of intervals stored with their summary and every pool row, failures are logged.
+pub async fn store_earnings_in_db(rows: Vec<EarningsInterval>, db: &Database, live: bool) -> usize {
+    let mut stored_count = 0;
+
+    for (earnings_summary, pool_rows) in rows {
+        let summary_id = match db.create_earnings_summary(earnings_summary, live).await {
+            Ok(stored) => stored.id,
+            Err(e) => {
+                eprintln!("Error storing earnings summary document: {:?}", e);
+                continue;
+            }
+        };
+
+        let mut complete = true;
+        for mut pool_earnings in pool_rows {
+            // A summary stored before keeps its id
+            pool_earnings.earnings_summary_id = summary_id;
+
+            match db.create_pool_earnings(pool_earnings, live).await {
+                Ok(stored) => println!("Successfully stored pool earnings: {:?}", stored.id),
+                Err(e) => {
+                    eprintln!("Error storing pool earnings document: {:?}", e);
+                    complete = false;
+                }
             }
         }
+
+        if complete {
+            stored_count += 1;
+        }
     }
 
-    
+    stored_count
 }
 
-pub async fn fetch_and_store_earnings(db: &Database, interval: &String, start_time: i64) -> Result<(), Error> {
+pub async fn fetch_and_store_earnings(db: &Database, interval: &String, start_time: i64, live: bool) -> Result<(), Error> {
     let mut current_time = start_time;
 
     loop {
@@ -107,10 +151,20 @@ pub async fn fetch_and_store_earnings(db: &Database, interval: &String, start_ti
         println!("Raw response: {}", raw_body);
         let response = reqwest::get(&url).await?.json::<ApiResponse>().await?;
         
-        let meta_end_time = response.meta.endTime.clone();
-        store_earnings_in_db(response.intervals, response.meta, db).await;
+        let end_time: i64 = response.meta.endTime.parse().unwrap();
+        match parse_earnings(response.intervals) {
+            Ok(mut rows) => {
+                // The hour in progress is left to the next cycle, so the feed and the anomaly scores
+                // only ever see closed intervals
+                if live {
+                    let now = Utc::now().timestamp();
+                    rows.retain(|(summary, _)| summary.end_time <= now);
+                }
+                store_earnings_in_db(rows, db, live).await;
+            }
+            Err(e) => eprintln!("Error parsing earnings intervals: {}", e),
+        }
 
-        let end_time: i64 = meta_end_time.parse().unwrap();
         let current_utc: DateTime<Utc> = Utc::now();
         let current_timestamp = current_utc.timestamp();
 
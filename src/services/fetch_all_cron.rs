@@ -22,6 +22,7 @@ pub async fn run_cron_job(db: Data<Database>, pool: String) {
             &pool,
             &interval_str,
             one_hour_ago,
+            true,
         )
         .await;
     
@@ -29,6 +30,7 @@ pub async fn run_cron_job(db: Data<Database>, pool: String) {
             &db,
             &interval_str,
             one_hour_ago,
+            true,
         )
         .await;
 
@@ -36,6 +38,7 @@ pub async fn run_cron_job(db: Data<Database>, pool: String) {
             &db,
             &interval_str,
             one_hour_ago,
+            true,
         )
         .await;
 
@@ -44,6 +47,7 @@ pub async fn run_cron_job(db: Data<Database>, pool: String) {
             &pool,
             &interval_str,
             one_hour_ago,
+            true,
         )
         .await;
 
@@ -63,6 +67,9 @@ pub async fn run_cron_job(db: Data<Database>, pool: String) {
             println!("All data fetch tasks completed successfully.");
         }
 
+        // Rules also run after a failed cycle, stale data is what some of them watch for
+        services::alerts::evaluate_rules(&db).await;
+
         let end_time = Utc::now();
         println!("Data fetch completed at {:?}, duration: {:?}", end_time, end_time - start_time);
         // println!("Cron job running");
@@ -0,0 +1,383 @@
+use std::collections::HashMap;
+
+use actix_web::web::Data;
+use async_graphql::{
+    dataloader::{DataLoader, Loader},
+    ComplexObject, Context, EmptyMutation, EmptySubscription, InputObject, Object, Result, Schema, SimpleObject,
+};
+use futures_util::stream::StreamExt;
+use mongodb::bson::{oid::ObjectId, Bson, Document};
+
+use crate::models::{depth_history_model, earnings_model, rpmuh_model, swap_history_model};
+
+use super::{
+    db::{interval_duration, Database},
+    query_builder::{build_filter, build_sort, parse_chain, parse_fill, parse_group_by, parse_pools, Fill, HistoryQuery},
+};
+
+pub type MetricsSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+/// Deepest selection accepted, the schema nests at most a summary inside an earnings row
+const MAX_DEPTH: usize = 8;
+
+const VALID_INTERVALS: &[&str] = &["hour", "day", "week", "month", "quarter", "year"];
+
+/// Reads a row field stored with any numeric BSON type, the way bucketed rows mix them
+pub trait FieldValue: Sized {
+    fn from_bson(value: &Bson) -> Option<Self>;
+}
+
+impl FieldValue for f64 {
+    fn from_bson(value: &Bson) -> Option<Self> {
+        match value {
+            Bson::Double(value) => Some(*value),
+            Bson::Int64(value) => Some(*value as f64),
+            Bson::Int32(value) => Some(*value as f64),
+            _ => None,
+        }
+    }
+}
+
+impl FieldValue for i64 {
+    fn from_bson(value: &Bson) -> Option<Self> {
+        match value {
+            Bson::Int64(value) => Some(*value),
+            Bson::Int32(value) => Some(*value as i64),
+            Bson::Double(value) => Some(*value as i64),
+            _ => None,
+        }
+    }
+}
+
+impl FieldValue for bool {
+    fn from_bson(value: &Bson) -> Option<Self> {
+        value.as_bool()
+    }
+}
+
+impl FieldValue for String {
+    fn from_bson(value: &Bson) -> Option<Self> {
+        value.as_str().map(str::to_string)
+    }
+}
+
+impl FieldValue for ObjectId {
+    fn from_bson(value: &Bson) -> Option<Self> {
+        value.as_object_id()
+    }
+}
+
+// Every field is nullable, since projections, null fills and bucketing can leave any of them out.
+// Hidden fields are read from the row but not exposed.
+macro_rules! graphql_row {
+    (
+        $(#[$attr:meta])*
+        $name:ident { $($field:ident: $ty:ty),* $(,)? }
+        $(hidden { $($hidden:ident: $hidden_ty:ty),* $(,)? })?
+    ) => {
+        #[derive(Debug, Clone, SimpleObject)]
+        #[graphql(rename_fields = "snake_case")]
+        $(#[$attr])*
+        pub struct $name {
+            $(pub $field: Option<$ty>,)*
+            $($(#[graphql(skip)] pub $hidden: Option<$hidden_ty>,)*)?
+        }
+
+        impl From<&Document> for $name {
+            fn from(row: &Document) -> Self {
+                Self {
+                    $($field: row.get(stringify!($field)).and_then(FieldValue::from_bson),)*
+                    $($($hidden: row.get(stringify!($hidden)).and_then(FieldValue::from_bson),)*)?
+                }
+            }
+        }
+    };
+}
+
+graphql_row! {
+    /// Depth and price of a pool over one interval, the rows of GET /depths
+    PoolDepthPriceHistory {
+        pool: String, asset_depth: f64, asset_price: f64, asset_price_usd: f64, start_time: i64, end_time: i64,
+        liquidity_units: f64, luvi: f64, members_count: i64, rune_depth: f64, synth_supply: f64, synth_units: f64,
+        units: f64,
+        tvl_usd: f64, synth_utilization: f64, implied_rune_price_usd: f64, units_per_member: f64,
+        filled: bool,
+    }
+}
+
+graphql_row! {
+    /// Swaps of a pool over one interval, the rows of GET /swaps
+    PoolSwapHistory {
+        pool: String, start_time: i64, end_time: i64,
+        to_asset_count: i64, to_rune_count: i64, to_trade_count: i64, from_trade_count: i64,
+        synth_mint_count: i64, synth_redeem_count: i64, total_count: i64,
+        to_asset_volume: f64, to_rune_volume: f64, to_trade_volume: f64, from_trade_volume: f64,
+        synth_mint_volume: f64, synth_redeem_volume: f64, total_volume: f64,
+        to_asset_volume_usd: f64, to_rune_volume_usd: f64, to_trade_volume_usd: f64, from_trade_volume_usd: f64,
+        synth_mint_volume_usd: f64, synth_redeem_volume_usd: f64, total_volume_usd: f64,
+        to_asset_fees: f64, to_rune_fees: f64, to_trade_fees: f64, from_trade_fees: f64,
+        synth_mint_fees: f64, synth_redeem_fees: f64, total_fees: f64,
+        to_asset_average_slip: f64, to_rune_average_slip: f64, to_trade_average_slip: f64,
+        from_trade_average_slip: f64, synth_mint_average_slip: f64, synth_redeem_average_slip: f64,
+        average_slip: f64, rune_price_usd: f64,
+        filled: bool,
+    }
+}
+
+graphql_row! {
+    /// Earnings of a pool over one interval, the rows of GET /earnings
+    #[graphql(complex)]
+    PoolEarnings {
+        pool: String, asset_liquidity_fees: f64, rune_liquidity_fees: f64, total_liquidity_fees_rune: f64,
+        saver_earning: f64, rewards: f64, start_time: i64, end_time: i64,
+    }
+    hidden { earnings_summary_id: ObjectId }
+}
+
+graphql_row! {
+    /// Network earnings over one interval, the rows of GET /earnings/summary
+    EarningsSummary {
+        start_time: i64, end_time: i64, block_rewards: f64, avg_node_count: f64, bonding_earnings: f64,
+        liquidity_earnings: f64, liquidity_fees: f64, rune_price_usd: f64,
+    }
+}
+
+graphql_row! {
+    /// RUNEPool members and units over one interval, the rows of GET /runepool
+    RunePoolHistory {
+        start_time: i64, end_time: i64, count: f64, units: f64,
+    }
+}
+
+#[ComplexObject(rename_fields = "snake_case")]
+impl PoolEarnings {
+    /// Network earnings of the hour the row was taken from. Loaded in one batch for every row of
+    /// the response.
+    async fn summary(&self, ctx: &Context<'_>) -> Result<Option<EarningsSummary>> {
+        let Some(earnings_summary_id) = self.earnings_summary_id else {
+            return Ok(None);
+        };
+
+        let loader = ctx.data::<DataLoader<EarningsSummaryLoader>>()?;
+        Ok(loader.load_one(earnings_summary_id).await?)
+    }
+}
+
+/// Batches the earnings summary lookups of one request into a single query
+pub struct EarningsSummaryLoader(Data<Database>);
+
+impl Loader<ObjectId> for EarningsSummaryLoader {
+    type Value = EarningsSummary;
+    type Error = mongodb::error::Error;
+
+    async fn load(&self, keys: &[ObjectId]) -> Result<HashMap<ObjectId, Self::Value>, Self::Error> {
+        let summaries = self.0.get_earnings_summaries(keys).await?;
+
+        Ok(summaries
+            .iter()
+            .filter_map(|summary| Some((summary.get_object_id("_id").ok()?, EarningsSummary::from(summary))))
+            .collect())
+    }
+}
+
+/// A loader lives for one request, so summaries are never served from a stale cache
+pub fn summary_loader(db: Data<Database>) -> DataLoader<EarningsSummaryLoader> {
+    DataLoader::new(EarningsSummaryLoader(db), tokio::spawn)
+}
+
+pub fn build_schema(db: Data<Database>) -> MetricsSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .data(db)
+        .limit_depth(MAX_DEPTH)
+        .finish()
+}
+
+/// Filtering and bucketing arguments, the query parameters of the history endpoints
+#[derive(Debug, Default, InputObject)]
+#[graphql(rename_fields = "snake_case")]
+pub struct HistoryArgs {
+    /// Start time Unix timestamp
+    pub start_time: Option<i64>,
+    /// End time Unix timestamp
+    pub end_time: Option<i64>,
+    /// Comma separated pool identifiers
+    pub pool: Option<String>,
+    /// Only pools of this chain, e.g. ETH matches every ETH.* pool
+    pub chain: Option<String>,
+    /// Page number (minimum: 1)
+    pub page: Option<u32>,
+    /// Items per page (1-100)
+    pub limit: Option<u32>,
+    /// Comma separated fields to sort by, prefix a field with - to sort it descending
+    pub sort_by: Option<String>,
+    /// Sort order (asc or desc)
+    pub order: Option<String>,
+    /// Time interval for aggregation (hour, day, week, month, quarter, year)
+    pub interval: Option<String>,
+    /// Comma separated numeric conditions on the returned rows, e.g. total_fees>1000
+    pub filter: Option<String>,
+    /// Set to pool to return a separate row per pool within each interval
+    pub group_by: Option<String>,
+}
+
+// Validates the arguments the same way the history routes validate their query parameters
+fn history_query(args: HistoryArgs, fields: &[&str]) -> Result<HistoryQuery, String> {
+    if let (Some(start), Some(end)) = (args.start_time, args.end_time) {
+        if start >= end {
+            return Err("start_time must be less than end_time.".to_string());
+        }
+    }
+
+    let limit = args.limit.unwrap_or(10).clamp(1, 100);
+    let page = args.page.unwrap_or(1).max(1);
+
+    let sort_order = match args.order.as_deref() {
+        None | Some("asc") => 1,
+        Some("desc") => -1,
+        Some(_) => return Err(format!("order must be one of: {:?}", ["asc", "desc"])),
+    };
+
+    if let Some(ref interval) = args.interval {
+        if !VALID_INTERVALS.contains(&interval.as_str()) {
+            return Err(format!("interval must be one of: {:?}", VALID_INTERVALS));
+        }
+    }
+
+    let mut sort_doc = build_sort(args.sort_by.as_deref(), sort_order, fields)?;
+    let filter = build_filter(args.filter.as_deref(), fields)?;
+    let chain = parse_chain(args.chain.as_deref())?;
+    let group_by_pool = parse_group_by(args.group_by.as_deref())?;
+
+    // Keep the rows of one interval in a stable pool order
+    if group_by_pool && !sort_doc.contains_key("pool") {
+        sort_doc.insert("pool", 1);
+    }
+
+    Ok(HistoryQuery {
+        start_time: args.start_time,
+        end_time: args.end_time,
+        pools: parse_pools(args.pool.as_deref()),
+        chain,
+        group_by_pool,
+        page,
+        limit: Some(limit),
+        sort_doc,
+        interval: args.interval,
+        projection: None,
+        filter,
+        fill: Fill::None,
+        derived: false,
+    })
+}
+
+fn database<'a>(ctx: &Context<'a>) -> Result<&'a Data<Database>> {
+    ctx.data::<Data<Database>>()
+}
+
+pub struct QueryRoot;
+
+#[Object(rename_fields = "snake_case", rename_args = "snake_case")]
+impl QueryRoot {
+    /// Depth and price history. The computed fields are only added when selected or referenced by
+    /// sort_by or filter.
+    async fn depths(
+        &self,
+        ctx: &Context<'_>,
+        args: Option<HistoryArgs>,
+        #[graphql(desc = "Emit every bucket of the range (none, zero, null, previous)")] fill: Option<String>,
+    ) -> Result<Vec<PoolDepthPriceHistory>> {
+        let allowed_fields: Vec<&str> = depth_history_model::FIELDS
+            .iter()
+            .chain(depth_history_model::DERIVED_FIELDS)
+            .copied()
+            .collect();
+
+        let mut options = history_query(args.unwrap_or_default(), &allowed_fields)?;
+        options.fill = parse_fill(fill.as_deref())?;
+
+        let selection = ctx.look_ahead();
+        options.derived = depth_history_model::DERIVED_FIELDS.iter().any(|field| {
+            selection.field(field).exists() || options.sort_doc.contains_key(*field) || options.filter.contains_key(*field)
+        });
+
+        let rows = database(ctx)?.get_pool_depth_price_history(options).await?;
+        Ok(rows.iter().map(PoolDepthPriceHistory::from).collect())
+    }
+
+    /// Swap history
+    async fn swaps(
+        &self,
+        ctx: &Context<'_>,
+        args: Option<HistoryArgs>,
+        #[graphql(desc = "Emit every bucket of the range (none, zero, null, previous)")] fill: Option<String>,
+    ) -> Result<Vec<PoolSwapHistory>> {
+        let mut options = history_query(args.unwrap_or_default(), swap_history_model::FIELDS)?;
+        options.fill = parse_fill(fill.as_deref())?;
+
+        let rows = database(ctx)?.get_pool_swap_history(options).await?;
+        Ok(rows.iter().map(PoolSwapHistory::from).collect())
+    }
+
+    /// Pool earnings history, select summary for the network earnings of each row
+    async fn earnings(&self, ctx: &Context<'_>, args: Option<HistoryArgs>) -> Result<Vec<PoolEarnings>> {
+        let options = history_query(args.unwrap_or_default(), earnings_model::FIELDS)?;
+
+        // Read the cursor directly so the rows keep their earnings_summary_id for the loader
+        let mut cursor = database(ctx)?.pool_earnings_cursor(options).await?;
+        let mut rows = Vec::new();
+
+        while let Some(result) = cursor.next().await {
+            match result {
+                Ok(row) => rows.push(PoolEarnings::from(&row)),
+                Err(e) => eprintln!("Error parsing document: {:?}", e),
+            }
+        }
+
+        Ok(rows)
+    }
+
+    /// Network earnings, the latest intervals when start_time is omitted
+    async fn earnings_summary(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(desc = "Start time Unix timestamp")] start_time: Option<i64>,
+        #[graphql(desc = "End time Unix timestamp")] end_time: Option<i64>,
+        #[graphql(desc = "Time interval for aggregation (hour, day, week, month, quarter, year), defaults to hour")]
+        interval: Option<String>,
+        #[graphql(desc = "Maximum number of intervals (1-1000), defaults to 100")] limit: Option<i64>,
+    ) -> Result<Vec<EarningsSummary>> {
+        if let (Some(start), Some(end)) = (start_time, end_time) {
+            if start >= end {
+                return Err("start_time must be less than end_time.".into());
+            }
+        }
+
+        let interval = interval.as_deref().unwrap_or("hour");
+        if !VALID_INTERVALS.contains(&interval) {
+            return Err(format!("interval must be one of: {:?}", VALID_INTERVALS).into());
+        }
+
+        let limit = limit.unwrap_or(100);
+        if !(1..=1000).contains(&limit) {
+            return Err("limit must be between 1 and 1000.".into());
+        }
+
+        let rows = database(ctx)?
+            .get_earnings_summary_intervals(start_time, end_time, Some(interval_duration(interval)), limit)
+            .await?;
+        Ok(rows.iter().map(EarningsSummary::from).collect())
+    }
+
+    /// RUNEPool history, which has no pools, so pool, chain and group_by are not supported
+    async fn runepool(&self, ctx: &Context<'_>, args: Option<HistoryArgs>) -> Result<Vec<RunePoolHistory>> {
+        let args = args.unwrap_or_default();
+        if args.pool.is_some() || args.chain.is_some() || args.group_by.is_some() {
+            return Err("runepool has no pool, so pool, chain and group_by are not supported.".into());
+        }
+
+        let options = history_query(args, rpmuh_model::FIELDS)?;
+
+        let rows = database(ctx)?.get_rune_pool_history(options).await?;
+        Ok(rows.iter().map(RunePoolHistory::from).collect())
+    }
+}
@@ -0,0 +1,84 @@
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::broadcast;
+use utoipa::ToSchema;
+
+pub const DATASETS: &[&str] = &["swaps", "depths", "earnings", "earnings_summary", "runepool"];
+
+/// Events a subscriber may fall behind by before it skips ahead
+const CAPACITY: usize = 1024;
+
+/// A newly stored interval
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LiveEvent {
+    /// swaps, depths, earnings, earnings_summary or runepool
+    #[schema(example = "depths")]
+    pub dataset: String,
+
+    /// Pool of the interval, null for earnings_summary and runepool
+    #[schema(example = "BTC.BTC")]
+    pub pool: Option<String>,
+
+    #[schema(example = 1729296000)]
+    pub start_time: i64,
+
+    #[schema(example = 1729299600)]
+    pub end_time: i64,
+
+    /// The stored row, with the same fields the dataset's history endpoint returns
+    #[schema(value_type = Object)]
+    pub data: Value,
+}
+
+pub fn channel() -> broadcast::Sender<LiveEvent> {
+    broadcast::channel(CAPACITY).0
+}
+
+/// Subscription filter. Empty lists match everything, events without a pool only pass when no pool
+/// is requested.
+pub struct LiveFilter {
+    pub datasets: Vec<String>,
+    pub pools: Vec<String>,
+}
+
+impl LiveFilter {
+    pub fn matches(&self, event: &LiveEvent) -> bool {
+        let dataset_matches = self.datasets.is_empty() || self.datasets.contains(&event.dataset);
+        let pool_matches = self.pools.is_empty()
+            || event.pool.as_ref().is_some_and(|pool| self.pools.contains(pool));
+
+        dataset_matches && pool_matches
+    }
+}
+
+/// Parses a comma separated dataset list
+pub fn parse_datasets(datasets: Option<&str>) -> Result<Vec<String>, String> {
+    let datasets: Vec<String> = datasets
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|dataset| !dataset.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    match datasets.iter().find(|dataset| !DATASETS.contains(&dataset.as_str())) {
+        Some(_) => Err(format!("dataset must be one of: {:?}", DATASETS)),
+        None => Ok(datasets),
+    }
+}
+
+/// Builds the event of a stored row, dropping the internal ids
+pub fn event<T: Serialize>(dataset: &str, pool: Option<&str>, record: &T) -> Option<LiveEvent> {
+    let mut data = serde_json::to_value(record).ok()?;
+    let fields = data.as_object_mut()?;
+    fields.remove("_id");
+    fields.remove("earnings_summary_id");
+
+    Some(LiveEvent {
+        dataset: dataset.to_string(),
+        pool: pool.map(str::to_string),
+        start_time: fields.get("start_time")?.as_i64()?,
+        end_time: fields.get("end_time")?.as_i64()?,
+        data,
+    })
+}
@@ -0,0 +1,271 @@
+use mongodb::bson::{to_document, Document};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::db::{number, Database};
+
+pub const MAX_POINTS: i64 = 1000;
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LpPoint {
+    /// Time of the depth snapshot (UNIX timestamp)
+    #[schema(example = 1666592610)]
+    pub time: i64,
+
+    /// Asset price in USD
+    #[schema(example = 29345.67)]
+    pub asset_price_usd: f64,
+
+    /// RUNE price in USD
+    #[schema(example = 1.52)]
+    pub rune_price_usd: f64,
+
+    /// Redeemable value of the LP position in USD
+    #[schema(example = 10234.56)]
+    pub lp_value_usd: f64,
+
+    /// Value of keeping the deposited asset and RUNE in USD
+    #[schema(example = 10123.45)]
+    pub hold_both_usd: f64,
+
+    /// Value of depositing everything as the asset and holding it in USD
+    #[schema(example = 11012.34)]
+    pub hold_asset_usd: f64,
+
+    /// Value of depositing everything as RUNE and holding it in USD
+    #[schema(example = 9234.56)]
+    pub hold_rune_usd: f64,
+
+    /// Loss from the price divergence alone, 2*sqrt(r)/(1+r) - 1 for the asset to RUNE price ratio r
+    #[schema(example = -0.0123)]
+    pub impermanent_loss: f64,
+
+    /// Growth of the liquidity unit value since the deposit, i.e. fees and rewards accrued per unit
+    #[schema(example = 0.0234)]
+    pub fee_growth: f64,
+
+    /// Return of the LP position against holding both assets
+    #[schema(example = 0.011)]
+    pub lp_vs_hold: f64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LpPerformance {
+    /// Pool identifier
+    #[schema(example = "BTC.BTC")]
+    pub pool: String,
+
+    /// Deposited value in USD, split evenly between the asset and RUNE
+    #[schema(example = 10000.0)]
+    pub amount_usd: f64,
+
+    /// Asset deposited, in whole units
+    #[schema(example = 0.17)]
+    pub deposit_asset: f64,
+
+    /// RUNE deposited, in whole units
+    #[schema(example = 3289.47)]
+    pub deposit_rune: f64,
+
+    /// Pool units received for the deposit
+    #[schema(example = 123456789.0)]
+    pub units: f64,
+
+    pub deposit: LpPoint,
+
+    pub current: LpPoint,
+
+    /// One point per interval from the deposit onwards
+    pub series: Vec<LpPoint>,
+}
+
+// The deposit as seen from its snapshot, everything else is valued against it
+struct Position {
+    asset: f64,
+    rune: f64,
+    units: f64,
+    asset_price_usd: f64,
+    rune_price_usd: f64,
+    asset_price: f64,
+    luvi: f64,
+    amount_usd: f64,
+}
+
+fn rune_price_usd(snapshot: &Document) -> f64 {
+    let asset_price = number(snapshot, "asset_price");
+    if asset_price > 0.0 {
+        number(snapshot, "asset_price_usd") / asset_price
+    } else {
+        0.0
+    }
+}
+
+fn value_at(position: &Position, time: i64, snapshot: &Document) -> Option<LpPoint> {
+    let units = number(snapshot, "units");
+    let asset_price = number(snapshot, "asset_price");
+    if units <= 0.0 || asset_price <= 0.0 {
+        return None;
+    }
+
+    let asset_price_usd = number(snapshot, "asset_price_usd");
+    let rune_price_usd = rune_price_usd(snapshot);
+
+    // Depths are in 1e8 base units, the position is kept in whole units
+    let share = position.units / units;
+    let lp_asset = share * number(snapshot, "asset_depth") / 1e8;
+    let lp_rune = share * number(snapshot, "rune_depth") / 1e8;
+
+    let lp_value_usd = lp_asset * asset_price_usd + lp_rune * rune_price_usd;
+    let hold_both_usd = position.asset * asset_price_usd + position.rune * rune_price_usd;
+
+    let ratio = asset_price / position.asset_price;
+    let luvi = number(snapshot, "luvi");
+
+    Some(LpPoint {
+        time,
+        asset_price_usd,
+        rune_price_usd,
+        lp_value_usd,
+        hold_both_usd,
+        hold_asset_usd: position.amount_usd / position.asset_price_usd * asset_price_usd,
+        hold_rune_usd: position.amount_usd / position.rune_price_usd * rune_price_usd,
+        impermanent_loss: 2.0 * ratio.sqrt() / (1.0 + ratio) - 1.0,
+        fee_growth: if position.luvi > 0.0 { luvi / position.luvi - 1.0 } else { 0.0 },
+        lp_vs_hold: if hold_both_usd > 0.0 { lp_value_usd / hold_both_usd - 1.0 } else { 0.0 },
+    })
+}
+
+/// Values a symmetric deposit of `amount_usd` made at `deposit_time` against holding, up to `to` or the latest snapshot.
+/// Returns `None` when the pool has no usable depth snapshot at the deposit time.
+pub async fn get_lp_performance(
+    db: &Database,
+    pool: String,
+    deposit_time: i64,
+    amount_usd: f64,
+    to: Option<i64>,
+    interval_duration: i64,
+) -> Result<Option<LpPerformance>, mongodb::error::Error> {
+    let Some(deposit) = db.depth_snapshot_at(&pool, Some(deposit_time)).await? else {
+        return Ok(None);
+    };
+
+    let deposit_doc = to_document(&deposit)?;
+    let deposit_rune_price = rune_price_usd(&deposit_doc);
+    if deposit.asset_price_usd <= 0.0 || deposit_rune_price <= 0.0 || deposit.rune_depth <= 0.0 {
+        return Ok(None);
+    }
+
+    let rune = amount_usd / 2.0 / deposit_rune_price;
+    let position = Position {
+        asset: amount_usd / 2.0 / deposit.asset_price_usd,
+        rune,
+        // A symmetric add mints units in proportion to the RUNE side
+        units: deposit.units * rune * 1e8 / deposit.rune_depth,
+        asset_price_usd: deposit.asset_price_usd,
+        rune_price_usd: deposit_rune_price,
+        asset_price: deposit.asset_price,
+        luvi: deposit.luvi,
+        amount_usd,
+    };
+
+    let Some(deposit_point) = value_at(&position, deposit_time, &deposit_doc) else {
+        return Ok(None);
+    };
+
+    let series: Vec<LpPoint> = db
+        .get_depth_intervals(pool.clone(), Some(deposit_time), to, Some(interval_duration), MAX_POINTS)
+        .await?
+        .iter()
+        .filter_map(|snapshot| value_at(&position, number(snapshot, "start_time") as i64, snapshot))
+        .collect();
+
+    let current = match db.depth_snapshot_at(&pool, to).await? {
+        Some(latest) => value_at(&position, latest.end_time, &to_document(&latest)?),
+        None => None,
+    };
+
+    Ok(Some(LpPerformance {
+        pool,
+        amount_usd,
+        deposit_asset: position.asset,
+        deposit_rune: position.rune,
+        units: position.units,
+        current: current.unwrap_or_else(|| deposit_point.clone()),
+        deposit: deposit_point,
+        series,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mongodb::bson::doc;
+
+    // 1000 USD deposited into a pool of 100 BTC and 1000 RUNE, 1 BTC is 10 RUNE and 50 USD.
+    // The deposit holds 10% of the 1000 pool units.
+    fn position() -> Position {
+        Position {
+            asset: 10.0,
+            rune: 100.0,
+            units: 100.0,
+            asset_price_usd: 50.0,
+            rune_price_usd: 5.0,
+            asset_price: 10.0,
+            luvi: 1.0,
+            amount_usd: 1000.0,
+        }
+    }
+
+    fn snapshot(asset_depth: f64, rune_depth: f64, asset_price_usd: f64, luvi: f64) -> Document {
+        doc! {
+            "asset_depth": asset_depth * 1e8,
+            "rune_depth": rune_depth * 1e8,
+            "asset_price": rune_depth / asset_depth,
+            "asset_price_usd": asset_price_usd,
+            "units": 1000.0,
+            "luvi": luvi,
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} is not {}", actual, expected);
+    }
+
+    #[test]
+    fn value_at_the_deposit_matches_holding() {
+        let point = value_at(&position(), 0, &snapshot(100.0, 1000.0, 50.0, 1.0)).unwrap();
+
+        assert_close(point.rune_price_usd, 5.0);
+        assert_close(point.lp_value_usd, 1000.0);
+        assert_close(point.hold_both_usd, 1000.0);
+        assert_close(point.impermanent_loss, 0.0);
+        assert_close(point.fee_growth, 0.0);
+        assert_close(point.lp_vs_hold, 0.0);
+    }
+
+    #[test]
+    fn value_at_a_fourfold_price_loses_a_fifth_against_holding() {
+        // The asset price in RUNE quadruples along the constant product, RUNE stays at 5 USD
+        let point = value_at(&position(), 0, &snapshot(50.0, 2000.0, 200.0, 1.0)).unwrap();
+
+        assert_close(point.lp_value_usd, 5.0 * 200.0 + 200.0 * 5.0);
+        assert_close(point.hold_both_usd, 10.0 * 200.0 + 100.0 * 5.0);
+        assert_close(point.hold_asset_usd, 4000.0);
+        assert_close(point.hold_rune_usd, 1000.0);
+        assert_close(point.impermanent_loss, 2.0 * 2.0 / 5.0 - 1.0);
+        assert_close(point.lp_vs_hold, point.impermanent_loss);
+    }
+
+    #[test]
+    fn value_at_reports_fee_growth_from_luvi() {
+        let point = value_at(&position(), 0, &snapshot(100.0, 1000.0, 50.0, 1.1)).unwrap();
+        assert_close(point.fee_growth, 0.1);
+    }
+
+    #[test]
+    fn value_at_skips_an_empty_pool() {
+        let mut empty = snapshot(100.0, 1000.0, 50.0, 1.0);
+        empty.insert("units", 0.0);
+        assert!(value_at(&position(), 0, &empty).is_none());
+    }
+}
@@ -0,0 +1,121 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::de::DeserializeOwned;
+
+use super::{
+    db::Database,
+    depth_history_fetcher::{self, ApiResponse},
+    earnings_fetcher,
+    rpmuh_fetcher::{self, RunePoolHistoryApiResponse},
+    swaps_history_fetcher::{self, SwapsApiResponse},
+};
+
+pub type ImportResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+pub const KINDS: &[&str] = &["swaps", "depths", "earnings", "runepool"];
+
+// Directories are expanded into their .json files, in name order so chunks are imported chronologically
+fn collect_files(paths: &[String]) -> ImportResult<Vec<PathBuf>> {
+    let mut files = Vec::new();
+
+    for path in paths {
+        let path = Path::new(path);
+
+        if path.is_dir() {
+            let mut entries: Vec<PathBuf> = fs::read_dir(path)?
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|entry| entry.extension().is_some_and(|extension| extension == "json"))
+                .collect();
+            entries.sort();
+            files.extend(entries);
+        } else {
+            files.push(path.to_path_buf());
+        }
+    }
+
+    Ok(files)
+}
+
+fn read_response<T: DeserializeOwned>(file: &Path) -> ImportResult<T> {
+    let raw_body = fs::read_to_string(file)?;
+    Ok(serde_json::from_str(&raw_body)?)
+}
+
+/// Stores one saved Midgard response through the same path as the fetchers, returning the number of intervals
+/// stored and the number in the file. A response with a malformed interval is rejected as a whole.
+pub async fn import_file(db: &Database, kind: &str, pool: Option<&str>, file: &Path) -> ImportResult<(usize, usize)> {
+    match (kind, pool) {
+        ("swaps", Some(pool)) => {
+            let response: SwapsApiResponse = read_response(file)?;
+            let count = response.intervals.len();
+            let rows = swaps_history_fetcher::parse_swaps_intervals(response.intervals, pool)?;
+            let stored = swaps_history_fetcher::store_swaps_intervals_in_db(rows, pool, db, false).await;
+            Ok((stored, count))
+        }
+        ("depths", Some(pool)) => {
+            let response: ApiResponse = read_response(file)?;
+            let count = response.intervals.len();
+            let rows = depth_history_fetcher::parse_intervals(response.intervals, pool)?;
+            let stored = depth_history_fetcher::store_intervals_in_db(rows, pool, db, false).await;
+            Ok((stored, count))
+        }
+        ("swaps" | "depths", None) => Err(format!("{} responses do not contain the pool, pass it with --pool", kind).into()),
+        ("earnings", _) => {
+            let response: earnings_fetcher::ApiResponse = read_response(file)?;
+            let count = response.intervals.len();
+            let rows = earnings_fetcher::parse_earnings(response.intervals)?;
+            let stored = earnings_fetcher::store_earnings_in_db(rows, db, false).await;
+            Ok((stored, count))
+        }
+        ("runepool", _) => {
+            let response: RunePoolHistoryApiResponse = read_response(file)?;
+            let count = response.intervals.len();
+            let rows = rpmuh_fetcher::parse_rune_pool_intervals(response.intervals)?;
+            let stored = rpmuh_fetcher::store_rune_pool_intervals_in_db(rows, db, false).await;
+            Ok((stored, count))
+        }
+        _ => Err(format!("kind must be one of: {:?}", KINDS).into()),
+    }
+}
+
+/// Offline import, run with `rust-crypto-metrics import-midgard <kind> [--pool <pool>] <file or dir>...`
+pub async fn run_import(db: &Database, args: &[String]) {
+    let Some(kind) = args.first() else {
+        eprintln!("Usage: import-midgard <{}> [--pool <pool>] <file or dir>...", KINDS.join("|"));
+        return;
+    };
+
+    let mut pool = None;
+    let mut paths = Vec::new();
+    let mut rest = args[1..].iter();
+    while let Some(arg) = rest.next() {
+        if arg == "--pool" {
+            pool = rest.next().map(String::as_str);
+        } else {
+            paths.push(arg.clone());
+        }
+    }
+
+    let files = match collect_files(&paths) {
+        Ok(files) => files,
+        Err(e) => {
+            eprintln!("Error reading import paths: {:?}", e);
+            return;
+        }
+    };
+
+    for file in files {
+        match import_file(db, kind, pool, &file).await {
+            Ok((stored, count)) if stored == count => {
+                println!("Imported {} {} intervals from {}", count, kind, file.display())
+            }
+            Ok((stored, count)) => {
+                eprintln!("Imported only {} of {} {} intervals from {}, see the errors above", stored, count, kind, file.display())
+            }
+            Err(e) => eprintln!("Error importing {}: {}", file.display(), e),
+        }
+    }
+}
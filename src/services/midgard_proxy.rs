@@ -0,0 +1,339 @@
+use std::{collections::BTreeMap, fmt::Display, str::FromStr};
+
+use mongodb::bson::Document;
+
+use super::db::{number, Database, SWAP_SLIP_FIELDS, SWAP_SUM_FIELDS};
+use super::depth_history_fetcher::{self, ApiResponse as DepthsApiResponse};
+use super::earnings_fetcher::{self, ApiResponse as EarningsApiResponse};
+use super::rpmuh_fetcher::{RunePoolHistoryApiResponse, RunePoolInterval, RunePoolMeta};
+use super::swaps_history_fetcher::{SwapsApiResponse, SwapsInterval, SwapsMeta};
+
+pub const MAX_COUNT: i64 = 400;
+
+// Midgard sends every number as a string, integers without a fractional part
+pub fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e18 {
+        (value as i64).to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+// Reads a number Midgard sent as a string, naming the field when it is malformed
+pub fn parse_number<T: FromStr>(value: &str, name: &str) -> Result<T, String>
+where
+    T::Err: Display,
+{
+    value.parse::<T>().map_err(|e| format!("Failed to parse {} '{}': {}", name, value, e))
+}
+
+fn field(doc: &Document, key: &str) -> String {
+    format_number(number(doc, key))
+}
+
+fn average_slip(doc: &Document, slip_field: &str, count_field: &str) -> String {
+    let count = number(doc, count_field);
+    if count == 0.0 {
+        return "0".to_string();
+    }
+    format_number(number(doc, &format!("{}_weighted", slip_field)) / count)
+}
+
+fn swaps_interval(doc: &Document) -> SwapsInterval {
+    SwapsInterval {
+        startTime: field(doc, "start_time"),
+        endTime: field(doc, "end_time"),
+        toAssetCount: field(doc, "to_asset_count"),
+        toRuneCount: field(doc, "to_rune_count"),
+        toTradeCount: field(doc, "to_trade_count"),
+        fromTradeCount: field(doc, "from_trade_count"),
+        synthMintCount: field(doc, "synth_mint_count"),
+        synthRedeemCount: field(doc, "synth_redeem_count"),
+        totalCount: field(doc, "total_count"),
+        toAssetVolume: field(doc, "to_asset_volume"),
+        toRuneVolume: field(doc, "to_rune_volume"),
+        toTradeVolume: field(doc, "to_trade_volume"),
+        fromTradeVolume: field(doc, "from_trade_volume"),
+        synthMintVolume: field(doc, "synth_mint_volume"),
+        synthRedeemVolume: field(doc, "synth_redeem_volume"),
+        totalVolume: field(doc, "total_volume"),
+        toAssetVolumeUSD: field(doc, "to_asset_volume_usd"),
+        toRuneVolumeUSD: field(doc, "to_rune_volume_usd"),
+        toTradeVolumeUSD: field(doc, "to_trade_volume_usd"),
+        fromTradeVolumeUSD: field(doc, "from_trade_volume_usd"),
+        synthMintVolumeUSD: field(doc, "synth_mint_volume_usd"),
+        synthRedeemVolumeUSD: field(doc, "synth_redeem_volume_usd"),
+        totalVolumeUSD: field(doc, "total_volume_usd"),
+        toAssetFees: field(doc, "to_asset_fees"),
+        toRuneFees: field(doc, "to_rune_fees"),
+        toTradeFees: field(doc, "to_trade_fees"),
+        fromTradeFees: field(doc, "from_trade_fees"),
+        synthMintFees: field(doc, "synth_mint_fees"),
+        synthRedeemFees: field(doc, "synth_redeem_fees"),
+        totalFees: field(doc, "total_fees"),
+        toAssetAverageSlip: average_slip(doc, "to_asset_average_slip", "to_asset_count"),
+        toRuneAverageSlip: average_slip(doc, "to_rune_average_slip", "to_rune_count"),
+        toTradeAverageSlip: average_slip(doc, "to_trade_average_slip", "to_trade_count"),
+        fromTradeAverageSlip: average_slip(doc, "from_trade_average_slip", "from_trade_count"),
+        synthMintAverageSlip: average_slip(doc, "synth_mint_average_slip", "synth_mint_count"),
+        synthRedeemAverageSlip: average_slip(doc, "synth_redeem_average_slip", "synth_redeem_count"),
+        averageSlip: average_slip(doc, "average_slip", "total_count"),
+        runePriceUSD: field(doc, "rune_price_usd"),
+    }
+}
+
+pub async fn get_swaps_history(
+    db: &Database,
+    pool: Option<String>,
+    from: Option<i64>,
+    to: Option<i64>,
+    interval_duration: Option<i64>,
+    count: i64,
+) -> Result<SwapsApiResponse, mongodb::error::Error> {
+    let docs = db.get_swap_intervals(pool, from, to, interval_duration, count).await?;
+
+    let mut totals = Document::new();
+    totals.insert("start_time", docs.first().map(|d| number(d, "start_time")).or(from.map(|v| v as f64)).unwrap_or(0.0));
+    totals.insert("end_time", docs.last().map(|d| number(d, "end_time")).or(to.map(|v| v as f64)).unwrap_or(0.0));
+    totals.insert("rune_price_usd", docs.last().map(|d| number(d, "rune_price_usd")).unwrap_or(0.0));
+
+    for key in SWAP_SUM_FIELDS.iter().map(|f| f.to_string())
+        .chain(SWAP_SLIP_FIELDS.iter().map(|(slip, _)| format!("{}_weighted", slip)))
+    {
+        let sum: f64 = docs.iter().map(|d| number(d, &key)).sum();
+        totals.insert(key, sum);
+    }
+
+    let meta = swaps_interval(&totals);
+
+    Ok(SwapsApiResponse {
+        meta: SwapsMeta {
+            startTime: meta.startTime,
+            endTime: meta.endTime,
+            toAssetCount: meta.toAssetCount,
+            toRuneCount: meta.toRuneCount,
+            toTradeCount: meta.toTradeCount,
+            fromTradeCount: meta.fromTradeCount,
+            synthMintCount: meta.synthMintCount,
+            synthRedeemCount: meta.synthRedeemCount,
+            totalCount: meta.totalCount,
+            toAssetVolume: meta.toAssetVolume,
+            toRuneVolume: meta.toRuneVolume,
+            toTradeVolume: meta.toTradeVolume,
+            fromTradeVolume: meta.fromTradeVolume,
+            synthMintVolume: meta.synthMintVolume,
+            synthRedeemVolume: meta.synthRedeemVolume,
+            totalVolume: meta.totalVolume,
+            toAssetVolumeUSD: meta.toAssetVolumeUSD,
+            toRuneVolumeUSD: meta.toRuneVolumeUSD,
+            toTradeVolumeUSD: meta.toTradeVolumeUSD,
+            fromTradeVolumeUSD: meta.fromTradeVolumeUSD,
+            synthMintVolumeUSD: meta.synthMintVolumeUSD,
+            synthRedeemVolumeUSD: meta.synthRedeemVolumeUSD,
+            totalVolumeUSD: meta.totalVolumeUSD,
+            toAssetFees: meta.toAssetFees,
+            toRuneFees: meta.toRuneFees,
+            toTradeFees: meta.toTradeFees,
+            fromTradeFees: meta.fromTradeFees,
+            synthMintFees: meta.synthMintFees,
+            synthRedeemFees: meta.synthRedeemFees,
+            totalFees: meta.totalFees,
+            toAssetAverageSlip: meta.toAssetAverageSlip,
+            toRuneAverageSlip: meta.toRuneAverageSlip,
+            toTradeAverageSlip: meta.toTradeAverageSlip,
+            fromTradeAverageSlip: meta.fromTradeAverageSlip,
+            synthMintAverageSlip: meta.synthMintAverageSlip,
+            synthRedeemAverageSlip: meta.synthRedeemAverageSlip,
+            averageSlip: meta.averageSlip,
+            runePriceUSD: meta.runePriceUSD,
+        },
+        intervals: docs.iter().map(swaps_interval).collect(),
+    })
+}
+
+pub async fn get_depths_history(
+    db: &Database,
+    pool: String,
+    from: Option<i64>,
+    to: Option<i64>,
+    interval_duration: Option<i64>,
+    count: i64,
+) -> Result<DepthsApiResponse, mongodb::error::Error> {
+    let docs = db.get_depth_intervals(pool, from, to, interval_duration, count).await?;
+
+    let empty = Document::new();
+    let first = docs.first().unwrap_or(&empty);
+    let last = docs.last().unwrap_or(&empty);
+
+    // Loss of holding LP units instead of the assets, purely from the price moving
+    let price_ratio = if number(first, "first_asset_price") > 0.0 {
+        number(last, "asset_price") / number(first, "first_asset_price")
+    } else {
+        1.0
+    };
+    let price_shift_loss = 2.0 * price_ratio.sqrt() / (1.0 + price_ratio);
+
+    let luvi_increase = if number(first, "first_luvi") > 0.0 {
+        number(last, "luvi") / number(first, "first_luvi")
+    } else {
+        1.0
+    };
+
+    let meta = depth_history_fetcher::Meta {
+        startTime: docs.first().map(|d| field(d, "start_time")).or(from.map(|v| v.to_string())).unwrap_or_else(|| "0".to_string()),
+        endTime: docs.last().map(|d| field(d, "end_time")).or(to.map(|v| v.to_string())).unwrap_or_else(|| "0".to_string()),
+        priceShiftLoss: format_number(price_shift_loss),
+        luviIncrease: format_number(luvi_increase),
+        startAssetDepth: field(first, "first_asset_depth"),
+        startRuneDepth: field(first, "first_rune_depth"),
+        startLPUnits: field(first, "first_liquidity_units"),
+        startMemberCount: field(first, "first_members_count"),
+        startSynthUnits: field(first, "first_synth_units"),
+        endAssetDepth: field(last, "asset_depth"),
+        endRuneDepth: field(last, "rune_depth"),
+        endLPUnits: field(last, "liquidity_units"),
+        endMemberCount: field(last, "members_count"),
+        endSynthUnits: field(last, "synth_units"),
+    };
+
+    let intervals = docs
+        .iter()
+        .map(|doc| depth_history_fetcher::Interval {
+            startTime: field(doc, "start_time"),
+            endTime: field(doc, "end_time"),
+            assetDepth: field(doc, "asset_depth"),
+            runeDepth: field(doc, "rune_depth"),
+            assetPrice: field(doc, "asset_price"),
+            assetPriceUSD: field(doc, "asset_price_usd"),
+            liquidityUnits: field(doc, "liquidity_units"),
+            membersCount: field(doc, "members_count"),
+            synthUnits: field(doc, "synth_units"),
+            synthSupply: field(doc, "synth_supply"),
+            units: field(doc, "units"),
+            luvi: field(doc, "luvi"),
+        })
+        .collect();
+
+    Ok(DepthsApiResponse { meta, intervals })
+}
+
+fn earnings_pool(pool: &str, doc: &Document) -> earnings_fetcher::Pool {
+    earnings_fetcher::Pool {
+        pool: pool.to_string(),
+        assetLiquidityFees: field(doc, "asset_liquidity_fees"),
+        runeLiquidityFees: field(doc, "rune_liquidity_fees"),
+        totalLiquidityFeesRune: field(doc, "total_liquidity_fees_rune"),
+        saverEarning: field(doc, "saver_earning"),
+        rewards: field(doc, "rewards"),
+        earnings: format_number(number(doc, "total_liquidity_fees_rune") + number(doc, "rewards")),
+    }
+}
+
+fn add_pool_totals(totals: &mut BTreeMap<String, Document>, pool: &str, doc: &Document) {
+    let entry = totals.entry(pool.to_string()).or_default();
+    for key in ["asset_liquidity_fees", "rune_liquidity_fees", "total_liquidity_fees_rune", "saver_earning", "rewards"] {
+        let sum = number(entry, key) + number(doc, key);
+        entry.insert(key, sum);
+    }
+}
+
+pub async fn get_earnings_history(
+    db: &Database,
+    from: Option<i64>,
+    to: Option<i64>,
+    interval_duration: Option<i64>,
+    count: i64,
+) -> Result<EarningsApiResponse, mongodb::error::Error> {
+    let summaries = db.get_earnings_summary_intervals(from, to, interval_duration, count).await?;
+
+    // Only load pool rows for the buckets that survived the count limit
+    let range_from = summaries.first().map(|d| number(d, "start_time") as i64).or(from);
+    let range_to = summaries.last().map(|d| number(d, "end_time") as i64).or(to);
+    let pool_rows = db.get_pool_earnings_intervals(range_from, range_to, interval_duration).await?;
+
+    let mut pools_by_bucket: BTreeMap<i64, Vec<&Document>> = BTreeMap::new();
+    for row in &pool_rows {
+        let bucket = if interval_duration.is_some() { number(row, "start_time") as i64 } else { 0 };
+        pools_by_bucket.entry(bucket).or_default().push(row);
+    }
+
+    let mut meta_pools: BTreeMap<String, Document> = BTreeMap::new();
+    let mut intervals = Vec::new();
+
+    for summary in &summaries {
+        let bucket = if interval_duration.is_some() { number(summary, "start_time") as i64 } else { 0 };
+        let mut pools = Vec::new();
+
+        for row in pools_by_bucket.get(&bucket).map(|rows| rows.as_slice()).unwrap_or(&[]) {
+            let pool = row.get_str("pool").unwrap_or_default();
+            add_pool_totals(&mut meta_pools, pool, row);
+            pools.push(earnings_pool(pool, row));
+        }
+
+        intervals.push(earnings_fetcher::Interval {
+            startTime: field(summary, "start_time"),
+            endTime: field(summary, "end_time"),
+            avgNodeCount: field(summary, "avg_node_count"),
+            blockRewards: field(summary, "block_rewards"),
+            bondingEarnings: field(summary, "bonding_earnings"),
+            earnings: format_number(number(summary, "liquidity_fees") + number(summary, "block_rewards")),
+            liquidityEarnings: field(summary, "liquidity_earnings"),
+            liquidityFees: field(summary, "liquidity_fees"),
+            runePriceUSD: field(summary, "rune_price_usd"),
+            pools,
+        });
+    }
+
+    let sum = |key: &str| summaries.iter().map(|d| number(d, key)).sum::<f64>();
+    let avg_node_count = if summaries.is_empty() { 0.0 } else { sum("avg_node_count") / summaries.len() as f64 };
+
+    let meta = earnings_fetcher::Meta {
+        avgNodeCount: format_number(avg_node_count),
+        blockRewards: format_number(sum("block_rewards")),
+        bondingEarnings: format_number(sum("bonding_earnings")),
+        earnings: format_number(sum("liquidity_fees") + sum("block_rewards")),
+        endTime: range_to.map(|v| v.to_string()).unwrap_or_else(|| "0".to_string()),
+        liquidityEarnings: format_number(sum("liquidity_earnings")),
+        liquidityFees: format_number(sum("liquidity_fees")),
+        pools: meta_pools.iter().map(|(pool, doc)| earnings_pool(pool, doc)).collect(),
+        runePriceUSD: summaries.last().map(|d| field(d, "rune_price_usd")).unwrap_or_else(|| "0".to_string()),
+        startTime: range_from.map(|v| v.to_string()).unwrap_or_else(|| "0".to_string()),
+    };
+
+    Ok(EarningsApiResponse { intervals, meta })
+}
+
+pub async fn get_rune_pool_history(
+    db: &Database,
+    from: Option<i64>,
+    to: Option<i64>,
+    interval_duration: Option<i64>,
+    count: i64,
+) -> Result<RunePoolHistoryApiResponse, mongodb::error::Error> {
+    let docs = db.get_rune_pool_intervals(from, to, interval_duration, count).await?;
+
+    let empty = Document::new();
+    let first = docs.first().unwrap_or(&empty);
+    let last = docs.last().unwrap_or(&empty);
+
+    let meta = RunePoolMeta {
+        startTime: docs.first().map(|d| field(d, "start_time")).or(from.map(|v| v.to_string())).unwrap_or_else(|| "0".to_string()),
+        endTime: docs.last().map(|d| field(d, "end_time")).or(to.map(|v| v.to_string())).unwrap_or_else(|| "0".to_string()),
+        startUnits: field(first, "first_units"),
+        startCount: field(first, "first_count"),
+        endUnits: field(last, "units"),
+        endCount: field(last, "count"),
+    };
+
+    let intervals = docs
+        .iter()
+        .map(|doc| RunePoolInterval {
+            startTime: field(doc, "start_time"),
+            endTime: field(doc, "end_time"),
+            count: field(doc, "count"),
+            units: field(doc, "units"),
+        })
+        .collect();
+
+    Ok(RunePoolHistoryApiResponse { meta, intervals })
+}
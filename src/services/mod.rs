@@ -3,4 +3,16 @@ pub mod depth_history_fetcher;
 pub mod earnings_fetcher;
 pub mod swaps_history_fetcher;
 pub mod rpmuh_fetcher;
-pub mod fetch_all_cron;
\ No newline at end of file
+pub mod fetch_all_cron;
+pub mod midgard_proxy;
+pub mod query_builder;
+pub mod parquet_export;
+pub mod midgard_import;
+pub mod analytics;
+pub mod lp_calculator;
+pub mod quote;
+pub mod compare;
+pub mod anomaly_detector;
+pub mod alerts;
+pub mod live_feed;
+pub mod graphql;
\ No newline at end of file
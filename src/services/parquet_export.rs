@@ -0,0 +1,300 @@
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use arrow_array::{ArrayRef, Float64Array, Int64Array, RecordBatch, StringArray};
+use arrow_schema::{ArrowError, DataType, Field, Schema};
+use chrono::DateTime;
+use futures_util::stream::StreamExt;
+use mongodb::{
+    bson::{doc, oid::ObjectId, Document},
+    Cursor,
+};
+use parquet::{arrow::ArrowWriter, basic::Compression, file::properties::WriterProperties};
+use serde::de::DeserializeOwned;
+
+use crate::models::{
+    depth_history_model::PoolDepthPriceHistory, earning_summary_model::EarningsSummary, earnings_model::PoolEarnings,
+    rpmuh_model::RunePoolHistory, swap_history_model::PoolSwapHistory,
+};
+use super::db::Database;
+
+pub type ExportResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+pub const DATASETS: &[&str] = &["swap_history", "depth_history", "earnings", "earnings_summary", "rpmuh"];
+
+const ROW_GROUP_SIZE: usize = 8192;
+
+/// Maps a model field type onto its Parquet column type
+pub trait ColumnValue: Clone {
+    fn data_type() -> DataType;
+    fn into_array(values: Vec<Self>) -> ArrayRef;
+}
+
+impl ColumnValue for f64 {
+    fn data_type() -> DataType {
+        DataType::Float64
+    }
+
+    fn into_array(values: Vec<Self>) -> ArrayRef {
+        Arc::new(Float64Array::from(values))
+    }
+}
+
+impl ColumnValue for i64 {
+    fn data_type() -> DataType {
+        DataType::Int64
+    }
+
+    fn into_array(values: Vec<Self>) -> ArrayRef {
+        Arc::new(Int64Array::from(values))
+    }
+}
+
+impl ColumnValue for String {
+    fn data_type() -> DataType {
+        DataType::Utf8
+    }
+
+    fn into_array(values: Vec<Self>) -> ArrayRef {
+        Arc::new(StringArray::from(values))
+    }
+}
+
+impl ColumnValue for ObjectId {
+    fn data_type() -> DataType {
+        DataType::Utf8
+    }
+
+    fn into_array(values: Vec<Self>) -> ArrayRef {
+        Arc::new(StringArray::from_iter_values(values.into_iter().map(ObjectId::to_hex)))
+    }
+}
+
+fn field_of<M, T: ColumnValue>(name: &str, _column: fn(&M) -> &T) -> Field {
+    Field::new(name, T::data_type(), false)
+}
+
+fn column_of<M, T: ColumnValue>(rows: &[M], column: fn(&M) -> &T) -> ArrayRef {
+    T::into_array(rows.iter().map(|row| column(row).clone()).collect())
+}
+
+/// A model that can be written as a Parquet row
+pub trait ParquetRow: Sized {
+    fn schema() -> Schema;
+    fn to_batch(rows: &[Self]) -> Result<RecordBatch, ArrowError>;
+    fn pool(&self) -> Option<&str>;
+    fn start_time(&self) -> i64;
+}
+
+// Column types come from the model struct fields, so the schema follows the models in `models/`
+macro_rules! parquet_row {
+    ($model:ty, [$($field:ident),* $(,)?]) => {
+        parquet_row!(@impl $model, None, [$($field),*]);
+    };
+    ($model:ty, pool, [$($field:ident),* $(,)?]) => {
+        parquet_row!(@impl $model, Some, [$($field),*]);
+    };
+    (@impl $model:ty, $pool:ident, [$($field:ident),*]) => {
+        impl ParquetRow for $model {
+            fn schema() -> Schema {
+                Schema::new(vec![$(field_of(stringify!($field), |row: &$model| &row.$field)),*])
+            }
+
+            fn to_batch(rows: &[Self]) -> Result<RecordBatch, ArrowError> {
+                RecordBatch::try_new(
+                    Arc::new(Self::schema()),
+                    vec![$(column_of(rows, |row: &$model| &row.$field)),*],
+                )
+            }
+
+            fn pool(&self) -> Option<&str> {
+                parquet_row!(@pool self, $pool)
+            }
+
+            fn start_time(&self) -> i64 {
+                self.start_time
+            }
+        }
+    };
+    (@pool $row:ident, Some) => { Some($row.pool.as_str()) };
+    (@pool $row:ident, None) => { None };
+}
+
+parquet_row!(PoolSwapHistory, pool, [
+    _id, pool, start_time, end_time,
+    to_asset_count, to_rune_count, to_trade_count, from_trade_count, synth_mint_count, synth_redeem_count, total_count,
+    to_asset_volume, to_rune_volume, to_trade_volume, from_trade_volume, synth_mint_volume, synth_redeem_volume, total_volume,
+    to_asset_volume_usd, to_rune_volume_usd, to_trade_volume_usd, from_trade_volume_usd,
+    synth_mint_volume_usd, synth_redeem_volume_usd, total_volume_usd,
+    to_asset_fees, to_rune_fees, to_trade_fees, from_trade_fees, synth_mint_fees, synth_redeem_fees, total_fees,
+    to_asset_average_slip, to_rune_average_slip, to_trade_average_slip, from_trade_average_slip,
+    synth_mint_average_slip, synth_redeem_average_slip, average_slip,
+    rune_price_usd,
+]);
+
+parquet_row!(PoolDepthPriceHistory, pool, [
+    _id, pool, asset_depth, asset_price, asset_price_usd, start_time, end_time, liquidity_units, luvi,
+    members_count, rune_depth, synth_supply, synth_units, units,
+]);
+
+parquet_row!(PoolEarnings, pool, [
+    _id, pool, asset_liquidity_fees, rune_liquidity_fees, total_liquidity_fees_rune, saver_earning, rewards,
+    start_time, end_time, earnings_summary_id,
+]);
+
+parquet_row!(EarningsSummary, [
+    _id, start_time, end_time, block_rewards, avg_node_count, bonding_earnings, liquidity_earnings,
+    liquidity_fees, rune_price_usd,
+]);
+
+parquet_row!(RunePoolHistory, [_id, start_time, end_time, count, units]);
+
+fn writer_properties() -> WriterProperties {
+    WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .set_max_row_group_size(ROW_GROUP_SIZE)
+        .build()
+}
+
+async fn write_rows<M, W>(mut cursor: Cursor<M>, sink: W) -> ExportResult<W>
+where
+    M: ParquetRow + DeserializeOwned + Send + Sync + Unpin,
+    W: Write + Send,
+{
+    let mut writer = ArrowWriter::try_new(sink, Arc::new(M::schema()), Some(writer_properties()))?;
+    let mut rows = Vec::with_capacity(ROW_GROUP_SIZE);
+
+    while let Some(row) = cursor.next().await {
+        rows.push(row?);
+
+        if rows.len() == ROW_GROUP_SIZE {
+            writer.write(&M::to_batch(&rows)?)?;
+            rows.clear();
+        }
+    }
+
+    if !rows.is_empty() {
+        writer.write(&M::to_batch(&rows)?)?;
+    }
+
+    Ok(writer.into_inner()?)
+}
+
+fn partition_path(dataset_dir: &Path, pool: Option<&str>, start_time: i64) -> PathBuf {
+    let month = DateTime::from_timestamp(start_time, 0)
+        .map(|time| time.format("%Y-%m").to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    let mut path = dataset_dir.to_path_buf();
+    if let Some(pool) = pool {
+        path.push(format!("pool={}", pool));
+    }
+    path.push(format!("month={}", month));
+    path
+}
+
+fn close_partition<M: ParquetRow>(writer: Option<ArrowWriter<File>>, rows: &mut Vec<M>) -> ExportResult<()> {
+    if let Some(mut writer) = writer {
+        if !rows.is_empty() {
+            writer.write(&M::to_batch(rows)?)?;
+        }
+        writer.close()?;
+    }
+    rows.clear();
+    Ok(())
+}
+
+// Rows arrive sorted by pool and start_time, so each pool/month partition is written in one pass
+async fn write_partitions<M>(mut cursor: Cursor<M>, dataset_dir: &Path) -> ExportResult<usize>
+where
+    M: ParquetRow + DeserializeOwned + Send + Sync + Unpin,
+{
+    let mut current_partition: Option<PathBuf> = None;
+    let mut writer: Option<ArrowWriter<File>> = None;
+    let mut rows: Vec<M> = Vec::with_capacity(ROW_GROUP_SIZE);
+    let mut files = 0;
+
+    while let Some(row) = cursor.next().await {
+        let row = row?;
+        let partition = partition_path(dataset_dir, row.pool(), row.start_time());
+
+        if current_partition.as_ref() != Some(&partition) {
+            close_partition(writer.take(), &mut rows)?;
+
+            fs::create_dir_all(&partition)?;
+            let file = File::create(partition.join("part-0.parquet"))?;
+            writer = Some(ArrowWriter::try_new(file, Arc::new(M::schema()), Some(writer_properties()))?);
+            current_partition = Some(partition);
+            files += 1;
+        }
+
+        rows.push(row);
+
+        if rows.len() == ROW_GROUP_SIZE {
+            if let Some(ref mut writer) = writer {
+                writer.write(&M::to_batch(&rows)?)?;
+            }
+            rows.clear();
+        }
+    }
+
+    close_partition(writer, &mut rows)?;
+
+    Ok(files)
+}
+
+pub fn has_pool(dataset: &str) -> bool {
+    matches!(dataset, "swap_history" | "depth_history" | "earnings")
+}
+
+/// Writes the matching rows of a dataset into a single Parquet file held in memory
+pub async fn export_to_bytes(db: &Database, dataset: &str, query: Document) -> ExportResult<Vec<u8>> {
+    let sort_doc = doc! { "pool": 1, "start_time": 1 };
+
+    match dataset {
+        "swap_history" => write_rows(db.swap_history_rows(query, sort_doc).await?, Vec::new()).await,
+        "depth_history" => write_rows(db.depth_history_rows(query, sort_doc).await?, Vec::new()).await,
+        "earnings" => write_rows(db.pool_earnings_rows(query, sort_doc).await?, Vec::new()).await,
+        "earnings_summary" => write_rows(db.earnings_summary_rows(query, sort_doc).await?, Vec::new()).await,
+        "rpmuh" => write_rows(db.rune_pool_history_rows(query, sort_doc).await?, Vec::new()).await,
+        _ => Err(format!("dataset must be one of: {:?}", DATASETS).into()),
+    }
+}
+
+/// Writes a whole dataset as `<out_dir>/<dataset>/pool=<pool>/month=<YYYY-MM>/part-0.parquet`,
+/// leaving out the pool level for datasets without a pool. Returns the number of files written.
+pub async fn export_partitioned(db: &Database, dataset: &str, out_dir: &Path) -> ExportResult<usize> {
+    let dataset_dir = out_dir.join(dataset);
+    let sort_doc = doc! { "pool": 1, "start_time": 1 };
+
+    match dataset {
+        "swap_history" => write_partitions(db.swap_history_rows(doc! {}, sort_doc).await?, &dataset_dir).await,
+        "depth_history" => write_partitions(db.depth_history_rows(doc! {}, sort_doc).await?, &dataset_dir).await,
+        "earnings" => write_partitions(db.pool_earnings_rows(doc! {}, sort_doc).await?, &dataset_dir).await,
+        "earnings_summary" => write_partitions(db.earnings_summary_rows(doc! {}, sort_doc).await?, &dataset_dir).await,
+        "rpmuh" => write_partitions(db.rune_pool_history_rows(doc! {}, sort_doc).await?, &dataset_dir).await,
+        _ => Err(format!("dataset must be one of: {:?}", DATASETS).into()),
+    }
+}
+
+/// Offline batch job, run with `rust-crypto-metrics export-parquet [out_dir] [dataset...]`
+pub async fn run_batch_export(db: &Database, out_dir: &str, datasets: &[String]) {
+    let datasets: Vec<&str> = if datasets.is_empty() {
+        DATASETS.to_vec()
+    } else {
+        datasets.iter().map(String::as_str).collect()
+    };
+
+    for dataset in datasets {
+        println!("Exporting {} to {}", dataset, out_dir);
+
+        match export_partitioned(db, dataset, Path::new(out_dir)).await {
+            Ok(files) => println!("Exported {} into {} parquet files", dataset, files),
+            Err(e) => eprintln!("Error exporting {}: {:?}", dataset, e),
+        }
+    }
+}
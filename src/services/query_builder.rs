@@ -0,0 +1,257 @@
+use mongodb::bson::{doc, Document};
+
+/// Validated options shared by the history query endpoints
+pub struct HistoryQuery {
+    pub start_time: Option<i64>,
+    pub end_time: Option<i64>,
+    pub pools: Vec<String>,
+    pub chain: Option<String>,
+    pub group_by_pool: bool,
+    pub page: u32,
+    pub limit: Option<u32>,
+    pub sort_doc: Document,
+    pub interval: Option<String>,
+    pub projection: Option<Document>,
+    pub filter: Document,
+    pub fill: Fill,
+    /// Adds the computed depth fields to every row, only used by the depth history
+    pub derived: bool,
+}
+
+/// How buckets without data are emitted in a time series
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fill {
+    /// Missing buckets are left out
+    None,
+    /// Missing buckets are emitted with every value set to 0
+    Zero,
+    /// Missing buckets are emitted with every value set to null
+    Null,
+    /// Missing buckets carry the values of the previous bucket forward
+    Previous,
+}
+
+const FILTER_OPERATORS: &[(&str, &str)] = &[
+    (">=", "$gte"),
+    ("<=", "$lte"),
+    ("!=", "$ne"),
+    (">", "$gt"),
+    ("<", "$lt"),
+    ("=", "$eq"),
+];
+
+// Builds the sort document from a comma separated list of fields, e.g. `pool,-total_volume_usd`.
+// A leading `-` sorts that field descending, every other field uses the requested order.
+pub fn build_sort(sort_by: Option<&str>, sort_order: i32, fields: &[&str]) -> Result<Document, String> {
+    let sort_by = match sort_by {
+        Some(value) if !value.trim().is_empty() => value,
+        _ => return Ok(doc! { "end_time": -1 }),
+    };
+
+    let mut sort_doc = doc! {};
+
+    for key in sort_by.split(',') {
+        let key = key.trim();
+        let (field, order) = match key.strip_prefix('-') {
+            Some(field) => (field, -1),
+            None => (key.strip_prefix('+').unwrap_or(key), sort_order),
+        };
+
+        if !fields.contains(&field) {
+            return Err(format!("sort_by field '{}' must be one of: {:?}", field, fields));
+        }
+
+        if sort_doc.contains_key(field) {
+            return Err(format!("sort_by field '{}' is listed more than once.", field));
+        }
+
+        sort_doc.insert(field, order);
+    }
+
+    Ok(sort_doc)
+}
+
+// Builds the projection for a comma separated list of fields, e.g. `pool,start_time,total_volume_usd`.
+// Returns None when every field should be returned.
+pub fn build_projection(fields: Option<&str>, allowed_fields: &[&str]) -> Result<Option<Document>, String> {
+    let fields = match fields {
+        Some(value) if !value.trim().is_empty() => value,
+        _ => return Ok(None),
+    };
+
+    let mut projection = doc! { "_id": 0 };
+
+    for field in fields.split(',').map(str::trim) {
+        if !allowed_fields.contains(&field) {
+            return Err(format!("fields entry '{}' must be one of: {:?}", field, allowed_fields));
+        }
+
+        projection.insert(field, 1);
+    }
+
+    Ok(Some(projection))
+}
+
+// Splits a comma separated pool list such as `BTC.BTC,ETH.ETH`
+pub fn parse_pools(pool: Option<&str>) -> Vec<String> {
+    pool.map(|value| {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|pool| !pool.is_empty())
+            .map(String::from)
+            .collect()
+    })
+    .unwrap_or_default()
+}
+
+// A chain filter matches every pool whose identifier starts with `<CHAIN>.`
+pub fn parse_chain(chain: Option<&str>) -> Result<Option<String>, String> {
+    match chain.map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(value) if value.chars().all(|c| c.is_ascii_alphanumeric()) => Ok(Some(value.to_ascii_uppercase())),
+        Some(value) => Err(format!("chain '{}' must only contain letters and digits.", value)),
+    }
+}
+
+pub fn parse_group_by(group_by: Option<&str>) -> Result<bool, String> {
+    match group_by {
+        None => Ok(false),
+        Some("pool") => Ok(true),
+        Some(value) => Err(format!("group_by '{}' must be one of: [\"pool\"]", value)),
+    }
+}
+
+pub fn parse_fill(fill: Option<&str>) -> Result<Fill, String> {
+    match fill {
+        None | Some("none") => Ok(Fill::None),
+        Some("zero") => Ok(Fill::Zero),
+        Some("null") => Ok(Fill::Null),
+        Some("previous") => Ok(Fill::Previous),
+        Some(_) => Err(format!("fill must be one of: {:?}", ["none", "zero", "null", "previous"])),
+    }
+}
+
+// Builds a match document from comma separated numeric conditions, e.g.
+// `total_volume_usd>1000000,average_slip<=20`. Every field except `pool` is numeric.
+pub fn build_filter(filter: Option<&str>, allowed_fields: &[&str]) -> Result<Document, String> {
+    let mut filter_doc = doc! {};
+
+    let filter = match filter {
+        Some(value) if !value.trim().is_empty() => value,
+        _ => return Ok(filter_doc),
+    };
+
+    let numeric_fields: Vec<&str> = allowed_fields.iter().copied().filter(|field| *field != "pool").collect();
+
+    for condition in filter.split(',').map(str::trim) {
+        let position = condition
+            .find(['<', '>', '=', '!'])
+            .ok_or_else(|| format!("filter '{}' must look like <field><operator><number>, e.g. start_time>1700000000.", condition))?;
+
+        let (field, rest) = condition.split_at(position);
+        let field = field.trim();
+
+        if !numeric_fields.contains(&field) {
+            return Err(format!("filter field '{}' must be one of: {:?}", field, numeric_fields));
+        }
+
+        let (operator, mongo_operator) = FILTER_OPERATORS
+            .iter()
+            .find(|(operator, _)| rest.starts_with(operator))
+            .ok_or_else(|| format!("filter '{}' must use one of: >=, <=, !=, >, <, =", condition))?;
+
+        // f64 parsing also accepts NaN and inf, which no stored value can be compared against
+        let value: f64 = rest[operator.len()..]
+            .trim()
+            .parse()
+            .ok()
+            .filter(|value: &f64| value.is_finite())
+            .ok_or_else(|| format!("filter '{}' must compare against a finite number.", condition))?;
+
+        match filter_doc.get_document_mut(field) {
+            Ok(conditions) if conditions.contains_key(*mongo_operator) => {
+                return Err(format!("filter field '{}' uses {} more than once.", field, operator));
+            }
+            Ok(conditions) => {
+                conditions.insert(*mongo_operator, value);
+            }
+            Err(_) => {
+                filter_doc.insert(field, doc! { *mongo_operator: value });
+            }
+        }
+    }
+
+    Ok(filter_doc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIELDS: &[&str] = &["pool", "start_time", "end_time", "total_fees", "average_slip"];
+
+    #[test]
+    fn build_sort_defaults_to_latest_first() {
+        assert_eq!(build_sort(None, 1, FIELDS).unwrap(), doc! { "end_time": -1 });
+        assert_eq!(build_sort(Some(" "), 1, FIELDS).unwrap(), doc! { "end_time": -1 });
+    }
+
+    #[test]
+    fn build_sort_keeps_the_field_order_and_prefixes() {
+        let sort = build_sort(Some("pool, -total_fees,+start_time"), -1, FIELDS).unwrap();
+        assert_eq!(sort, doc! { "pool": -1, "total_fees": -1, "start_time": -1 });
+
+        let sort = build_sort(Some("-average_slip,pool"), 1, FIELDS).unwrap();
+        assert_eq!(sort, doc! { "average_slip": -1, "pool": 1 });
+    }
+
+    #[test]
+    fn build_sort_rejects_unknown_and_repeated_fields() {
+        assert!(build_sort(Some("volume"), 1, FIELDS).is_err());
+        assert!(build_sort(Some("pool,-pool"), 1, FIELDS).is_err());
+    }
+
+    #[test]
+    fn build_filter_combines_conditions_per_field() {
+        let filter = build_filter(Some("total_fees>1000, total_fees<=5000,average_slip!=0"), FIELDS).unwrap();
+        assert_eq!(
+            filter,
+            doc! {
+                "total_fees": { "$gt": 1000.0, "$lte": 5000.0 },
+                "average_slip": { "$ne": 0.0 },
+            }
+        );
+    }
+
+    #[test]
+    fn build_filter_rejects_invalid_conditions() {
+        // pool is not numeric, so it cannot be filtered on
+        assert!(build_filter(Some("pool=1"), FIELDS).is_err());
+        assert!(build_filter(Some("volume>1"), FIELDS).is_err());
+        assert!(build_filter(Some("total_fees"), FIELDS).is_err());
+        assert!(build_filter(Some("total_fees>abc"), FIELDS).is_err());
+    }
+
+    #[test]
+    fn build_filter_rejects_non_finite_values() {
+        assert!(build_filter(Some("total_fees>NaN"), FIELDS).is_err());
+        assert!(build_filter(Some("total_fees<inf"), FIELDS).is_err());
+        assert!(build_filter(Some("total_fees>-infinity"), FIELDS).is_err());
+    }
+
+    #[test]
+    fn build_filter_rejects_a_repeated_operator() {
+        assert!(build_filter(Some("total_fees>1,total_fees>5"), FIELDS).is_err());
+    }
+
+    #[test]
+    fn parse_fill_accepts_the_documented_modes() {
+        assert_eq!(parse_fill(None).unwrap(), Fill::None);
+        assert_eq!(parse_fill(Some("none")).unwrap(), Fill::None);
+        assert_eq!(parse_fill(Some("zero")).unwrap(), Fill::Zero);
+        assert_eq!(parse_fill(Some("null")).unwrap(), Fill::Null);
+        assert_eq!(parse_fill(Some("previous")).unwrap(), Fill::Previous);
+        assert!(parse_fill(Some("linear")).is_err());
+    }
+}
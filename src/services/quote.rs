@@ -0,0 +1,238 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::models::depth_history_model::PoolDepthPriceHistory;
+use super::db::Database;
+
+pub const RUNE: &str = "THOR.RUNE";
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct QuoteLeg {
+    /// Pool the leg swaps through
+    #[schema(example = "BTC.BTC")]
+    pub pool: String,
+
+    #[schema(example = "BTC.BTC")]
+    pub from_asset: String,
+
+    #[schema(example = "THOR.RUNE")]
+    pub to_asset: String,
+
+    /// Time of the depth snapshot used (UNIX timestamp)
+    #[schema(example = 1666591200)]
+    pub snapshot_time: i64,
+
+    /// Amount swapped in, whole units
+    #[schema(example = 1.5)]
+    pub input: f64,
+
+    /// Amount received, whole units
+    #[schema(example = 12345.67)]
+    pub output: f64,
+
+    /// Liquidity fee kept by the pool, whole units of the output asset
+    #[schema(example = 12.34)]
+    pub liquidity_fee: f64,
+
+    /// Liquidity fee in USD
+    #[schema(example = 18.76)]
+    pub liquidity_fee_usd: f64,
+
+    /// Swap slip, input / (input + input side depth), in basis points
+    #[schema(example = 9.8)]
+    pub slip_bps: f64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Quote {
+    #[schema(example = "BTC.BTC")]
+    pub from_asset: String,
+
+    #[schema(example = "ETH.ETH")]
+    pub to_asset: String,
+
+    /// Amount swapped in, whole units
+    #[schema(example = 1.5)]
+    pub amount: f64,
+
+    /// Expected amount received, whole units
+    #[schema(example = 23.45)]
+    pub expected_output: f64,
+
+    /// Amount received at the spot price, without slip or fees
+    #[schema(example = 23.51)]
+    pub ideal_output: f64,
+
+    /// Sum of the liquidity fees of every leg in USD
+    #[schema(example = 37.52)]
+    pub liquidity_fee_usd: f64,
+
+    /// Shortfall of the expected output against the ideal output, in basis points
+    #[schema(example = 25.5)]
+    pub total_slip_bps: f64,
+
+    /// One leg for a swap to or from RUNE, two for a double swap through RUNE
+    pub legs: Vec<QuoteLeg>,
+}
+
+fn rune_price_usd(pool: &PoolDepthPriceHistory) -> f64 {
+    if pool.asset_price > 0.0 {
+        pool.asset_price_usd / pool.asset_price
+    } else {
+        0.0
+    }
+}
+
+// THORChain's CLP: for x in against depths X (in) and Y (out), the output is x*X*Y/(x+X)^2,
+// the liquidity fee x^2*Y/(x+X)^2 and the slip x/(x+X)
+fn swap_leg(pool: &PoolDepthPriceHistory, to_rune: bool, input: f64) -> QuoteLeg {
+    let (input_depth, output_depth) = if to_rune {
+        (pool.asset_depth, pool.rune_depth)
+    } else {
+        (pool.rune_depth, pool.asset_depth)
+    };
+
+    // Depths are stored in 1e8 base units
+    let x = input * 1e8;
+    let denominator = (x + input_depth).powi(2);
+    let output = x * input_depth * output_depth / denominator / 1e8;
+    let liquidity_fee = x * x * output_depth / denominator / 1e8;
+
+    let output_price_usd = if to_rune { rune_price_usd(pool) } else { pool.asset_price_usd };
+    let (from_asset, to_asset) = if to_rune {
+        (pool.pool.clone(), RUNE.to_string())
+    } else {
+        (RUNE.to_string(), pool.pool.clone())
+    };
+
+    QuoteLeg {
+        pool: pool.pool.clone(),
+        from_asset,
+        to_asset,
+        snapshot_time: pool.start_time,
+        input,
+        output,
+        liquidity_fee,
+        liquidity_fee_usd: liquidity_fee * output_price_usd,
+        slip_bps: x / (x + input_depth) * 10000.0,
+    }
+}
+
+fn ideal_leg(pool: &PoolDepthPriceHistory, to_rune: bool, input: f64) -> f64 {
+    if to_rune {
+        input * pool.rune_depth / pool.asset_depth
+    } else {
+        input * pool.asset_depth / pool.rune_depth
+    }
+}
+
+async fn snapshot(db: &Database, pool: &str, at: Option<i64>) -> Result<Option<PoolDepthPriceHistory>, mongodb::error::Error> {
+    Ok(db
+        .depth_snapshot_at(pool, at)
+        .await?
+        .filter(|snapshot| snapshot.asset_depth > 0.0 && snapshot.rune_depth > 0.0))
+}
+
+/// Quotes swapping `amount` of `from_asset` into `to_asset` against the depths at `at` or the latest ones.
+/// Returns `None` when a pool on the route has no depth history at that time.
+pub async fn get_quote(
+    db: &Database,
+    from_asset: &str,
+    to_asset: &str,
+    amount: f64,
+    at: Option<i64>,
+) -> Result<Option<Quote>, mongodb::error::Error> {
+    let mut legs = Vec::new();
+    let mut ideal_output = amount;
+
+    if from_asset != RUNE {
+        let Some(pool) = snapshot(db, from_asset, at).await? else {
+            return Ok(None);
+        };
+        ideal_output = ideal_leg(&pool, true, ideal_output);
+        legs.push(swap_leg(&pool, true, amount));
+    }
+
+    if to_asset != RUNE {
+        let Some(pool) = snapshot(db, to_asset, at).await? else {
+            return Ok(None);
+        };
+        let input = legs.last().map(|leg| leg.output).unwrap_or(amount);
+        ideal_output = ideal_leg(&pool, false, ideal_output);
+        legs.push(swap_leg(&pool, false, input));
+    }
+
+    let expected_output = legs.last().map(|leg| leg.output).unwrap_or(amount);
+
+    Ok(Some(Quote {
+        from_asset: from_asset.to_string(),
+        to_asset: to_asset.to_string(),
+        amount,
+        expected_output,
+        ideal_output,
+        liquidity_fee_usd: legs.iter().map(|leg| leg.liquidity_fee_usd).sum(),
+        total_slip_bps: if ideal_output > 0.0 { (1.0 - expected_output / ideal_output) * 10000.0 } else { 0.0 },
+        legs,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mongodb::bson::oid::ObjectId;
+
+    // 100 BTC against 1000 RUNE in 1e8 base units, so 1 BTC is 10 RUNE and 50 USD
+    fn pool() -> PoolDepthPriceHistory {
+        PoolDepthPriceHistory {
+            _id: ObjectId::new(),
+            pool: "BTC.BTC".to_string(),
+            asset_depth: 100e8,
+            asset_price: 10.0,
+            asset_price_usd: 50.0,
+            start_time: 1666591200,
+            end_time: 1666594800,
+            liquidity_units: 0.0,
+            luvi: 0.0,
+            members_count: 0,
+            rune_depth: 1000e8,
+            synth_supply: 0.0,
+            synth_units: 0.0,
+            units: 0.0,
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} is not {}", actual, expected);
+    }
+
+    #[test]
+    fn swap_leg_to_rune_follows_the_clp_formula() {
+        let leg = swap_leg(&pool(), true, 1.0);
+
+        assert_eq!((leg.from_asset.as_str(), leg.to_asset.as_str()), ("BTC.BTC", RUNE));
+        assert_close(leg.output, 1.0 * 100.0 * 1000.0 / (101.0 * 101.0));
+        assert_close(leg.liquidity_fee, 1000.0 / (101.0 * 101.0));
+        assert_close(leg.slip_bps, 10000.0 / 101.0);
+        // The fee is paid in RUNE, worth 5 USD each
+        assert_close(leg.liquidity_fee_usd, leg.liquidity_fee * 5.0);
+        // Output and fee together are what the pool gives up at the slipped price
+        assert_close(leg.output + leg.liquidity_fee, 1000.0 / 101.0);
+    }
+
+    #[test]
+    fn swap_leg_from_rune_swaps_the_depths() {
+        let leg = swap_leg(&pool(), false, 10.0);
+
+        assert_eq!((leg.from_asset.as_str(), leg.to_asset.as_str()), (RUNE, "BTC.BTC"));
+        assert_close(leg.output, 10.0 * 1000.0 * 100.0 / (1010.0 * 1010.0));
+        assert_close(leg.slip_bps, 10.0 / 1010.0 * 10000.0);
+        assert_close(leg.liquidity_fee_usd, leg.liquidity_fee * 50.0);
+    }
+
+    #[test]
+    fn ideal_leg_uses_the_spot_price() {
+        assert_close(ideal_leg(&pool(), true, 1.0), 10.0);
+        assert_close(ideal_leg(&pool(), false, 10.0), 1.0);
+        assert!(swap_leg(&pool(), true, 1.0).output < ideal_leg(&pool(), true, 1.0));
+    }
+}
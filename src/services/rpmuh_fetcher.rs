@@ -1,11 +1,12 @@
 use reqwest::Error;
 use serde::{Deserialize, Serialize};
 use crate::models::rpmuh_model::{RunePoolHistory, RunePoolHistoryRequest};
-use super::db::Database;
+use super::{db::Database, midgard_proxy::parse_number};
 use chrono::{DateTime, Utc};
 
 #[derive(Debug, Serialize, Deserialize)]
-struct RunePoolMeta {
+#[allow(non_snake_case)]
+pub struct RunePoolMeta {
     pub startTime: String,
     pub endTime: String,
     pub startUnits: String,
@@ -15,7 +16,8 @@ struct RunePoolMeta {
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-struct RunePoolInterval {
+#[allow(non_snake_case)]
+pub struct RunePoolInterval {
     pub startTime: String,
     pub endTime: String,
     pub count: String,
@@ -23,12 +25,48 @@ struct RunePoolInterval {
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-struct RunePoolHistoryApiResponse {
+pub struct RunePoolHistoryApiResponse {
     pub meta: RunePoolMeta,
     pub intervals: Vec<RunePoolInterval>,
 }
 
-pub async fn fetch_and_store_rune_pool_history(db: &Database, interval: &String, mut from: i64) -> Result<(), Error> {
+// Parses the whole response up front, so a malformed interval stores nothing of it
+pub fn parse_rune_pool_intervals(intervals: Vec<RunePoolInterval>) -> Result<Vec<RunePoolHistory>, String> {
+    let mut rows = Vec::new();
+
+    for interval_data in intervals {
+        let rune_pool_interval_request = RunePoolHistoryRequest {
+            start_time: parse_number(&interval_data.startTime, "startTime")?,
+            end_time: parse_number(&interval_data.endTime, "endTime")?,
+            units: parse_number(&interval_data.units, "units")?,
+            count: parse_number(&interval_data.count, "count")?,
+        };
+
+        rows.push(RunePoolHistory::try_from(rune_pool_interval_request).unwrap());
+    }
+
+    Ok(rows)
+}
+
+/// `live` rows are closed intervals from the ingestion cycle, only their inserts reach the live feed.
+/// Returns the number of rows stored, failures are logged.
+pub async fn store_rune_pool_intervals_in_db(rows: Vec<RunePoolHistory>, db: &Database, live: bool) -> usize {
+    let mut stored_count = 0;
+
+    for rune_pool_interval in rows {
+        match db.create_rpmuh(rune_pool_interval, live).await {
+            Ok(stored) => {
+                println!("Successfully stored: {:?}", stored.id);
+                stored_count += 1;
+            }
+            Err(e) => eprintln!("Error inserting document: {:?}", e),
+        }
+    }
+
+    stored_count
+}
+
+pub async fn fetch_and_store_rune_pool_history(db: &Database, interval: &String, mut from: i64, live: bool) -> Result<(), Error> {
     loop {
         let url = format!(
             "https://midgard.ninerealms.com/v2/history/runepool/?interval={}&from={}&count=400",
@@ -38,20 +76,17 @@ pub async fn fetch_and_store_rune_pool_history(db: &Database, interval: &String,
 
         let response: RunePoolHistoryApiResponse = reqwest::get(&url).await?.json().await?;
 
-        for interval_data in response.intervals {
-            let rune_pool_interval_request = RunePoolHistoryRequest {
-                start_time: interval_data.startTime.parse::<i64>().expect("Failed to parse startTime"),
-                end_time: interval_data.endTime.parse::<i64>().expect("Failed to parse endTime"),
-                units: interval_data.units.parse::<f64>().expect("Failed to parse units"),
-                count: interval_data.count.parse::<f64>().expect("Failed to parse count"),
-            };
-
-            let rune_pool_interval = RunePoolHistory::try_from(rune_pool_interval_request).unwrap();
-
-            match db.create_rpmuh(rune_pool_interval).await {
-                Ok(result) => println!("Successfully inserted: {:?}", result.inserted_id),
-                Err(e) => eprintln!("Error inserting document: {:?}", e),
+        match parse_rune_pool_intervals(response.intervals) {
+            Ok(mut rows) => {
+                // The hour in progress is left to the next cycle, so the feed and the anomaly scores
+                // only ever see closed intervals
+                if live {
+                    let now = Utc::now().timestamp();
+                    rows.retain(|row| row.end_time <= now);
+                }
+                store_rune_pool_intervals_in_db(rows, db, live).await;
             }
+            Err(e) => eprintln!("Error parsing RUNEPool intervals: {}", e),
         }
 
         let end_time: i64 = response.meta.endTime.parse().unwrap();
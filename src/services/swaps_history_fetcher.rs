@@ -5,158 +5,185 @@ use chrono::{DateTime, Utc};
 
 use crate::models::swap_history_model::{PoolSwapHistory, PoolSwapHistoryRequest};
 
-use super::db::Database;
+use super::{anomaly_detector, db::Database, midgard_proxy::parse_number};
 
 #[derive(Debug, Serialize, Deserialize)]
-struct SwapsMeta {
-    startTime: String,
-    endTime: String,
-    toAssetCount: String,
-    toRuneCount: String,
-    toTradeCount: String,
-    fromTradeCount: String,
-    synthMintCount: String,
-    synthRedeemCount: String,
-    totalCount: String,
-    toAssetVolume: String,
-    toRuneVolume: String,
-    toTradeVolume: String,
-    fromTradeVolume: String,
-    synthMintVolume: String,
-    synthRedeemVolume: String,
-    totalVolume: String,
-    toAssetVolumeUSD: String,
-    toRuneVolumeUSD: String,
-    toTradeVolumeUSD: String,
-    fromTradeVolumeUSD: String,
-    synthMintVolumeUSD: String,
-    synthRedeemVolumeUSD: String,
-    totalVolumeUSD: String,
-    toAssetFees: String,
-    toRuneFees: String,
-    toTradeFees: String,
-    fromTradeFees: String,
-    synthMintFees: String,
-    synthRedeemFees: String,
-    totalFees: String,
-    toAssetAverageSlip: String,
-    toRuneAverageSlip: String,
-    toTradeAverageSlip: String,
-    fromTradeAverageSlip: String,
-    synthMintAverageSlip: String,
-    synthRedeemAverageSlip: String,
-    averageSlip: String,
-    runePriceUSD: String,
+#[allow(non_snake_case)]
+pub struct SwapsMeta {
+    pub startTime: String,
+    pub endTime: String,
+    pub toAssetCount: String,
+    pub toRuneCount: String,
+    pub toTradeCount: String,
+    pub fromTradeCount: String,
+    pub synthMintCount: String,
+    pub synthRedeemCount: String,
+    pub totalCount: String,
+    pub toAssetVolume: String,
+    pub toRuneVolume: String,
+    pub toTradeVolume: String,
+    pub fromTradeVolume: String,
+    pub synthMintVolume: String,
+    pub synthRedeemVolume: String,
+    pub totalVolume: String,
+    pub toAssetVolumeUSD: String,
+    pub toRuneVolumeUSD: String,
+    pub toTradeVolumeUSD: String,
+    pub fromTradeVolumeUSD: String,
+    pub synthMintVolumeUSD: String,
+    pub synthRedeemVolumeUSD: String,
+    pub totalVolumeUSD: String,
+    pub toAssetFees: String,
+    pub toRuneFees: String,
+    pub toTradeFees: String,
+    pub fromTradeFees: String,
+    pub synthMintFees: String,
+    pub synthRedeemFees: String,
+    pub totalFees: String,
+    pub toAssetAverageSlip: String,
+    pub toRuneAverageSlip: String,
+    pub toTradeAverageSlip: String,
+    pub fromTradeAverageSlip: String,
+    pub synthMintAverageSlip: String,
+    pub synthRedeemAverageSlip: String,
+    pub averageSlip: String,
+    pub runePriceUSD: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-struct SwapsInterval {
-    startTime: String,
-    endTime: String,
-    toAssetCount: String,
-    toRuneCount: String,
-    toTradeCount: String,
-    fromTradeCount: String,
-    synthMintCount: String,
-    synthRedeemCount: String,
-    totalCount: String,
-    toAssetVolume: String,
-    toRuneVolume: String,
-    toTradeVolume: String,
-    fromTradeVolume: String,
-    synthMintVolume: String,
-    synthRedeemVolume: String,
-    totalVolume: String,
-    toAssetVolumeUSD: String,
-    toRuneVolumeUSD: String,
-    toTradeVolumeUSD: String,
-    fromTradeVolumeUSD: String,
-    synthMintVolumeUSD: String,
-    synthRedeemVolumeUSD: String,
-    totalVolumeUSD: String,
-    toAssetFees: String,
-    toRuneFees: String,
-    toTradeFees: String,
-    fromTradeFees: String,
-    synthMintFees: String,
-    synthRedeemFees: String,
-    totalFees: String,
-    toAssetAverageSlip: String,
-    toRuneAverageSlip: String,
-    toTradeAverageSlip: String,
-    fromTradeAverageSlip: String,
-    synthMintAverageSlip: String,
-    synthRedeemAverageSlip: String,
-    averageSlip: String,
-    runePriceUSD: String,
+#[allow(non_snake_case)]
+pub struct SwapsInterval {
+    pub startTime: String,
+    pub endTime: String,
+    pub toAssetCount: String,
+    pub toRuneCount: String,
+    pub toTradeCount: String,
+    pub fromTradeCount: String,
+    pub synthMintCount: String,
+    pub synthRedeemCount: String,
+    pub totalCount: String,
+    pub toAssetVolume: String,
+    pub toRuneVolume: String,
+    pub toTradeVolume: String,
+    pub fromTradeVolume: String,
+    pub synthMintVolume: String,
+    pub synthRedeemVolume: String,
+    pub totalVolume: String,
+    pub toAssetVolumeUSD: String,
+    pub toRuneVolumeUSD: String,
+    pub toTradeVolumeUSD: String,
+    pub fromTradeVolumeUSD: String,
+    pub synthMintVolumeUSD: String,
+    pub synthRedeemVolumeUSD: String,
+    pub totalVolumeUSD: String,
+    pub toAssetFees: String,
+    pub toRuneFees: String,
+    pub toTradeFees: String,
+    pub fromTradeFees: String,
+    pub synthMintFees: String,
+    pub synthRedeemFees: String,
+    pub totalFees: String,
+    pub toAssetAverageSlip: String,
+    pub toRuneAverageSlip: String,
+    pub toTradeAverageSlip: String,
+    pub fromTradeAverageSlip: String,
+    pub synthMintAverageSlip: String,
+    pub synthRedeemAverageSlip: String,
+    pub averageSlip: String,
+    pub runePriceUSD: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-struct SwapsApiResponse {
-    meta: SwapsMeta,
-    intervals: Vec<SwapsInterval>,
+pub struct SwapsApiResponse {
+    pub meta: SwapsMeta,
+    pub intervals: Vec<SwapsInterval>,
 }
 
-async fn store_swaps_intervals_in_db(intervals: Vec<SwapsInterval>, pool: &String, db: &Database) {
+// Parses the whole response up front, so a malformed interval stores nothing of it
+pub fn parse_swaps_intervals(intervals: Vec<SwapsInterval>, pool: &str) -> Result<Vec<PoolSwapHistory>, String> {
+    let mut rows = Vec::new();
+
     for interval in intervals {
         let new_swap_history = PoolSwapHistory::try_from(PoolSwapHistoryRequest {
             pool: pool.to_string(),
-            start_time: interval.startTime.parse::<i64>().expect("Failed to parse startTime"),
-            end_time: interval.endTime.parse::<i64>().expect("Failed to parse endTime"),
+            start_time: parse_number(&interval.startTime, "startTime")?,
+            end_time: parse_number(&interval.endTime, "endTime")?,
             
-            to_asset_count: interval.toAssetCount.parse::<i64>().expect("Failed to parse toAssetCount"),
-            to_rune_count: interval.toRuneCount.parse::<i64>().expect("Failed to parse toRuneCount"),
-            to_trade_count: interval.toTradeCount.parse::<i64>().expect("Failed to parse toTradeCount"),
-            from_trade_count: interval.fromTradeCount.parse::<i64>().expect("Failed to parse fromTradeCount"),
-            synth_mint_count: interval.synthMintCount.parse::<i64>().expect("Failed to parse synthMintCount"),
-            synth_redeem_count: interval.synthRedeemCount.parse::<i64>().expect("Failed to parse synthRedeemCount"),
-            total_count: interval.totalCount.parse::<i64>().expect("Failed to parse totalCount"),
+            to_asset_count: parse_number(&interval.toAssetCount, "toAssetCount")?,
+            to_rune_count: parse_number(&interval.toRuneCount, "toRuneCount")?,
+            to_trade_count: parse_number(&interval.toTradeCount, "toTradeCount")?,
+            from_trade_count: parse_number(&interval.fromTradeCount, "fromTradeCount")?,
+            synth_mint_count: parse_number(&interval.synthMintCount, "synthMintCount")?,
+            synth_redeem_count: parse_number(&interval.synthRedeemCount, "synthRedeemCount")?,
+            total_count: parse_number(&interval.totalCount, "totalCount")?,
             
-            to_asset_volume: interval.toAssetVolume.parse::<f64>().expect("Failed to parse toAssetVolume"),
-            to_rune_volume: interval.toRuneVolume.parse::<f64>().expect("Failed to parse toRuneVolume"),
-            to_trade_volume: interval.toTradeVolume.parse::<f64>().expect("Failed to parse toTradeVolume"),
-            from_trade_volume: interval.fromTradeVolume.parse::<f64>().expect("Failed to parse fromTradeVolume"),
-            synth_mint_volume: interval.synthMintVolume.parse::<f64>().expect("Failed to parse synthMintVolume"),
-            synth_redeem_volume: interval.synthRedeemVolume.parse::<f64>().expect("Failed to parse synthRedeemVolume"),
-            total_volume: interval.totalVolume.parse::<f64>().expect("Failed to parse totalVolume"),
+            to_asset_volume: parse_number(&interval.toAssetVolume, "toAssetVolume")?,
+            to_rune_volume: parse_number(&interval.toRuneVolume, "toRuneVolume")?,
+            to_trade_volume: parse_number(&interval.toTradeVolume, "toTradeVolume")?,
+            from_trade_volume: parse_number(&interval.fromTradeVolume, "fromTradeVolume")?,
+            synth_mint_volume: parse_number(&interval.synthMintVolume, "synthMintVolume")?,
+            synth_redeem_volume: parse_number(&interval.synthRedeemVolume, "synthRedeemVolume")?,
+            total_volume: parse_number(&interval.totalVolume, "totalVolume")?,
             
-            to_asset_volume_usd: interval.toAssetVolumeUSD.parse::<f64>().expect("Failed to parse toAssetVolumeUSD"),
-            to_rune_volume_usd: interval.toRuneVolumeUSD.parse::<f64>().expect("Failed to parse toRuneVolumeUSD"),
-            to_trade_volume_usd: interval.toTradeVolumeUSD.parse::<f64>().expect("Failed to parse toTradeVolumeUSD"),
-            from_trade_volume_usd: interval.fromTradeVolumeUSD.parse::<f64>().expect("Failed to parse fromTradeVolumeUSD"),
-            synth_mint_volume_usd: interval.synthMintVolumeUSD.parse::<f64>().expect("Failed to parse synthMintVolumeUSD"),
-            synth_redeem_volume_usd: interval.synthRedeemVolumeUSD.parse::<f64>().expect("Failed to parse synthRedeemVolumeUSD"),
-            total_volume_usd: interval.totalVolumeUSD.parse::<f64>().expect("Failed to parse totalVolumeUSD"),
+            to_asset_volume_usd: parse_number(&interval.toAssetVolumeUSD, "toAssetVolumeUSD")?,
+            to_rune_volume_usd: parse_number(&interval.toRuneVolumeUSD, "toRuneVolumeUSD")?,
+            to_trade_volume_usd: parse_number(&interval.toTradeVolumeUSD, "toTradeVolumeUSD")?,
+            from_trade_volume_usd: parse_number(&interval.fromTradeVolumeUSD, "fromTradeVolumeUSD")?,
+            synth_mint_volume_usd: parse_number(&interval.synthMintVolumeUSD, "synthMintVolumeUSD")?,
+            synth_redeem_volume_usd: parse_number(&interval.synthRedeemVolumeUSD, "synthRedeemVolumeUSD")?,
+            total_volume_usd: parse_number(&interval.totalVolumeUSD, "totalVolumeUSD")?,
             
-            to_asset_fees: interval.toAssetFees.parse::<f64>().expect("Failed to parse toAssetFees"),
-            to_rune_fees: interval.toRuneFees.parse::<f64>().expect("Failed to parse toRuneFees"),
-            to_trade_fees: interval.toTradeFees.parse::<f64>().expect("Failed to parse toTradeFees"),
-            from_trade_fees: interval.fromTradeFees.parse::<f64>().expect("Failed to parse fromTradeFees"),
-            synth_mint_fees: interval.synthMintFees.parse::<f64>().expect("Failed to parse synthMintFees"),
-            synth_redeem_fees: interval.synthRedeemFees.parse::<f64>().expect("Failed to parse synthRedeemFees"),
-            total_fees: interval.totalFees.parse::<f64>().expect("Failed to parse totalFees"),
+            to_asset_fees: parse_number(&interval.toAssetFees, "toAssetFees")?,
+            to_rune_fees: parse_number(&interval.toRuneFees, "toRuneFees")?,
+            to_trade_fees: parse_number(&interval.toTradeFees, "toTradeFees")?,
+            from_trade_fees: parse_number(&interval.fromTradeFees, "fromTradeFees")?,
+            synth_mint_fees: parse_number(&interval.synthMintFees, "synthMintFees")?,
+            synth_redeem_fees: parse_number(&interval.synthRedeemFees, "synthRedeemFees")?,
+            total_fees: parse_number(&interval.totalFees, "totalFees")?,
             
-            to_asset_average_slip: interval.toAssetAverageSlip.parse::<f64>().expect("Failed to parse toAssetAverageSlip"),
-            to_rune_average_slip: interval.toRuneAverageSlip.parse::<f64>().expect("Failed to parse toRuneAverageSlip"),
-            to_trade_average_slip: interval.toTradeAverageSlip.parse::<f64>().expect("Failed to parse toTradeAverageSlip"),
-            from_trade_average_slip: interval.fromTradeAverageSlip.parse::<f64>().expect("Failed to parse fromTradeAverageSlip"),
-            synth_mint_average_slip: interval.synthMintAverageSlip.parse::<f64>().expect("Failed to parse synthMintAverageSlip"),
-            synth_redeem_average_slip: interval.synthRedeemAverageSlip.parse::<f64>().expect("Failed to parse synthRedeemAverageSlip"),
-            average_slip: interval.averageSlip.parse::<f64>().expect("Failed to parse averageSlip"),
+            to_asset_average_slip: parse_number(&interval.toAssetAverageSlip, "toAssetAverageSlip")?,
+            to_rune_average_slip: parse_number(&interval.toRuneAverageSlip, "toRuneAverageSlip")?,
+            to_trade_average_slip: parse_number(&interval.toTradeAverageSlip, "toTradeAverageSlip")?,
+            from_trade_average_slip: parse_number(&interval.fromTradeAverageSlip, "fromTradeAverageSlip")?,
+            synth_mint_average_slip: parse_number(&interval.synthMintAverageSlip, "synthMintAverageSlip")?,
+            synth_redeem_average_slip: parse_number(&interval.synthRedeemAverageSlip, "synthRedeemAverageSlip")?,
+            average_slip: parse_number(&interval.averageSlip, "averageSlip")?,
             
-            rune_price_usd: interval.runePriceUSD.parse::<f64>().expect("Failed to parse runePriceUSD"),
+            rune_price_usd: parse_number(&interval.runePriceUSD, "runePriceUSD")?,
         })
         .unwrap();
-        
 
-        match db.create_swap_history(new_swap_history).await {
-            Ok(result) => println!("Successfully inserted: {:?}", result.inserted_id),
+        rows.push(new_swap_history);
+    }
+
+    Ok(rows)
+}
+
+/// `live` rows are closed intervals from the ingestion cycle, only their inserts reach the live feed and are scored for anomalies.
+/// Returns the number of rows stored, failures are logged.
+pub async fn store_swaps_intervals_in_db(rows: Vec<PoolSwapHistory>, pool: &str, db: &Database, live: bool) -> usize {
+    let mut stored_count = 0;
+
+    for new_swap_history in rows {
+        let (start_time, end_time) = (new_swap_history.start_time, new_swap_history.end_time);
+        let values = anomaly_detector::metric_values(anomaly_detector::SWAP_METRICS, &new_swap_history);
+
+        match db.create_swap_history(new_swap_history, live).await {
+            Ok(stored) => {
+                println!("Successfully stored: {:?}", stored.id);
+                stored_count += 1;
+                if live && stored.inserted {
+                    anomaly_detector::score_interval(db, "swaps", pool, start_time, end_time, &values).await;
+                }
+            }
             Err(e) => eprintln!("Error inserting document: {:?}", e),
         }
     }
+
+    stored_count
 }
 
-pub async fn fetch_and_store_swaps_history(db: &Database, pool: &String, interval: &String, start_time: i64) -> Result<(), Error> {
+pub async fn fetch_and_store_swaps_history(db: &Database, pool: &String, interval: &String, start_time: i64, live: bool) -> Result<(), Error> {
     let mut current_time = start_time;
 
     loop {
@@ -175,7 +202,18 @@ pub async fn fetch_and_store_swaps_history(db: &Database, pool: &String, interva
 
         let response = reqwest::get(&url).await?.json::<SwapsApiResponse>().await?;
 
-        store_swaps_intervals_in_db(response.intervals, &pool, db).await;
+        match parse_swaps_intervals(response.intervals, pool) {
+            Ok(mut rows) => {
+                // The hour in progress is left to the next cycle, so the feed and the anomaly scores
+                // only ever see closed intervals
+                if live {
+                    let now = Utc::now().timestamp();
+                    rows.retain(|row| row.end_time <= now);
+                }
+                store_swaps_intervals_in_db(rows, pool, db, live).await;
+            }
+            Err(e) => eprintln!("Error parsing swap intervals: {}", e),
+        }
         let end_time: i64 = response.meta.endTime.parse().unwrap();
 
         let current_utc: DateTime<Utc> = Utc::now();